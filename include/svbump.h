@@ -0,0 +1,47 @@
+#ifndef SVBUMP_H
+#define SVBUMP_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Read the version at `selector` in `file`.
+//
+// # Safety
+//
+// `file` and `selector` must be NULL or point to NUL-terminated strings.
+char *svbump_read(const char *file, const char *selector);
+
+// Bump the version at `selector` in `file` by `level` (a level such as
+// "minor", or an explicit version) and write the file, returning the new
+// version.
+//
+// # Safety
+//
+// `file`, `level` and `selector` must be NULL or point to NUL-terminated
+// strings.
+char *svbump_write(const char *file, const char *level, const char *selector);
+
+// The version `svbump_write` would write, leaving the file as is.
+//
+// # Safety
+//
+// `file`, `level` and `selector` must be NULL or point to NUL-terminated
+// strings.
+char *svbump_preview(const char *file, const char *level, const char *selector);
+
+// Why the last call on this thread returned NULL, or NULL if it succeeded.
+// The message belongs to svbump and lasts until the next call.
+const char *svbump_last_error(void);
+
+// Release a string returned by svbump.
+//
+// # Safety
+//
+// `value` must be NULL or a string returned by svbump, freed only once.
+void svbump_free(char *value);
+
+#endif  /* SVBUMP_H */
@@ -0,0 +1,104 @@
+//! Copies of files taken before they are rewritten
+
+use anyhow::{Context, Result};
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Options for backing up files before writing them
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct BackupOptions {
+    /// Copy each file to `<file><SUFFIX>` before writing it
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ".bak",
+        value_name = "SUFFIX"
+    )]
+    pub backup: Option<String>,
+
+    /// Put backups in this directory, under each file's relative path,
+    /// instead of next to the file
+    #[arg(long, value_name = "DIR")]
+    pub backup_dir: Option<PathBuf>,
+}
+
+impl BackupOptions {
+    fn enabled(&self) -> bool {
+        self.backup.is_some() || self.backup_dir.is_some()
+    }
+
+    /// Where the backup of `path` goes
+    fn backup_path(&self, path: &Path) -> PathBuf {
+        let suffix = self.backup.as_deref().unwrap_or(".bak");
+        let target = match &self.backup_dir {
+            // Relative paths are mirrored so same-named files don't collide
+            Some(dir)
+                if path
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_))) =>
+            {
+                dir.join(path)
+            }
+            Some(dir) => dir.join(path.file_name().unwrap_or(path.as_os_str())),
+            None => path.to_path_buf(),
+        };
+        let mut name = OsString::from(target.as_os_str());
+        name.push(suffix);
+        name.into()
+    }
+
+    /// Copy `path` to its backup if backups were asked for, returning where
+    pub fn save(&self, path: &Path) -> Result<Option<PathBuf>> {
+        if !self.enabled() {
+            return Ok(None);
+        }
+        let backup = self.backup_path(path);
+        if let Some(dir) = backup.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        fs::copy(path, &backup).with_context(|| {
+            format!(
+                "Failed to back up {} to {}",
+                path.display(),
+                backup.display()
+            )
+        })?;
+        Ok(Some(backup))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_paths() {
+        let next_to = BackupOptions {
+            backup: Some(".orig".to_string()),
+            backup_dir: None,
+        };
+        assert_eq!(
+            next_to.backup_path(Path::new("chart/Chart.yaml")),
+            Path::new("chart/Chart.yaml.orig")
+        );
+
+        let in_dir = BackupOptions {
+            backup: None,
+            backup_dir: Some("backups".into()),
+        };
+        assert_eq!(
+            in_dir.backup_path(Path::new("chart/Chart.yaml")),
+            Path::new("backups/chart/Chart.yaml.bak")
+        );
+        assert_eq!(
+            in_dir.backup_path(Path::new("../Chart.yaml")),
+            Path::new("backups/Chart.yaml.bak")
+        );
+        assert!(!BackupOptions::default().enabled());
+    }
+}
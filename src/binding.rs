@@ -0,0 +1,145 @@
+//! Locating and replacing the string bound at a path in the formats read by
+//! a scanner of bindings: Nix, HCL, CUE and Dhall
+
+use anyhow::{Context, Result};
+use std::ops::Range;
+
+/// A value bound at `path`, with the range of its contents when it is a
+/// plain double-quoted string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub path: Vec<String>,
+    pub value: Option<Range<usize>>,
+}
+
+/// Byte range of the string bound at `path` in the bindings of `content`,
+/// which must be bound exactly once, to a double-quoted string for which
+/// `is_interpolated` is false
+pub fn locate(
+    content: &str,
+    path: &[String],
+    bindings: fn(&str) -> Result<Vec<Binding>>,
+    is_interpolated: fn(&str) -> bool,
+) -> Result<Option<Range<usize>>> {
+    let found: Vec<_> = bindings(content)?
+        .into_iter()
+        .filter(|binding| binding.path == path)
+        .collect();
+    let name = path.join(".");
+    match found.as_slice() {
+        [] => Ok(None),
+        [Binding { value: None, .. }] => anyhow::bail!("{} isn't set to a plain string", name),
+        [Binding {
+            value: Some(value), ..
+        }] if is_interpolated(&content[value.clone()]) => anyhow::bail!(
+            "{} is interpolated ({}), so it can't be bumped in place",
+            name,
+            &content[value.clone()]
+        ),
+        [Binding {
+            value: Some(value), ..
+        }] => Ok(Some(value.clone())),
+        _ => anyhow::bail!(
+            "{} is set {} times; use --type regex to choose one",
+            name,
+            found.len()
+        ),
+    }
+}
+
+/// Check that replacing the string at `path` turned `old` into `new` without
+/// changing anything else about its structure, by scanning `new` again
+pub fn check_edit(
+    old: &str,
+    new: &str,
+    path: &[String],
+    version: &str,
+    bindings: fn(&str) -> Result<Vec<Binding>>,
+    is_interpolated: fn(&str) -> bool,
+) -> Result<()> {
+    let paths = |content: &str| -> Result<Vec<Vec<String>>> {
+        Ok(bindings(content)?
+            .into_iter()
+            .map(|binding| binding.path)
+            .collect())
+    };
+    let invalid = || {
+        format!(
+            "Setting {} to {} would break the file",
+            path.join("."),
+            version
+        )
+    };
+    if paths(new).with_context(invalid)? != paths(old)? {
+        anyhow::bail!(invalid());
+    }
+    match locate(new, path, bindings, is_interpolated).with_context(invalid)? {
+        Some(range) if new[range.clone()] == *version => Ok(()),
+        _ => anyhow::bail!(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `name = "value"` binding on each line
+    fn lines(content: &str) -> Result<Vec<Binding>> {
+        let mut bindings = Vec::new();
+        let mut start = 0;
+        for line in content.split_inclusive('\n') {
+            let (name, value) = line
+                .trim_end()
+                .split_once(" = ")
+                .context("Expected name = value")?;
+            let offset = start + name.len() + 3;
+            let value = (value.len() >= 2
+                && value.starts_with('"')
+                && value.ends_with('"')
+                && value.matches('"').count() == 2)
+                .then(|| offset + 1..offset + value.len() - 1);
+            bindings.push(Binding {
+                path: vec![name.to_string()],
+                value,
+            });
+            start += line.len();
+        }
+        Ok(bindings)
+    }
+
+    fn is_interpolated(value: &str) -> bool {
+        value.contains("${")
+    }
+
+    #[test]
+    fn test_locate() -> Result<()> {
+        let content =
+            "version = \"1.2.3\"\nname = app\nsrc = \"${name}-1\"\nx = \"1\"\nx = \"2\"\n";
+        let locate = |name: &str| {
+            locate(content, &[name.to_string()], lines, is_interpolated)
+                .map(|range| range.map(|range| &content[range]))
+        };
+        assert_eq!(locate("version")?, Some("1.2.3"));
+        assert_eq!(locate("missing")?, None);
+        let err = |name: &str| locate(name).unwrap_err().to_string();
+        assert_eq!(err("name"), "name isn't set to a plain string");
+        assert!(err("src").contains("is interpolated"));
+        assert!(err("x").contains("set 2 times"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_edit() -> Result<()> {
+        let old = "version = \"1.2.3\"\nname = \"app\"\n";
+        let path = vec!["version".to_string()];
+        let check = |version: &str| {
+            let new = old.replace("1.2.3", version);
+            check_edit(old, &new, &path, version, lines, is_interpolated)
+        };
+        check("1.3.0")?;
+        assert!(check("1.3.0\"\nx = \"y").is_err());
+        assert!(check("1.3\"").is_err());
+        assert!(check("${x}").is_err());
+        Ok(())
+    }
+}
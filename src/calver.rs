@@ -0,0 +1,161 @@
+//! Calendar versions such as `2024.5.0`, described by a format of calver.org
+//! tokens (`YYYY.MM.MICRO`, `YY.0M.0D`, ...)
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use regex::Regex;
+
+/// Used when `--calver-format` is not given
+pub const DEFAULT_FORMAT: &str = "YYYY.MM.MICRO";
+
+/// Tokens in matching order, so `YYYY` is tried before `YY`
+const TOKENS: [&str; 10] = [
+    "YYYY", "YY", "0Y", "MM", "0M", "WW", "0W", "DD", "0D", "MICRO",
+];
+
+enum Segment<'a> {
+    Literal(&'a str),
+    Token(&'static str),
+}
+
+fn segments(format: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        match TOKENS.iter().find(|token| rest.starts_with(*token)) {
+            Some(token) => {
+                segments.push(Segment::Token(token));
+                rest = &rest[token.len()..];
+            }
+            None => {
+                segments.push(Segment::Literal(&rest[..c.len_utf8()]));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    segments
+}
+
+/// Pattern for the values a token can take on any date
+fn token_pattern(token: &str) -> &'static str {
+    match token {
+        "YYYY" => r"\d{4}",
+        "YY" => r"[1-9]\d*|0",
+        "0Y" => r"\d{2}",
+        "MM" => r"[1-9]|1[0-2]",
+        "0M" => r"0[1-9]|1[0-2]",
+        "WW" => r"[1-9]|[1-4]\d|5[0-3]",
+        "0W" => r"0[1-9]|[1-4]\d|5[0-3]",
+        "DD" => r"[1-9]|[12]\d|3[01]",
+        "0D" => r"0[1-9]|[12]\d|3[01]",
+        "MICRO" => r"0|[1-9]\d*",
+        _ => unreachable!("not a token: {}", token),
+    }
+}
+
+/// Check that `version` is laid out as `format`, with each token holding a
+/// value it could take
+pub fn validate(version: &str, format: &str) -> Result<()> {
+    let mut pattern = String::from("^");
+    for segment in segments(format) {
+        match segment {
+            Segment::Literal(text) => pattern.push_str(&regex::escape(text)),
+            Segment::Token(token) => pattern.push_str(&format!("(?:{})", token_pattern(token))),
+        }
+    }
+    pattern.push('$');
+    if !Regex::new(&pattern)?.is_match(version) {
+        anyhow::bail!(
+            "{} is not a calendar version in the format {}",
+            version,
+            format
+        );
+    }
+    Ok(())
+}
+
+/// The value of a date token on `today`
+fn date_part(token: &str, today: NaiveDate) -> String {
+    let short_year = today.year() - 2000;
+    let week = today.iso_week().week();
+    match token {
+        "YYYY" => today.year().to_string(),
+        "YY" => short_year.to_string(),
+        "0Y" => format!("{:02}", short_year),
+        "MM" => today.month().to_string(),
+        "0M" => format!("{:02}", today.month()),
+        "WW" => week.to_string(),
+        "0W" => format!("{:02}", week),
+        "DD" => today.day().to_string(),
+        "0D" => format!("{:02}", today.day()),
+        _ => unreachable!("not a date token: {}", token),
+    }
+}
+
+/// The release after `current` on `today`: the date tokens of `format` are
+/// filled in from today, and `MICRO` counts up from the current version when
+/// it is from the same period, or starts again at 0
+pub fn next(current: &str, format: &str, today: NaiveDate) -> Result<String> {
+    let segments = segments(format);
+    if !segments
+        .iter()
+        .any(|segment| matches!(segment, Segment::Token(token) if *token != "MICRO"))
+    {
+        anyhow::bail!("CalVer format {} has no date token", format);
+    }
+
+    let mut pattern = String::from("^");
+    for segment in &segments {
+        match segment {
+            Segment::Literal(text) => pattern.push_str(&regex::escape(text)),
+            Segment::Token("MICRO") => pattern.push_str(r"(\d+)"),
+            Segment::Token(token) => pattern.push_str(&regex::escape(&date_part(token, today))),
+        }
+    }
+    pattern.push('$');
+    let micro = match Regex::new(&pattern)?.captures(current) {
+        Some(captures) => match captures.get(1) {
+            Some(micro) => micro.as_str().parse::<u64>()? + 1,
+            None => 0,
+        },
+        None => 0,
+    };
+
+    Ok(segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Literal(text) => text.to_string(),
+            Segment::Token("MICRO") => micro.to_string(),
+            Segment::Token(token) => date_part(token, today),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next() -> Result<()> {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        assert_eq!(next("2024.4.3", DEFAULT_FORMAT, today)?, "2024.5.0");
+        assert_eq!(next("2024.5.0", DEFAULT_FORMAT, today)?, "2024.5.1");
+        assert_eq!(next("1.2.3", DEFAULT_FORMAT, today)?, "2024.5.0");
+        assert_eq!(next("24.05.9", "YY.0M.MICRO", today)?, "24.05.10");
+        assert_eq!(next("2024.05.01", "YYYY.0M.0D", today)?, "2024.05.10");
+        assert_eq!(next("v24w18-2", "v0Yw0W-MICRO", today)?, "v24w19-0");
+        assert!(next("1.0.0", "MAJOR.MICRO", today).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("2024.5.0", DEFAULT_FORMAT).is_ok());
+        assert!(validate("24.05.10", "YY.0M.MICRO").is_ok());
+        assert!(validate("v24w19-0", "v0Yw0W-MICRO").is_ok());
+        assert!(validate("2024.05.0", DEFAULT_FORMAT).is_err());
+        assert!(validate("2024.13.0", DEFAULT_FORMAT).is_err());
+        assert!(validate("1.2.3", DEFAULT_FORMAT).is_err());
+        assert!(validate("2024.5.01", DEFAULT_FORMAT).is_err());
+    }
+}
@@ -0,0 +1,97 @@
+//! Releasing the unreleased section of a Keep a Changelog file
+
+use anyhow::Result;
+use chrono::NaiveDate;
+
+/// Move the entries under `## [Unreleased]` into a new `## [version] - date`
+/// section below it, leaving the unreleased section empty
+pub fn release(contents: &str, version: &str, date: NaiveDate) -> Result<String> {
+    let lines: Vec<&str> = contents.split_inclusive('\n').collect();
+    let Some(start) = lines.iter().position(|line| is_unreleased(line)) else {
+        anyhow::bail!("No `## [Unreleased]` section found");
+    };
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| line.starts_with("## ") || is_link_reference(line))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+
+    let entries = lines[start + 1..end].concat();
+    let entries = entries.trim_matches(['\r', '\n']);
+    let newline = if contents.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+
+    let mut out = lines[..=start].concat();
+    if !out.ends_with('\n') {
+        out.push_str(newline);
+    }
+    out.push_str(newline);
+    out.push_str(&format!(
+        "## [{}] - {}{}",
+        version,
+        date.format("%Y-%m-%d"),
+        newline
+    ));
+    if !entries.trim().is_empty() {
+        out.push_str(newline);
+        out.push_str(entries.trim_end());
+        out.push_str(newline);
+    }
+    if end < lines.len() {
+        out.push_str(newline);
+        out.push_str(&lines[end..].concat());
+    }
+    Ok(out)
+}
+
+/// Whether `contents` has a `## [Unreleased]` section to release
+pub fn has_unreleased(contents: &str) -> bool {
+    contents.lines().any(is_unreleased)
+}
+
+fn is_unreleased(line: &str) -> bool {
+    line.trim_end()
+        .strip_prefix("## ")
+        .is_some_and(|heading| heading.trim().eq_ignore_ascii_case("[unreleased]"))
+}
+
+/// A `[label]: url` line, as used for the version links at the end
+fn is_link_reference(line: &str) -> bool {
+    line.starts_with('[') && line.contains("]: ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()
+    }
+
+    #[test]
+    fn test_release() -> Result<()> {
+        let contents = "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- a thing\n\n## [1.2.0] - 2024-01-01\n\n- older\n";
+        assert_eq!(
+            release(contents, "1.3.0", date())?,
+            "# Changelog\n\n## [Unreleased]\n\n## [1.3.0] - 2024-05-01\n\n### Added\n\n- a thing\n\n## [1.2.0] - 2024-01-01\n\n- older\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_before_links() -> Result<()> {
+        let contents = "## [unreleased]\n- a thing\n\n[unreleased]: https://example.com\n";
+        assert_eq!(
+            release(contents, "0.1.0", date())?,
+            "## [unreleased]\n\n## [0.1.0] - 2024-05-01\n\n- a thing\n\n[unreleased]: https://example.com\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_without_unreleased() {
+        assert!(release("# Changelog\n", "1.0.0", date()).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! Project configuration (`svbump.toml`) describing where versions live
+
+use crate::{hook::Hooks, FileType};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// File names checked, in order, when no `--config` is given
+pub const CONFIG_FILE_NAMES: [&str; 2] = ["svbump.toml", ".svbump.toml"];
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Where the version is read and bumped
+    pub primary: Option<Location>,
+
+    /// Locations set to the primary's new version
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<Location>,
+
+    /// Treat the primary as the canonical version: a write fails, listing the
+    /// drifted targets, unless every target holds it already
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub lockstep: bool,
+
+    /// Selectors used when none is given, keyed by a file name glob (e.g.
+    /// `"*.csproj" = "Project.PropertyGroup.Version"`); these take precedence
+    /// over the built-in ones
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub selectors: BTreeMap<String, String>,
+
+    /// Shell commands run around every write
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+
+    /// Commands reading and writing versions in formats svbump doesn't know,
+    /// keyed by file extension (e.g. `spec = "./scripts/spec-version"`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub plugins: BTreeMap<String, String>,
+
+    /// Packages with versions of their own, bumped by `foreach`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub packages: BTreeMap<String, Package>,
+}
+
+/// A package in a monorepo: its version and the locations that follow it
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Package {
+    pub primary: Option<Location>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<Location>,
+}
+
+impl Package {
+    /// This package as a config of its own, to bump like a single project
+    pub fn config(&self) -> Config {
+        Config {
+            primary: self.primary.clone(),
+            targets: self.targets.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A version field in a file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Location {
+    pub file: PathBuf,
+    pub selector: String,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub file_type: Option<FileType>,
+}
+
+impl Location {
+    pub fn new(file: PathBuf, selector: String) -> Self {
+        Location {
+            file,
+            selector,
+            file_type: None,
+        }
+    }
+
+    /// Parse a `FILE:SELECTOR` argument, splitting at the last colon
+    pub fn parse_file_selector(arg: &str) -> Result<Self> {
+        match arg.rsplit_once(':') {
+            Some((file, selector)) if !file.is_empty() && !selector.is_empty() => {
+                Ok(Location::new(file.into(), selector.to_string()))
+            }
+            _ => anyhow::bail!("Expected FILE:SELECTOR, got {:?}", arg),
+        }
+    }
+
+    /// Parse a `SELECTOR@FILE` argument, splitting at the first `@`
+    pub fn parse_selector_at_file(arg: &str) -> Result<Self> {
+        match arg.split_once('@') {
+            Some((selector, file)) if !selector.is_empty() && !file.is_empty() => {
+                Ok(Location::new(file.into(), selector.to_string()))
+            }
+            _ => anyhow::bail!("Expected SELECTOR@FILE, got {:?}", arg),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config at `path`, or discover one in the current directory
+    pub fn load(path: Option<&Path>) -> Result<Option<Config>> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match CONFIG_FILE_NAMES
+                .iter()
+                .map(PathBuf::from)
+                .find(|p| p.is_file())
+            {
+                Some(path) => path,
+                None => return Ok(None),
+            },
+        };
+        Config::from_file(&path).map(Some)
+    }
+
+    /// The selector configured for files named like `file`
+    pub fn selector_for(&self, file: &Path) -> Result<Option<&str>> {
+        let Some(name) = file.file_name().and_then(|name| name.to_str()) else {
+            return Ok(None);
+        };
+        for (pattern, selector) in &self.selectors {
+            if glob::Pattern::new(pattern)?.matches(name) {
+                return Ok(Some(selector));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parse a config file, resolving its file paths relative to its directory
+    pub fn from_file(path: &Path) -> Result<Config> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut config: Config = toml_edit::de::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        resolve(base, &mut config.primary, &mut config.targets);
+        for package in config.packages.values_mut() {
+            resolve(base, &mut package.primary, &mut package.targets);
+        }
+        Ok(config)
+    }
+}
+
+/// Make locations relative to `base`; a plain list of targets is bumped from
+/// its first entry
+fn resolve(base: &Path, primary: &mut Option<Location>, targets: &mut Vec<Location>) {
+    for location in primary.iter_mut().chain(targets.iter_mut()) {
+        location.file = base.join(&location.file);
+    }
+    if primary.is_none() && !targets.is_empty() {
+        *primary = Some(targets.remove(0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_file_resolves_paths() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("svbump.toml");
+        fs::write(
+            &path,
+            r#"
+[primary]
+file = "Cargo.toml"
+selector = "package.version"
+
+[[targets]]
+file = "chart/Chart.yaml"
+selector = "appVersion"
+type = "yaml"
+"#,
+        )?;
+
+        let config = Config::from_file(&path)?;
+        let primary = config.primary.unwrap();
+        assert_eq!(primary.file, dir.path().join("Cargo.toml"));
+        assert_eq!(config.targets[0].file, dir.path().join("chart/Chart.yaml"));
+        assert_eq!(config.targets[0].file_type, Some(FileType::Yaml));
+        Ok(())
+    }
+
+    #[test]
+    fn test_first_target_is_primary() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(".svbump.toml");
+        fs::write(
+            &path,
+            r#"
+[[targets]]
+file = "packages/core/package.json"
+selector = "version"
+
+[[targets]]
+file = "deploy/values.tpl"
+selector = "image.tag"
+type = "yaml"
+"#,
+        )?;
+
+        let config = Config::from_file(&path)?;
+        assert_eq!(
+            config.primary.unwrap().file,
+            dir.path().join("packages/core/package.json")
+        );
+        assert_eq!(config.targets.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_packages() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("svbump.toml");
+        fs::write(
+            &path,
+            r#"
+[packages.core]
+primary = { file = "packages/core/package.json", selector = "version" }
+
+[[packages.cli.targets]]
+file = "packages/cli/Cargo.toml"
+selector = "package.version"
+
+[[packages.cli.targets]]
+file = "packages/cli/npm/package.json"
+selector = "version"
+"#,
+        )?;
+
+        let config = Config::from_file(&path)?;
+        assert!(config.primary.is_none());
+        let names: Vec<&str> = config.packages.keys().map(String::as_str).collect();
+        assert_eq!(names, ["cli", "core"]);
+        let cli = config.packages["cli"].config();
+        assert_eq!(
+            cli.primary.unwrap().file,
+            dir.path().join("packages/cli/Cargo.toml")
+        );
+        assert_eq!(cli.targets.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_location_args() -> Result<()> {
+        let location = Location::parse_file_selector("C:/app/Cargo.toml:package.version")?;
+        assert_eq!(location.file, PathBuf::from("C:/app/Cargo.toml"));
+        assert_eq!(location.selector, "package.version");
+
+        let location =
+            Location::parse_selector_at_file("version@node_modules/@scope/package.json")?;
+        assert_eq!(
+            location.file,
+            PathBuf::from("node_modules/@scope/package.json")
+        );
+        assert_eq!(location.selector, "version");
+
+        assert!(Location::parse_file_selector("package.json").is_err());
+        assert!(Location::parse_selector_at_file("version@").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_configured_selectors() -> Result<()> {
+        let config: Config = toml_edit::de::from_str(
+            r#"
+[selectors]
+"*.csproj" = "Project.PropertyGroup.Version"
+"Cargo.toml" = "workspace.package.version"
+"#,
+        )?;
+        assert_eq!(
+            config.selector_for(Path::new("src/App.csproj"))?,
+            Some("Project.PropertyGroup.Version")
+        );
+        assert_eq!(
+            config.selector_for(Path::new("Cargo.toml"))?,
+            Some("workspace.package.version")
+        );
+        assert_eq!(config.selector_for(Path::new("package.json"))?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+//! Choosing a bump level from Conventional Commits messages
+
+use crate::BumpLevel;
+
+/// The bump a commit message calls for: `feat` is minor, `fix` is patch, and
+/// a `!` after the type or a `BREAKING CHANGE` footer is major
+pub fn classify(message: &str) -> Option<BumpLevel> {
+    let header = message.lines().next().unwrap_or_default();
+    let (prefix, _) = header.split_once(':')?;
+    let breaking_header = prefix.ends_with('!');
+    let kind = prefix.trim_end_matches('!');
+    let kind = kind.split_once('(').map_or(kind, |(kind, _)| kind).trim();
+
+    let breaking_footer = message
+        .lines()
+        .skip(1)
+        .any(|line| line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:"));
+    if breaking_header || breaking_footer {
+        return Some(BumpLevel::Major);
+    }
+    match kind.to_lowercase().as_str() {
+        "feat" => Some(BumpLevel::Minor),
+        "fix" => Some(BumpLevel::Patch),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("feat: add auto"), Some(BumpLevel::Minor));
+        assert_eq!(classify("fix(yaml): keep comments"), Some(BumpLevel::Patch));
+        assert_eq!(classify("feat(cli)!: rename flags"), Some(BumpLevel::Major));
+        assert_eq!(
+            classify("fix: parse\n\nBREAKING CHANGE: selectors are quoted"),
+            Some(BumpLevel::Major)
+        );
+        assert_eq!(classify("chore: release 1.2.3"), None);
+        assert_eq!(classify("Merge branch 'main'"), None);
+    }
+}
@@ -0,0 +1,439 @@
+//! Locating string fields in CUE files by their field path, where the
+//! fields of a struct are under the field holding it and `a: b: "1.2.3"` is
+//! short for `a: { b: "1.2.3" }`
+
+use crate::binding::{self, Binding};
+use anyhow::{Context, Result};
+use std::ops::Range;
+
+struct Scanner<'a> {
+    content: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    bindings: Vec<Binding>,
+}
+
+/// Every field in `content`, with its full path
+fn bindings(content: &str) -> Result<Vec<Binding>> {
+    let mut scanner = Scanner {
+        content,
+        bytes: content.as_bytes(),
+        pos: 0,
+        bindings: Vec::new(),
+    };
+    scanner.body(Some(&[]), false)?;
+    Ok(scanner.bindings)
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).copied()
+    }
+
+    /// Read the declarations of a struct, up to and including the `}`
+    /// closing it when `nested`. Fields are recorded under `prefix`, unless
+    /// it is None for a struct that has no path, such as one in a list.
+    fn body(&mut self, prefix: Option<&[String]>, nested: bool) -> Result<()> {
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None if nested => anyhow::bail!("Unbalanced braces in CUE"),
+                None => return Ok(()),
+                Some(b'}') if nested => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(b',') => {
+                    self.pos += 1;
+                    continue;
+                }
+                Some(c @ (b'}' | b']' | b')')) => {
+                    anyhow::bail!("Unexpected {:?} at byte {} in CUE", c as char, self.pos)
+                }
+                _ => {}
+            }
+            if self.label().is_some() {
+                self.field(prefix)?;
+                continue;
+            }
+            let start = self.pos;
+            match self.ident() {
+                "package" if !nested => self.skip_line(),
+                "import" => {
+                    self.skip_inline();
+                    match self.peek() {
+                        Some(b'(') => {
+                            self.pos += 1;
+                            self.group(b')')?;
+                        }
+                        _ => self.skip_line(),
+                    }
+                }
+                "let" => {
+                    self.expr(None)?;
+                }
+                "for" | "if" => self.comprehension()?,
+                _ => {
+                    // An embedded value, such as `#Base & { ... }`
+                    self.pos = start;
+                    self.expr(prefix)?;
+                }
+            }
+        }
+    }
+
+    /// Skip the clauses of a comprehension and the struct it adds, whose
+    /// fields depend on the clauses and so have no path of their own
+    fn comprehension(&mut self) -> Result<()> {
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None => anyhow::bail!("Comprehension without a struct in CUE"),
+                Some(b',') => self.pos += 1,
+                // The struct of a clause such as `for x in {a: 1}` has more
+                // clauses after it, where the struct added ends the line
+                Some(b'{') => {
+                    self.token(None)?;
+                    self.skip_inline();
+                    if matches!(self.peek(), None | Some(b'\n' | b',' | b'}')) {
+                        return Ok(());
+                    }
+                }
+                Some(_) => self.token(None)?,
+            }
+        }
+    }
+
+    /// The label of the field starting here and the position after its `:`,
+    /// without moving past it
+    fn label(&mut self) -> Option<(String, usize)> {
+        let start = self.pos;
+        let label = match self.peek() {
+            Some(b'"') if !self.content[self.pos..].starts_with("\"\"\"") => {
+                self.skip_string().ok()?;
+                Some(self.content[start + 1..self.pos - 1].to_string())
+            }
+            Some(c) if is_ident_start(c) => Some(self.ident().to_string()),
+            _ => None,
+        };
+        self.skip_inline();
+        if matches!(self.peek(), Some(b'?' | b'!')) {
+            self.pos += 1;
+            self.skip_inline();
+        }
+        let found = match (label, self.peek()) {
+            (Some(label), Some(b':')) if self.peek_at(1) != Some(b'=') => {
+                Some((label, self.pos + 1))
+            }
+            _ => None,
+        };
+        self.pos = start;
+        found
+    }
+
+    /// Read a field and its value, along with any fields it is short for
+    fn field(&mut self, prefix: Option<&[String]>) -> Result<()> {
+        let (label, after) = self.label().unwrap();
+        self.pos = after;
+        let path = prefix.map(|prefix| {
+            let mut path = prefix.to_vec();
+            path.push(label);
+            path
+        });
+        self.skip_inline();
+        if self.label().is_some() {
+            return self.field(path.as_deref());
+        }
+        let value = self.expr(path.as_deref())?;
+        if let Some(path) = path {
+            self.bindings.push(Binding { path, value });
+        }
+        Ok(())
+    }
+
+    /// Skip one expression, stopping before the newline, `,` or closing
+    /// bracket that ends it, with the fields of its structs under `path`.
+    /// Returns the range of the contents when it is just a quoted string.
+    fn expr(&mut self, path: Option<&[String]>) -> Result<Option<Range<usize>>> {
+        self.skip_inline();
+        let start = self.pos;
+        let mut string = None;
+        loop {
+            self.skip_inline();
+            match self.peek() {
+                None | Some(b'\n' | b',' | b'}' | b']' | b')') => break,
+                Some(b'"') if self.pos == start && !self.content[start..].starts_with("\"\"\"") => {
+                    self.skip_string()?;
+                    string = Some(start + 1..self.pos - 1);
+                }
+                Some(_) => {
+                    self.token(path)?;
+                    string = None;
+                }
+            }
+        }
+        Ok(string)
+    }
+
+    /// Skip one token, entering any struct, list or parentheses it opens
+    fn token(&mut self, path: Option<&[String]>) -> Result<()> {
+        match self.peek() {
+            Some(b'"' | b'\'') if self.content[self.pos..].starts_with("\"\"\"") => {
+                self.skip_multiline(b'"')?
+            }
+            Some(b'\'') if self.content[self.pos..].starts_with("'''") => {
+                self.skip_multiline(b'\'')?
+            }
+            Some(b'"' | b'\'') => self.skip_string()?,
+            Some(b'#') if matches!(self.peek_at(1), Some(b'"' | b'#')) => self.skip_raw_string()?,
+            Some(b'{') => {
+                self.pos += 1;
+                self.body(path, true)?;
+            }
+            Some(open @ (b'[' | b'(')) => {
+                self.pos += 1;
+                self.group(if open == b'[' { b']' } else { b')' })?;
+            }
+            Some(c) if is_ident_start(c) => {
+                self.ident();
+            }
+            Some(_) => self.pos += 1,
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Skip the elements of a list or parentheses up to and including `close`
+    fn group(&mut self, close: u8) -> Result<()> {
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None => anyhow::bail!("Missing {:?} in CUE", close as char),
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(b',') => self.pos += 1,
+                Some(c @ (b'}' | b']' | b')')) => {
+                    anyhow::bail!("Unexpected {:?} at byte {} in CUE", c as char, self.pos)
+                }
+                Some(_) => {
+                    self.expr(None)?;
+                }
+            }
+        }
+    }
+
+    fn ident(&mut self) -> &str {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| {
+            c.is_ascii_alphanumeric()
+                || matches!(c, b'_' | b'$')
+                || (c == b'#' && self.pos == start)
+        }) {
+            self.pos += 1;
+        }
+        &self.content[start..self.pos]
+    }
+
+    fn skip_line(&mut self) {
+        while self.peek().is_some_and(|c| c != b'\n') {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip spaces and comments, but not the newline ending a line
+    fn skip_inline(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\r') => self.pos += 1,
+                Some(b'/') if self.peek_at(1) == Some(b'/') => self.skip_line(),
+                _ => return,
+            }
+        }
+    }
+
+    /// Skip spaces, comments and newlines
+    fn skip_trivia(&mut self) {
+        loop {
+            self.skip_inline();
+            if self.peek() != Some(b'\n') {
+                return;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn skip_string(&mut self) -> Result<()> {
+        let start = self.pos;
+        let quote = self.bytes[start];
+        self.pos += 1;
+        loop {
+            match self.peek() {
+                None | Some(b'\n') => anyhow::bail!("Unterminated string at byte {}", start),
+                Some(b'\\') if self.peek_at(1) == Some(b'(') => {
+                    self.pos += 2;
+                    self.group(b')')?;
+                }
+                Some(b'\\') => self.pos = (self.pos + 2).min(self.bytes.len()),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// Skip a `"""` or `'''` string
+    fn skip_multiline(&mut self, quote: u8) -> Result<()> {
+        let start = self.pos;
+        let delimiter = if quote == b'"' { "\"\"\"" } else { "'''" };
+        let end = self.content[start + 3..]
+            .find(delimiter)
+            .with_context(|| format!("Unterminated string at byte {}", start))?;
+        self.pos = start + 3 + end + 3;
+        Ok(())
+    }
+
+    /// Skip a `#"..."#` string, where the number of `#` matches
+    fn skip_raw_string(&mut self) -> Result<()> {
+        let start = self.pos;
+        let hashes =
+            self.content[start..].len() - self.content[start..].trim_start_matches('#').len();
+        let rest = &self.content[start + hashes..];
+        if !rest.starts_with('"') {
+            self.pos = start + hashes;
+            return Ok(());
+        }
+        let quotes = if rest.starts_with("\"\"\"") { 3 } else { 1 };
+        let close = format!("{}{}", &rest[..quotes], "#".repeat(hashes));
+        let end = rest[quotes..]
+            .find(&close)
+            .with_context(|| format!("Unterminated string at byte {}", start))?;
+        self.pos = start + hashes + quotes + end + close.len();
+        Ok(())
+    }
+}
+
+fn is_ident_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, b'_' | b'$' | b'#')
+}
+
+/// Byte range of the string at `path`, which must be set exactly once, to a
+/// double-quoted string without interpolation
+pub fn locate(content: &str, path: &[String]) -> Result<Option<Range<usize>>> {
+    binding::locate(content, path, bindings, is_interpolated)
+}
+
+/// Check that replacing the string at `path` turned `old` into `new` without
+/// changing anything else about its structure, by scanning `new` again
+pub fn check_edit(old: &str, new: &str, path: &[String], version: &str) -> Result<()> {
+    binding::check_edit(old, new, path, version, bindings, is_interpolated)
+}
+
+/// Whether a quoted string holds a `\(` interpolation, rather than an escaped
+/// backslash before a `(`
+fn is_interpolated(value: &str) -> bool {
+    let mut bytes = value.bytes();
+    while let Some(c) = bytes.next() {
+        if c == b'\\' && bytes.next() == Some(b'(') {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_paths() -> Result<()> {
+        let content = r##"package app
+
+import (
+	"strings"
+)
+
+// version: "0.0.1"
+version: "1.2.3"
+name:    strings.ToLower("App")
+
+#Image: {
+	repo: string
+	tag?: string
+}
+
+deploy: image: #Image & {
+	repo: "ghcr.io/acme/app"
+	tag:  "1.2.3" // the release
+}
+
+chart: {
+	"app.version": "2.0.0", kind: "helm"
+	notes: """
+		version: "9.9.9"
+		"""
+	url:   "https://example.com/\(version)"
+	tags: ["a", {version: "0.1.0"}]
+	raw:  #"a "quoted" \(x)"#
+	for k, v in {a: "b"} {
+		(k): v
+	}
+}
+"##;
+        let value = |selector: &[&str]| {
+            let path: Vec<String> = selector.iter().map(|s| s.to_string()).collect();
+            locate(content, &path).map(|range| range.map(|range| &content[range]))
+        };
+        assert_eq!(value(&["version"])?, Some("1.2.3"));
+        assert_eq!(value(&["deploy", "image", "tag"])?, Some("1.2.3"));
+        assert_eq!(
+            value(&["deploy", "image", "repo"])?,
+            Some("ghcr.io/acme/app")
+        );
+        assert_eq!(value(&["chart", "app.version"])?, Some("2.0.0"));
+        assert_eq!(value(&["chart", "kind"])?, Some("helm"));
+        assert_eq!(value(&["chart", "version"])?, None);
+        assert_eq!(value(&["chart", "a"])?, None);
+        assert_eq!(value(&["chart", "tags", "version"])?, None);
+        assert!(value(&["name"]).is_err());
+        assert!(value(&["chart", "notes"]).is_err());
+        assert!(value(&["chart", "url"]).is_err());
+        assert!(value(&["chart", "raw"]).is_err());
+        assert!(value(&["#Image", "tag"]).is_err());
+
+        let twice = "a: version: \"1\"\na: {version: \"2\"}\n";
+        let path = ["a", "version"].map(String::from);
+        assert!(locate(twice, &path).is_err());
+        assert!(locate("a: {\n", &path).is_err());
+        assert!(locate("a: [}]\n", &path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpolation() -> Result<()> {
+        let content = r#"app: {
+	image:   "ghcr.io/acme/app:\(version)"
+	path:    "C:\\(x)"
+	version: "1.2.3"
+}
+"#;
+        let path = |name: &str| ["app", name].map(String::from);
+        assert!(locate(content, &path("image")).is_err());
+        let range = locate(content, &path("path"))?.unwrap();
+        assert_eq!(&content[range], "C:\\\\(x)");
+
+        // A version that would start an interpolation isn't written
+        let range = locate(content, &path("version"))?.unwrap();
+        let mut new = content.to_string();
+        new.replace_range(range, "\\(x)");
+        assert!(check_edit(content, &new, &path("version"), "\\(x)").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+//! Selectors of the version field in well-known files
+
+use std::path::Path;
+
+/// File names and the selector of their version
+pub const SELECTORS: &[(&str, &str)] = &[
+    ("Cargo.toml", "package.version"),
+    ("package.json", "version"),
+    ("pyproject.toml", "project.version"),
+    ("setup.cfg", "metadata.version"),
+    (".bumpversion.cfg", "bumpversion.current_version"),
+    ("Chart.yaml", "version"),
+    ("pubspec.yaml", "version"),
+    ("composer.json", "version"),
+    ("deno.json", "version"),
+    ("jsr.json", "version"),
+    ("manifest.json", "version"),
+    ("gradle.properties", "version"),
+    ("build.gradle", "version"),
+    ("build.gradle.kts", "version"),
+    ("Info.plist", "CFBundleShortVersionString"),
+    ("default.nix", "version"),
+    ("package.nix", "version"),
+    ("VERSION", "."),
+    (".version", "."),
+];
+
+/// The selector of the version in a well-known file, by its file name
+pub fn selector_for(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    SELECTORS
+        .iter()
+        .find(|(file, _)| *file == name)
+        .map(|(_, selector)| *selector)
+}
+
+/// Distinct known selectors, each with the files it's used for
+pub fn known_selectors() -> Vec<(&'static str, Vec<&'static str>)> {
+    let mut known: Vec<(&str, Vec<&str>)> = Vec::new();
+    for (file, selector) in SELECTORS {
+        match known.iter_mut().find(|(known, _)| known == selector) {
+            Some((_, files)) => files.push(file),
+            None => known.push((selector, vec![file])),
+        }
+    }
+    known
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_selectors() {
+        let known = known_selectors();
+        assert_eq!(known[0], ("package.version", vec!["Cargo.toml"]));
+        let (_, files) = known.iter().find(|(s, _)| *s == "version").unwrap();
+        assert_eq!(files[..2], ["package.json", "Chart.yaml"]);
+    }
+
+    #[test]
+    fn test_selector_for() {
+        assert_eq!(
+            selector_for(Path::new("crates/a/Cargo.toml")),
+            Some("package.version")
+        );
+        assert_eq!(
+            selector_for(Path::new("pyproject.toml")),
+            Some("project.version")
+        );
+        assert_eq!(selector_for(Path::new("app.json")), None);
+    }
+}
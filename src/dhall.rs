@@ -0,0 +1,489 @@
+//! Locating string fields in Dhall expressions by their path: the fields of
+//! a record literal are under the field or `let` binding holding it, and
+//! `let` bindings are under their name alone, as in
+//! `let version = "1.2.3" in { package = { name = "app", version } }`
+
+use crate::binding::{self, Binding};
+use anyhow::{Context, Result};
+use std::ops::Range;
+
+struct Scanner<'a> {
+    content: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    bindings: Vec<Binding>,
+}
+
+/// Every record field and `let` binding in `content`, with its full path
+fn bindings(content: &str) -> Result<Vec<Binding>> {
+    let mut scanner = Scanner {
+        content,
+        bytes: content.as_bytes(),
+        pos: 0,
+        bindings: Vec::new(),
+    };
+    scanner.expr(Some(&[]), false)?;
+    scanner.skip_trivia()?;
+    if let Some(c) = scanner.peek() {
+        anyhow::bail!(
+            "Unexpected {:?} at byte {} in Dhall",
+            c as char,
+            scanner.pos
+        );
+    }
+    Ok(scanner.bindings)
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).copied()
+    }
+
+    /// Skip one expression, stopping before the `,` or closing bracket that
+    /// ends it, or the `in` or `let` ending the value of a `let` when
+    /// `in_let`. The fields of its records are under `path`. Returns the range
+    /// of the contents when it is just a quoted string.
+    fn expr(&mut self, path: Option<&[String]>, in_let: bool) -> Result<Option<Range<usize>>> {
+        self.skip_trivia()?;
+        let start = self.pos;
+        let mut string = None;
+        loop {
+            self.skip_trivia()?;
+            match self.peek() {
+                None | Some(b',' | b'}' | b']' | b')') => break,
+                Some(b'"') if self.pos == start => {
+                    self.skip_string()?;
+                    string = Some(start + 1..self.pos - 1);
+                    continue;
+                }
+                Some(b'"') => self.skip_string()?,
+                Some(b'\'') if self.peek_at(1) == Some(b'\'') => self.skip_indented_string()?,
+                Some(b'`') => self.skip_quoted_label()?,
+                Some(b'{') => {
+                    self.pos += 1;
+                    self.record(path)?;
+                }
+                Some(open @ (b'[' | b'(' | b'<')) => {
+                    self.pos += 1;
+                    self.group(match open {
+                        b'[' => b']',
+                        b'(' => b')',
+                        _ => b'>',
+                    })?;
+                }
+                Some(c) if is_label_start(c) => {
+                    let word = self.label();
+                    match word {
+                        "in" | "let" if in_let => {
+                            self.pos -= word.len();
+                            break;
+                        }
+                        "let" => {
+                            self.pos -= word.len();
+                            self.lets()?;
+                        }
+                        _ => {}
+                    }
+                }
+                Some(_) => self.pos += 1,
+            }
+            string = None;
+        }
+        Ok(string)
+    }
+
+    /// Read `let name = value` bindings up to and including their `in`
+    fn lets(&mut self) -> Result<()> {
+        loop {
+            self.skip_trivia()?;
+            let start = self.pos;
+            match self.label() {
+                "let" => {}
+                "in" => return Ok(()),
+                _ => anyhow::bail!("Expected let or in at byte {}", start),
+            }
+            self.skip_trivia()?;
+            let name = match self.peek() {
+                Some(b'`') => {
+                    let start = self.pos;
+                    self.skip_quoted_label()?;
+                    self.content[start + 1..self.pos - 1].to_string()
+                }
+                _ => self.label().to_string(),
+            };
+            if name.is_empty() {
+                anyhow::bail!("Expected a name after let at byte {}", start);
+            }
+            self.skip_trivia()?;
+            if self.peek() == Some(b':') {
+                self.pos += 1;
+                self.annotation()?;
+            }
+            if self.peek() != Some(b'=') {
+                anyhow::bail!("Expected = in the let at byte {}", start);
+            }
+            self.pos += 1;
+            let path = vec![name];
+            let value = self.expr(Some(&path), true)?;
+            self.bindings.push(Binding { path, value });
+        }
+    }
+
+    /// Skip the type annotating a `let`, up to the `=` after it
+    fn annotation(&mut self) -> Result<()> {
+        loop {
+            self.skip_trivia()?;
+            match self.peek() {
+                None | Some(b',' | b'}' | b']' | b')') => {
+                    anyhow::bail!("Expected = in the let at byte {}", self.pos)
+                }
+                // `==` and `===` are operators
+                Some(b'=') if self.peek_at(1) == Some(b'=') => {
+                    while self.peek() == Some(b'=') {
+                        self.pos += 1;
+                    }
+                }
+                Some(b'=') => return Ok(()),
+                Some(b'"') => self.skip_string()?,
+                Some(b'`') => self.skip_quoted_label()?,
+                Some(b'{') => {
+                    self.pos += 1;
+                    self.record(None)?;
+                }
+                Some(open @ (b'[' | b'(' | b'<')) => {
+                    self.pos += 1;
+                    self.group(match open {
+                        b'[' => b']',
+                        b'(' => b')',
+                        _ => b'>',
+                    })?;
+                }
+                Some(c) if is_label_start(c) => {
+                    self.label();
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// Read the fields of a record literal or type up to and including its
+    /// `}`, recording each `name = value` under `path`
+    fn record(&mut self, path: Option<&[String]>) -> Result<()> {
+        loop {
+            self.skip_trivia()?;
+            let mut names = Vec::new();
+            match self.peek() {
+                None => anyhow::bail!("Unbalanced braces in Dhall"),
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                // `{=}` and leading commas
+                Some(b',' | b'=') => {
+                    self.pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            // A field name, or a dotted path as in `{ a.b = 1 }`
+            loop {
+                self.skip_trivia()?;
+                match self.peek() {
+                    Some(b'`') => {
+                        let start = self.pos;
+                        self.skip_quoted_label()?;
+                        names.push(self.content[start + 1..self.pos - 1].to_string());
+                    }
+                    Some(c) if is_label_start(c) => names.push(self.label().to_string()),
+                    Some(c) => anyhow::bail!(
+                        "Unexpected {:?} at byte {} in Dhall record",
+                        c as char,
+                        self.pos
+                    ),
+                    None => anyhow::bail!("Unbalanced braces in Dhall"),
+                }
+                self.skip_trivia()?;
+                if self.peek() != Some(b'.') {
+                    break;
+                }
+                self.pos += 1;
+            }
+            match self.peek() {
+                Some(b'=') => {
+                    self.pos += 1;
+                    let child = path.map(|path| path.iter().cloned().chain(names).collect());
+                    let value = self.expr(child.as_deref(), false)?;
+                    if let Some(path) = child {
+                        self.bindings.push(Binding { path, value });
+                    }
+                }
+                // A field of a record type
+                Some(b':') => {
+                    self.pos += 1;
+                    self.expr(None, false)?;
+                }
+                // A punned field, `{ version }`
+                _ => {}
+            }
+        }
+    }
+
+    /// Skip the expressions of a list, parentheses or union type up to and
+    /// including `close`
+    fn group(&mut self, close: u8) -> Result<()> {
+        loop {
+            self.skip_trivia()?;
+            match self.peek() {
+                None => anyhow::bail!("Missing {:?} in Dhall", close as char),
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(b',') => self.pos += 1,
+                Some(c @ (b'}' | b']' | b')')) => {
+                    anyhow::bail!("Unexpected {:?} at byte {} in Dhall", c as char, self.pos)
+                }
+                // The alternatives of a union type end before its `>`
+                Some(_) if close == b'>' => self.union_alternative()?,
+                Some(_) => {
+                    self.expr(None, false)?;
+                }
+            }
+        }
+    }
+
+    /// Skip an alternative of a union type, `< A | B : Text >`
+    fn union_alternative(&mut self) -> Result<()> {
+        loop {
+            self.skip_trivia()?;
+            match self.peek() {
+                None | Some(b'>') => return Ok(()),
+                Some(b'{') => {
+                    self.pos += 1;
+                    self.record(None)?;
+                }
+                Some(open @ (b'[' | b'(')) => {
+                    self.pos += 1;
+                    self.group(if open == b'[' { b']' } else { b')' })?;
+                }
+                Some(b'`') => self.skip_quoted_label()?,
+                // The arrow of a function type
+                Some(b'-') if self.peek_at(1) == Some(b'>') => self.pos += 2,
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    fn label(&mut self) -> &str {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-' | b'/'))
+        {
+            self.pos += 1;
+        }
+        &self.content[start..self.pos]
+    }
+
+    /// Skip whitespace, `--` comments and `{- -}` comments, which nest
+    fn skip_trivia(&mut self) -> Result<()> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_ascii_whitespace() => self.pos += 1,
+                Some(b'-') if self.peek_at(1) == Some(b'-') => {
+                    while self.peek().is_some_and(|c| c != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(b'{') if self.peek_at(1) == Some(b'-') => {
+                    let start = self.pos;
+                    let mut depth = 0;
+                    loop {
+                        let rest = &self.content[self.pos.min(self.bytes.len())..];
+                        if rest.is_empty() {
+                            anyhow::bail!("Unterminated comment at byte {}", start);
+                        } else if rest.starts_with("{-") {
+                            depth += 1;
+                            self.pos += 2;
+                        } else if rest.starts_with("-}") {
+                            depth -= 1;
+                            self.pos += 2;
+                            if depth == 0 {
+                                break;
+                            }
+                        } else {
+                            self.pos += rest.chars().next().map_or(1, char::len_utf8);
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn skip_string(&mut self) -> Result<()> {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.peek() {
+                None => anyhow::bail!("Unterminated string at byte {}", start),
+                Some(b'\\') => self.pos = (self.pos + 2).min(self.bytes.len()),
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(b'$') if self.peek_at(1) == Some(b'{') => self.skip_interpolation()?,
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// Skip a `''` string, where `'''` and `''${` are escapes
+    fn skip_indented_string(&mut self) -> Result<()> {
+        let start = self.pos;
+        self.pos += 2;
+        loop {
+            let rest = &self.bytes[self.pos.min(self.bytes.len())..];
+            if rest.is_empty() {
+                anyhow::bail!("Unterminated string at byte {}", start);
+            } else if rest.starts_with(b"'''") {
+                self.pos += 3;
+            } else if rest.starts_with(b"''${") {
+                self.pos += 4;
+            } else if rest.starts_with(b"''") {
+                self.pos += 2;
+                return Ok(());
+            } else if rest.starts_with(b"${") {
+                self.skip_interpolation()?;
+            } else {
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn skip_interpolation(&mut self) -> Result<()> {
+        self.pos += 2;
+        self.expr(None, false)?;
+        self.skip_trivia()?;
+        if self.peek() != Some(b'}') {
+            anyhow::bail!("Unterminated interpolation at byte {}", self.pos);
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Skip a label in backticks, such as `` `version-1` ``
+    fn skip_quoted_label(&mut self) -> Result<()> {
+        let start = self.pos;
+        let end = self.content[start + 1..]
+            .find('`')
+            .with_context(|| format!("Unterminated label at byte {}", start))?;
+        self.pos = start + 1 + end + 1;
+        Ok(())
+    }
+}
+
+fn is_label_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_'
+}
+
+/// Byte range of the string at `path`, which must be set exactly once, to a
+/// double-quoted string without interpolation
+pub fn locate(content: &str, path: &[String]) -> Result<Option<Range<usize>>> {
+    binding::locate(content, path, bindings, is_interpolated)
+}
+
+/// Check that replacing the string at `path` turned `old` into `new` without
+/// changing anything else about its structure, by scanning `new` again
+pub fn check_edit(old: &str, new: &str, path: &[String], version: &str) -> Result<()> {
+    binding::check_edit(old, new, path, version, bindings, is_interpolated)
+}
+
+/// Whether a quoted string holds a `${` interpolation, which `\${` escapes
+fn is_interpolated(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'$' if bytes.get(i + 1) == Some(&b'{') => return true,
+            _ => i += 1,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_paths() -> Result<()> {
+        let content = r#"-- version = "0.0.1"
+let Prelude = https://prelude.dhall-lang.org/v21.1.0/package.dhall
+let version : Text = "1.2.3" {- released { -}
+let Kind = < Library | App : { id : Text } >
+let image
+    : { repo : Text, tag : Text }
+    = { repo = "ghcr.io/acme/app", tag = "2.0.0" }
+in  { package =
+      { name = "app"
+      , version
+      , `docs-url` = "https://example.com/${version}"
+      , kind = Kind.Library
+      , deps = [ { name = "core", version = "0.4.0" } ]
+      }
+    , meta.license = "MIT"
+    , notes = ''
+        version = "9.9.9"
+        ''
+    , image = image // { tag = "2.1.0" }
+    }
+"#;
+        let value = |selector: &[&str]| {
+            let path: Vec<String> = selector.iter().map(|s| s.to_string()).collect();
+            locate(content, &path).map(|range| range.map(|range| &content[range]))
+        };
+        assert_eq!(value(&["version"])?, Some("1.2.3"));
+        assert_eq!(value(&["image", "repo"])?, Some("ghcr.io/acme/app"));
+        assert_eq!(value(&["package", "name"])?, Some("app"));
+        assert_eq!(value(&["meta", "license"])?, Some("MIT"));
+        assert_eq!(value(&["package", "version"])?, None);
+        assert_eq!(value(&["package", "deps", "version"])?, None);
+        assert!(value(&["Prelude"]).is_err());
+        assert!(value(&["package", "docs-url"]).is_err());
+        assert!(value(&["notes"]).is_err());
+        // Set in the binding and again in the record it is merged with
+        assert!(value(&["image", "tag"]).is_err());
+
+        let path = vec!["version".to_string()];
+        assert!(locate("{ version = \"1\"", &path).is_err());
+        assert!(locate("{ version = \"1\" }\n}", &path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpolation() -> Result<()> {
+        let content = r#"let name = "app"
+in  { image = "ghcr.io/acme/${name}"
+    , template = "\${version}"
+    , version = "1.2.3"
+    }
+"#;
+        let path = |name: &str| vec![name.to_string()];
+        assert!(locate(content, &path("image")).is_err());
+        let range = locate(content, &path("template"))?.unwrap();
+        assert_eq!(&content[range], "\\${version}");
+
+        // A version that would start an interpolation isn't written
+        let range = locate(content, &path("version"))?.unwrap();
+        let mut new = content.to_string();
+        new.replace_range(range, "${name}");
+        assert!(check_edit(content, &new, &path("version"), "${name}").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+//! Locating versions in Dockerfiles: `ARG` defaults (`ARG.VERSION`), `LABEL`
+//! values (`LABEL.org.opencontainers.image.version`) and `FROM` image tags
+//! (`FROM.node`)
+
+use crate::image;
+use anyhow::{Context, Result};
+use std::ops::Range;
+
+/// Index just past the line continuation starting with the `\` at `pos`, if
+/// only whitespace follows it on its line
+fn continuation(content: &str, pos: usize) -> Option<usize> {
+    let rest = &content[pos + 1..];
+    let trimmed = rest.trim_start_matches([' ', '\t', '\r']);
+    match trimmed.strip_prefix('\n') {
+        Some(_) => Some(content.len() - trimmed.len() + 1),
+        None if trimmed.is_empty() => Some(content.len()),
+        None => None,
+    }
+}
+
+/// The byte ranges of the words of each instruction, quotes included. Words
+/// are split at whitespace and line continuations; comment lines, and blank
+/// lines inside a continued instruction, are skipped.
+fn instructions(content: &str) -> Vec<Vec<Range<usize>>> {
+    let bytes = content.as_bytes();
+    let mut instructions = Vec::new();
+    let mut words: Vec<Range<usize>> = Vec::new();
+    let mut word: Option<usize> = None;
+    let mut line_start = true;
+    let mut continued = false;
+    let mut pos = 0;
+    let end_word = |word: &mut Option<usize>, words: &mut Vec<Range<usize>>, pos| {
+        if let Some(start) = word.take() {
+            words.push(start..pos);
+        }
+    };
+    while pos < bytes.len() {
+        if line_start {
+            line_start = false;
+            let rest = &content[pos..];
+            let indented = pos + rest.len() - rest.trim_start_matches([' ', '\t']).len();
+            let blank = matches!(bytes.get(indented), None | Some(b'\r' | b'\n'));
+            if bytes.get(indented) == Some(&b'#') || (continued && blank) {
+                pos = content[pos..]
+                    .find('\n')
+                    .map_or(content.len(), |i| pos + i + 1);
+                line_start = true;
+                continue;
+            }
+            continued = false;
+            pos = indented;
+            continue;
+        }
+        match bytes[pos] {
+            b'\n' => {
+                end_word(&mut word, &mut words, pos);
+                if !words.is_empty() {
+                    instructions.push(std::mem::take(&mut words));
+                }
+                line_start = true;
+                pos += 1;
+            }
+            b'\\' if continuation(content, pos).is_some() => {
+                end_word(&mut word, &mut words, pos);
+                pos = continuation(content, pos).unwrap();
+                line_start = true;
+                continued = true;
+            }
+            b' ' | b'\t' | b'\r' => {
+                end_word(&mut word, &mut words, pos);
+                pos += 1;
+            }
+            quote @ (b'"' | b'\'') => {
+                word.get_or_insert(pos);
+                pos += 1;
+                while pos < bytes.len() && bytes[pos] != quote && bytes[pos] != b'\n' {
+                    pos += if quote == b'"' && bytes[pos] == b'\\' {
+                        2
+                    } else {
+                        1
+                    };
+                }
+                pos = (pos + 1).min(bytes.len());
+            }
+            _ => {
+                word.get_or_insert(pos);
+                pos += 1;
+            }
+        }
+    }
+    end_word(&mut word, &mut words, pos);
+    if !words.is_empty() {
+        instructions.push(words);
+    }
+    instructions
+}
+
+/// The range inside any quotes around `range`
+fn unquote(content: &str, range: Range<usize>) -> Range<usize> {
+    let text = &content[range.clone()];
+    let quoted = text.len() >= 2
+        && (text.starts_with('"') && text.ends_with('"')
+            || text.starts_with('\'') && text.ends_with('\''));
+    if quoted {
+        range.start + 1..range.end - 1
+    } else {
+        range
+    }
+}
+
+/// The value of each `key=value` word naming `key`
+fn assignments<'a>(
+    content: &'a str,
+    words: &'a [Range<usize>],
+    key: &'a str,
+) -> impl Iterator<Item = Range<usize>> + 'a {
+    words.iter().filter_map(move |word| {
+        let eq = content[word.clone()].find('=')? + word.start;
+        let name = &content[unquote(content, word.start..eq)];
+        (name == key).then(|| unquote(content, eq + 1..word.end))
+    })
+}
+
+/// The tag of a `FROM` image reference naming `image`, as in
+/// `FROM --platform=$BUILDPLATFORM node:20.1.0@sha256:... AS build`
+fn from_tag(content: &str, words: &[Range<usize>], image: &str) -> Option<Range<usize>> {
+    let reference = words
+        .iter()
+        .find(|word| !content[(*word).clone()].starts_with("--"))?;
+    let text = &content[reference.clone()];
+    let tag = image::tag_range(text)?;
+    (text[..tag.start - 1] == *image)
+        .then(|| reference.start + tag.start..reference.start + tag.end)
+}
+
+/// Byte range of the version chosen by `selector`, `INSTRUCTION.KEY`, which
+/// must be set exactly once and without `$` substitution
+pub fn locate(content: &str, selector: &str) -> Result<Option<Range<usize>>> {
+    let (instruction, key) = selector.split_once('.').with_context(|| {
+        format!(
+            "Dockerfile selectors are ARG.NAME, LABEL.KEY or FROM.IMAGE, got {}",
+            selector
+        )
+    })?;
+    let instruction = instruction.to_ascii_uppercase();
+    if !["ARG", "LABEL", "FROM"].contains(&instruction.as_str()) {
+        anyhow::bail!(
+            "Dockerfile selectors start with ARG, LABEL or FROM, got {}",
+            selector
+        );
+    }
+    let mut values = Vec::new();
+    for words in instructions(content) {
+        let Some((first, args)) = words.split_first() else {
+            continue;
+        };
+        if !content[first.clone()].eq_ignore_ascii_case(&instruction) {
+            continue;
+        }
+        match instruction.as_str() {
+            "FROM" => values.extend(from_tag(content, args, key)),
+            _ => values.extend(assignments(content, args, key)),
+        }
+    }
+    match values.as_slice() {
+        [] => Ok(None),
+        [value] if content[value.clone()].contains('$') => anyhow::bail!(
+            "{} is substituted ({}), so it can't be bumped in place",
+            selector,
+            &content[value.clone()]
+        ),
+        [value] => Ok(Some(value.clone())),
+        _ => anyhow::bail!(
+            "{} is set {} times; use --type regex to choose one",
+            selector,
+            values.len()
+        ),
+    }
+}
+
+/// Check that setting `selector` to `version` in `old`, giving `new`, kept
+/// every instruction's words and left the version where it's found again
+pub fn check_edit(old: &str, new: &str, selector: &str, version: &str) -> Result<()> {
+    let invalid = || format!("Setting {} to {} would break the file", selector, version);
+    let shape = |content| -> Vec<usize> { instructions(content).iter().map(Vec::len).collect() };
+    if shape(new) != shape(old) {
+        anyhow::bail!(invalid());
+    }
+    match locate(new, selector).with_context(invalid)? {
+        Some(range) if new[range.clone()] == *version => Ok(()),
+        _ => anyhow::bail!(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() -> Result<()> {
+        let content = r#"# syntax=docker/dockerfile:1
+ARG BASE=node
+FROM --platform=$BUILDPLATFORM node:20.1.0@sha256:abc AS build
+# ARG VERSION=0.0.1
+ARG VERSION=1.2.3 \
+    # the build number
+    BUILD="42"
+
+FROM ghcr.io/acme/runtime:2.0.0
+LABEL org.opencontainers.image.title="app" \
+      "org.opencontainers.image.version"="1.2.3"
+arg SUBST=${VERSION}
+"#;
+        let value =
+            |selector| locate(content, selector).map(|range| range.map(|range| &content[range]));
+        assert_eq!(value("ARG.VERSION")?, Some("1.2.3"));
+        assert_eq!(value("ARG.BUILD")?, Some("42"));
+        assert_eq!(value("FROM.node")?, Some("20.1.0"));
+        assert_eq!(value("from.ghcr.io/acme/runtime")?, Some("2.0.0"));
+        assert_eq!(
+            value("LABEL.org.opencontainers.image.version")?,
+            Some("1.2.3")
+        );
+        assert_eq!(value("LABEL.org.opencontainers.image.title")?, Some("app"));
+        assert_eq!(value("ARG.MISSING")?, None);
+        assert_eq!(value("FROM.ghcr.io/acme")?, None);
+        assert!(value("ARG.SUBST").is_err());
+        assert!(value("ENV.VERSION").is_err());
+        assert!(value("VERSION").is_err());
+
+        let twice = "FROM node:20 AS build\nFROM node:20\n";
+        assert!(locate(twice, "FROM.node").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_edit() -> Result<()> {
+        let old = "ARG VERSION=1.2.3\nRUN make\n";
+        let range = locate(old, "ARG.VERSION")?.unwrap();
+        let edit = |version: &str| {
+            let mut new = old.to_string();
+            new.replace_range(range.clone(), version);
+            check_edit(old, &new, "ARG.VERSION", version)
+        };
+        assert!(edit("1.3.0").is_ok());
+        assert!(edit("1.3.0 OTHER=1").is_err());
+        assert!(edit("1.3.0\\\n").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+//! Byte order marks and line endings, kept so a rewritten file matches the
+//! bytes it was read from
+
+/// The UTF-8 byte order mark
+const BOM: char = '\u{feff}';
+
+/// Whether a file starts with a BOM and mostly uses CRLF line endings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Encoding {
+    pub bom: bool,
+    pub crlf: bool,
+}
+
+impl Encoding {
+    pub fn detect(content: &str) -> Self {
+        let crlf = content.matches("\r\n").count();
+        let lf = content.matches('\n').count();
+        Encoding {
+            bom: content.starts_with(BOM),
+            crlf: crlf > 0 && crlf * 2 >= lf,
+        }
+    }
+
+    /// Give `rendered` this encoding: the BOM is restored and, for CRLF
+    /// files, lone LFs written by a serializer become CRLF
+    pub fn apply(&self, rendered: String) -> String {
+        let mut out = String::with_capacity(rendered.len() + 3);
+        if self.bom && !rendered.starts_with(BOM) {
+            out.push(BOM);
+        }
+        if !self.crlf {
+            out.push_str(&rendered);
+            return out;
+        }
+        let mut previous = '\0';
+        for c in rendered.chars() {
+            if c == '\n' && previous != '\r' {
+                out.push('\r');
+            }
+            out.push(c);
+            previous = c;
+        }
+        out
+    }
+}
+
+/// `content` without its BOM
+pub fn strip_bom(content: &str) -> &str {
+    content.strip_prefix(BOM).unwrap_or(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let content = "\u{feff}a = 1\r\nb = 2\r\n";
+        let encoding = Encoding::detect(content);
+        assert_eq!(
+            encoding,
+            Encoding {
+                bom: true,
+                crlf: true
+            }
+        );
+        assert_eq!(strip_bom(content), "a = 1\r\nb = 2\r\n");
+        assert_eq!(
+            encoding.apply("a = 1\nb = 3\r\n".to_string()),
+            "\u{feff}a = 1\r\nb = 3\r\n"
+        );
+
+        let plain = Encoding::detect("a\nb\r\nc\n");
+        assert!(!plain.bom && !plain.crlf);
+        assert_eq!(plain.apply("a\nb\n".to_string()), "a\nb\n");
+    }
+}
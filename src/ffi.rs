@@ -0,0 +1,196 @@
+//! A C ABI for release tools in other languages, built with `--features ffi`.
+//! The header is include/svbump.h, generated with `just header`.
+//!
+//! Each function returns a string owned by the caller, to be released with
+//! `svbump_free`, or NULL on failure with the reason in `svbump_last_error`.
+//! A NULL selector picks the one of a well-known file, such as
+//! `package.version` for Cargo.toml.
+
+use crate::{defaults, load_document, Document, InputOptions, VersionBump};
+use anyhow::{Context, Result};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    path::Path,
+    ptr,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// A string argument, which may only be NULL when `optional`
+unsafe fn arg<'a>(value: *const c_char, name: &str, optional: bool) -> Result<Option<&'a str>> {
+    if value.is_null() {
+        if optional {
+            return Ok(None);
+        }
+        anyhow::bail!("{} must not be NULL", name);
+    }
+    let value = CStr::from_ptr(value)
+        .to_str()
+        .with_context(|| format!("{} is not valid UTF-8", name))?;
+    Ok(Some(value))
+}
+
+/// Hand a result to C, recording the error for `svbump_last_error`
+fn output(result: Result<String>) -> *mut c_char {
+    let result = result.and_then(|value| Ok(CString::new(value)?));
+    LAST_ERROR.with(|last| match result {
+        Ok(value) => {
+            *last.borrow_mut() = None;
+            value.into_raw()
+        }
+        Err(err) => {
+            let message = format!("{:#}", err).replace('\0', "");
+            *last.borrow_mut() = CString::new(message).ok();
+            ptr::null_mut()
+        }
+    })
+}
+
+/// Load the file at `file`, with the selector to use in it
+unsafe fn open(file: *const c_char, selector: *const c_char) -> Result<(Document, String, String)> {
+    let file = arg(file, "file", false)?.unwrap_or_default();
+    let selector = match arg(selector, "selector", true)? {
+        Some(selector) => selector,
+        None => defaults::selector_for(Path::new(file))
+            .with_context(|| format!("No default selector for {}, pass one", file))?,
+    };
+    let doc = load_document(Path::new(file), InputOptions::default())?;
+    Ok((doc, file.to_string(), selector.to_string()))
+}
+
+/// Bump the version in `file` by `level`, writing the file when `write`
+unsafe fn bump(
+    file: *const c_char,
+    level: *const c_char,
+    selector: *const c_char,
+    write: bool,
+) -> Result<String> {
+    let level: VersionBump = arg(level, "level", false)?.unwrap_or_default().parse()?;
+    let (mut doc, file, selector) = open(file, selector)?;
+    let change = doc.bump(&selector, &level)?;
+    if write {
+        doc.save(&file)?;
+    }
+    Ok(change.new)
+}
+
+/// Read the version at `selector` in `file`.
+///
+/// # Safety
+///
+/// `file` and `selector` must be NULL or point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn svbump_read(file: *const c_char, selector: *const c_char) -> *mut c_char {
+    output(open(file, selector).and_then(|(doc, _, selector)| doc.read_version(&selector)))
+}
+
+/// Bump the version at `selector` in `file` by `level` (a level such as
+/// "minor", or an explicit version) and write the file, returning the new
+/// version.
+///
+/// # Safety
+///
+/// `file`, `level` and `selector` must be NULL or point to NUL-terminated
+/// strings.
+#[no_mangle]
+pub unsafe extern "C" fn svbump_write(
+    file: *const c_char,
+    level: *const c_char,
+    selector: *const c_char,
+) -> *mut c_char {
+    output(bump(file, level, selector, true))
+}
+
+/// The version `svbump_write` would write, leaving the file as is.
+///
+/// # Safety
+///
+/// `file`, `level` and `selector` must be NULL or point to NUL-terminated
+/// strings.
+#[no_mangle]
+pub unsafe extern "C" fn svbump_preview(
+    file: *const c_char,
+    level: *const c_char,
+    selector: *const c_char,
+) -> *mut c_char {
+    output(bump(file, level, selector, false))
+}
+
+/// Why the last call on this thread returned NULL, or NULL if it succeeded.
+/// The message belongs to svbump and lasts until the next call.
+#[no_mangle]
+pub extern "C" fn svbump_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Release a string returned by svbump.
+///
+/// # Safety
+///
+/// `value` must be NULL or a string returned by svbump, freed only once.
+#[no_mangle]
+pub unsafe extern "C" fn svbump_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Take a string returned by svbump, or the last error
+    unsafe fn take(value: *mut c_char) -> Result<String, String> {
+        if value.is_null() {
+            return Err(CStr::from_ptr(svbump_last_error())
+                .to_string_lossy()
+                .into_owned());
+        }
+        let owned = CStr::from_ptr(value).to_string_lossy().into_owned();
+        svbump_free(value);
+        Ok(owned)
+    }
+
+    #[test]
+    fn test_ffi() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("Cargo.toml");
+        fs::write(&path, "[package]\nname = \"app\"\nversion = \"1.2.3\"\n")?;
+        let file = CString::new(path.to_str().unwrap())?;
+        let minor = CString::new("minor")?;
+        let selector = CString::new("package.missing")?;
+
+        unsafe {
+            assert_eq!(
+                take(svbump_read(file.as_ptr(), ptr::null())).unwrap(),
+                "1.2.3"
+            );
+            assert_eq!(
+                take(svbump_preview(file.as_ptr(), minor.as_ptr(), ptr::null())).unwrap(),
+                "1.3.0"
+            );
+            assert!(fs::read_to_string(&path)?.contains("\"1.2.3\""));
+            assert_eq!(
+                take(svbump_write(file.as_ptr(), minor.as_ptr(), ptr::null())).unwrap(),
+                "1.3.0"
+            );
+            assert!(fs::read_to_string(&path)?.contains("\"1.3.0\""));
+            assert!(svbump_last_error().is_null());
+
+            assert!(take(svbump_read(file.as_ptr(), selector.as_ptr())).is_err());
+            assert_eq!(
+                take(svbump_write(file.as_ptr(), ptr::null(), ptr::null())).unwrap_err(),
+                "level must not be NULL"
+            );
+        }
+        Ok(())
+    }
+}
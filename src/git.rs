@@ -0,0 +1,486 @@
+//! Git integration: tag naming, commit history, and committing and tagging
+//! written versions
+
+use crate::render_template;
+use anyhow::{Context, Result};
+use semver::Version;
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Options controlling the git tag for a new version
+#[derive(Debug, Clone, clap::Args)]
+pub struct TagOptions {
+    /// Prefix for tag names
+    #[arg(long, default_value = "v")]
+    pub tag_prefix: String,
+
+    /// Template for tag names, overriding --tag-prefix (e.g. "release-{major}.{minor}.{patch}")
+    #[arg(long, visible_alias = "tag-format")]
+    pub tag_template: Option<String>,
+
+    /// Print the tag name for the new version without touching git
+    #[arg(long)]
+    pub emit_tag_name: bool,
+
+    /// Refuse to write when the tag for the new version already exists
+    #[arg(long)]
+    pub fail_if_tag_exists: bool,
+}
+
+impl Default for TagOptions {
+    fn default() -> Self {
+        TagOptions {
+            tag_prefix: "v".to_string(),
+            tag_template: None,
+            emit_tag_name: false,
+            fail_if_tag_exists: false,
+        }
+    }
+}
+
+impl TagOptions {
+    /// Render the tag name for a version. Templates support `{prefix}` in
+    /// addition to the version placeholders of `render_template`.
+    pub fn tag_name(&self, version: &str) -> Result<String> {
+        let Some(template) = &self.tag_template else {
+            return Ok(format!("{}{}", self.tag_prefix, version));
+        };
+        render_template(&template.replace("{prefix}", &self.tag_prefix), version)
+    }
+
+    /// With --fail-if-tag-exists, check before anything is written that the
+    /// repository holding `file` has no tag for `version` yet
+    pub fn check_unreleased(&self, file: &Path, version: &str) -> Result<()> {
+        if !self.fail_if_tag_exists {
+            return Ok(());
+        }
+        let dir = repo_dir(file);
+        git(&dir, &["rev-parse", "--git-dir"])
+            .context("--fail-if-tag-exists needs a git repository")?;
+        let name = self.tag_name(version)?;
+        let tag_ref = format!("refs/tags/{}", name);
+        if git(&dir, &["rev-parse", "--quiet", "--verify", &tag_ref]).is_ok() {
+            anyhow::bail!(
+                "Tag {} already exists, so {} was already released",
+                name,
+                version
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Options for recording a written version in git
+#[derive(Debug, Clone, clap::Args)]
+pub struct GitOptions {
+    /// Commit the written files
+    #[arg(long)]
+    pub commit: bool,
+
+    /// Template for the commit message, with the same placeholders as --tag-format
+    #[arg(long, default_value = "chore: release {version}")]
+    pub commit_message: String,
+
+    /// Commit even when other changes are staged; they are left staged
+    #[arg(long, requires = "commit")]
+    pub allow_dirty: bool,
+
+    /// Create a git tag at HEAD for the new version, named by --tag-prefix or --tag-format
+    #[arg(long)]
+    pub tag: bool,
+
+    /// Message for an annotated tag, with the same placeholders as --tag-format
+    #[arg(long, requires = "tag")]
+    pub tag_message: Option<String>,
+
+    /// Create a GPG-signed tag
+    #[arg(long, requires = "tag")]
+    pub sign: bool,
+}
+
+impl Default for GitOptions {
+    fn default() -> Self {
+        GitOptions {
+            commit: false,
+            commit_message: "chore: release {version}".to_string(),
+            allow_dirty: false,
+            tag: false,
+            tag_message: None,
+            sign: false,
+        }
+    }
+}
+
+impl GitOptions {
+    /// Check, before anything is written, that `files` can be committed on
+    /// their own: other staged changes are refused without --allow-dirty
+    pub fn preflight(&self, files: &[&Path]) -> Result<()> {
+        if !self.commit || self.allow_dirty {
+            return Ok(());
+        }
+        let dir = repo_dir(files.first().context("No files to commit")?);
+        let root = fs::canonicalize(git(&dir, &["rev-parse", "--show-toplevel"])?)?;
+        let ours = absolute(files)?;
+        let staged = git(&dir, &["diff", "--cached", "--name-only", "-z"])?;
+        let unrelated: Vec<&str> = staged
+            .split('\0')
+            .filter(|name| !name.is_empty() && !ours.contains(&root.join(name)))
+            .collect();
+        if !unrelated.is_empty() {
+            anyhow::bail!(
+                "Refusing to commit with unrelated staged changes: {} (pass --allow-dirty to leave them staged)",
+                unrelated.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Run the requested git steps for `version`, which was just written to `files`
+    pub fn record(&self, files: &[&Path], tag: &TagOptions, version: &str) -> Result<()> {
+        if !self.commit && !self.tag {
+            return Ok(());
+        }
+        let dir = repo_dir(files.first().context("No files were written")?);
+
+        if self.commit {
+            let paths = absolute(files)?;
+            let paths: Vec<&str> = paths
+                .iter()
+                .map(|path| path.to_str().context("Non UTF-8 path"))
+                .collect::<Result<_>>()?;
+            let message = render_template(&self.commit_message, version)?;
+            let mut add = vec!["add", "--"];
+            add.extend(&paths);
+            git(&dir, &add)?;
+            let mut commit = vec!["commit", "--quiet", "--message", &message, "--"];
+            commit.extend(&paths);
+            git(&dir, &commit).context("Failed to commit")?;
+        }
+        if !self.tag {
+            return Ok(());
+        }
+
+        let name = tag.tag_name(version)?;
+        let message = match &self.tag_message {
+            Some(template) => Some(render_template(template, version)?),
+            None if self.sign => Some(name.clone()),
+            None => None,
+        };
+
+        let mut args = vec!["tag"];
+        if self.sign {
+            args.push("--sign");
+        } else if message.is_some() {
+            args.push("--annotate");
+        }
+        if let Some(message) = &message {
+            args.extend(["--message", message]);
+        }
+        args.push(&name);
+        git(&dir, &args).with_context(|| format!("Failed to create tag {}", name))?;
+        Ok(())
+    }
+}
+
+fn absolute(files: &[&Path]) -> Result<Vec<PathBuf>> {
+    files
+        .iter()
+        .map(|file| {
+            fs::canonicalize(file).with_context(|| format!("Failed to resolve {}", file.display()))
+        })
+        .collect()
+}
+
+/// A commit made since the last tag
+pub struct Commit {
+    pub hash: String,
+    pub message: String,
+}
+
+/// The most recent tag reachable from HEAD, if any, and the commits after it,
+/// newest first
+pub fn history_since_last_tag(dir: &Path) -> Result<(Option<String>, Vec<Commit>)> {
+    let tag = git(dir, &["describe", "--tags", "--abbrev=0"]).ok();
+    let range = match &tag {
+        Some(tag) => format!("{}..HEAD", tag),
+        None => "HEAD".to_string(),
+    };
+    let log = git(dir, &["log", "--format=%h%x1f%B%x1e", &range])?;
+    let commits = log
+        .split('\x1e')
+        .filter_map(|entry| {
+            let (hash, message) = entry.trim().split_once('\x1f')?;
+            Some(Commit {
+                hash: hash.to_string(),
+                message: message.trim().to_string(),
+            })
+        })
+        .collect();
+    Ok((tag, commits))
+}
+
+/// The highest version among the tags named `<prefix><version>` that are
+/// reachable from HEAD, with its tag
+pub fn latest_version_tag(dir: &Path, prefix: &str) -> Result<Option<(String, Version)>> {
+    let pattern = format!("{}*", prefix);
+    let tags = git(dir, &["tag", "--merged", "HEAD", "--list", &pattern])?;
+    Ok(tags
+        .lines()
+        .filter_map(|tag| {
+            let version = Version::parse(tag.strip_prefix(prefix)?).ok()?;
+            Some((tag.to_string(), version))
+        })
+        .max_by(|(_, a), (_, b)| a.cmp(b)))
+}
+
+/// The contents of `file` at revision `rev`
+pub fn show_file(file: &Path, rev: &str) -> Result<String> {
+    let name = file
+        .file_name()
+        .with_context(|| format!("Not a file: {}", file.display()))?;
+    let object = format!("{}:./{}", rev, name.to_string_lossy());
+    git(&repo_dir(file), &["show", &object])
+}
+
+/// Directory to run git in for a file
+pub fn repo_dir(file: &Path) -> PathBuf {
+    match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// The commit hash of HEAD, abbreviated with `short`
+pub fn head_sha(dir: &Path, short: bool) -> Result<String> {
+    match short {
+        true => git(dir, &["rev-parse", "--short", "HEAD"]),
+        false => git(dir, &["rev-parse", "HEAD"]),
+    }
+}
+
+/// Run git in `dir`, returning its trimmed stdout
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Print a tag name, also recording it as the `tag` step output on GitHub Actions
+pub fn emit_tag_name(tag: &str) -> Result<()> {
+    println!("{}", tag);
+    if let Ok(output) = env::var("GITHUB_OUTPUT") {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&output)
+            .with_context(|| format!("Failed to open {}", output))?;
+        writeln!(file, "tag={}", tag)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_name() -> Result<()> {
+        let opts = TagOptions::default();
+        assert_eq!(opts.tag_name("1.3.0")?, "v1.3.0");
+
+        let opts = TagOptions {
+            tag_template: Some("{prefix}{major}.{minor}/release-{version}".to_string()),
+            tag_prefix: "app-".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(opts.tag_name("1.3.0-rc.1")?, "app-1.3/release-1.3.0-rc.1");
+        Ok(())
+    }
+
+    fn init_repo() -> Result<tempfile::TempDir> {
+        let dir = tempfile::tempdir()?;
+        for args in [
+            &["init", "--quiet"][..],
+            &["config", "user.name", "svbump"],
+            &["config", "user.email", "svbump@example.com"],
+            &["config", "commit.gpgSign", "false"],
+            &["config", "tag.gpgSign", "false"],
+            &["commit", "--quiet", "--allow-empty", "--message", "init"],
+        ] {
+            git(dir.path(), args)?;
+        }
+        Ok(dir)
+    }
+
+    #[test]
+    fn test_record_tag() -> Result<()> {
+        let dir = init_repo()?;
+        let repo = dir.path();
+        let file = repo.join("package.json");
+
+        let opts = GitOptions {
+            tag: true,
+            ..Default::default()
+        };
+        opts.record(&[&file], &TagOptions::default(), "1.3.0")?;
+        assert_eq!(git(repo, &["cat-file", "-t", "v1.3.0"])?, "commit");
+
+        let opts = GitOptions {
+            tag: true,
+            tag_message: Some("Release {version}".to_string()),
+            ..Default::default()
+        };
+        opts.record(&[&file], &TagOptions::default(), "1.4.0")?;
+        assert_eq!(git(repo, &["cat-file", "-t", "v1.4.0"])?, "tag");
+        assert_eq!(
+            git(repo, &["tag", "-l", "--format=%(contents)", "v1.4.0"])?,
+            "Release 1.4.0"
+        );
+
+        assert!(opts
+            .record(&[&file], &TagOptions::default(), "1.4.0")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_commit() -> Result<()> {
+        let dir = init_repo()?;
+        let repo = dir.path();
+        let file = repo.join("package.json");
+        let other = repo.join("notes.txt");
+        fs::write(&file, r#"{"version": "1.3.0"}"#)?;
+        fs::write(&other, "unrelated")?;
+        git(repo, &["add", "notes.txt"])?;
+
+        let opts = GitOptions {
+            commit: true,
+            ..Default::default()
+        };
+        let err = opts.preflight(&[&file]).unwrap_err();
+        assert!(err.to_string().contains("notes.txt"));
+
+        let opts = GitOptions {
+            allow_dirty: true,
+            ..opts
+        };
+        opts.preflight(&[&file])?;
+        opts.record(&[&file], &TagOptions::default(), "1.3.0")?;
+        assert_eq!(
+            git(repo, &["log", "-1", "--format=%s"])?,
+            "chore: release 1.3.0"
+        );
+        assert_eq!(
+            git(repo, &["show", "--name-only", "--format="])?,
+            "package.json"
+        );
+        assert_eq!(
+            git(repo, &["diff", "--cached", "--name-only"])?,
+            "notes.txt"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_unreleased() -> Result<()> {
+        let dir = init_repo()?;
+        let file = dir.path().join("package.json");
+        git(dir.path(), &["tag", "v1.3.0"])?;
+
+        let opts = TagOptions {
+            fail_if_tag_exists: true,
+            ..Default::default()
+        };
+        assert!(opts.check_unreleased(&file, "1.3.0").is_err());
+        opts.check_unreleased(&file, "1.4.0")?;
+        TagOptions::default().check_unreleased(&file, "1.3.0")?;
+
+        let outside = tempfile::tempdir()?;
+        assert!(opts
+            .check_unreleased(&outside.path().join("package.json"), "1.4.0")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_history_since_last_tag() -> Result<()> {
+        let dir = init_repo()?;
+        let repo = dir.path();
+        let commit = |message: &str| {
+            git(
+                repo,
+                &["commit", "--quiet", "--allow-empty", "--message", message],
+            )
+        };
+
+        let (tag, commits) = history_since_last_tag(repo)?;
+        assert_eq!((tag, commits.len()), (None, 1));
+
+        commit("feat: before the tag")?;
+        git(repo, &["tag", "v1.0.0"])?;
+        commit("fix: after the tag")?;
+        commit("docs: readme\n\nlonger body")?;
+
+        let (tag, commits) = history_since_last_tag(repo)?;
+        assert_eq!(tag.as_deref(), Some("v1.0.0"));
+        let messages: Vec<_> = commits.iter().map(|c| c.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            ["docs: readme\n\nlonger body", "fix: after the tag"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_latest_version_tag() -> Result<()> {
+        let dir = init_repo()?;
+        let repo = dir.path();
+        assert!(latest_version_tag(repo, "v")?.is_none());
+
+        for tag in ["v1.2.0", "v1.10.0", "v1.9.0-rc.1", "nightly", "v2"] {
+            git(repo, &["tag", tag])?;
+        }
+        git(repo, &["checkout", "--quiet", "-b", "next"])?;
+        git(
+            repo,
+            &["commit", "--quiet", "--allow-empty", "--message", "next"],
+        )?;
+        git(repo, &["tag", "v3.0.0"])?;
+        git(repo, &["checkout", "--quiet", "-"])?;
+
+        let (tag, version) = latest_version_tag(repo, "v")?.unwrap();
+        assert_eq!((tag.as_str(), version), ("v1.10.0", Version::new(1, 10, 0)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_file() -> Result<()> {
+        let dir = init_repo()?;
+        let repo = dir.path();
+        let file = repo.join("package.json");
+        fs::write(&file, r#"{"version": "1.2.3"}"#)?;
+        git(repo, &["add", "package.json"])?;
+        git(repo, &["commit", "--quiet", "--message", "add"])?;
+        fs::write(&file, r#"{"version": "1.3.0"}"#)?;
+
+        assert_eq!(show_file(&file, "HEAD")?, r#"{"version": "1.2.3"}"#);
+        assert!(show_file(&file, "HEAD~1").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+//! Locating string assignments in Groovy and Kotlin Gradle scripts such as
+//! build.gradle and build.gradle.kts
+
+use anyhow::Result;
+use regex::Regex;
+use std::ops::Range;
+
+/// Pattern for the lines assigning a quoted string to `name`, as
+/// `name = "1.2.3"`, `name '1.2.3'`, `name("1.2.3")` or
+/// `val name: String = "1.2.3"`, capturing the value inside the quotes as
+/// `double` or `single`
+fn pattern(name: &str) -> Result<Regex> {
+    let pattern = format!(
+        r#"(?m)^[ \t]*(?:(?:val|var|def|const[ \t]+val)[ \t]+)?{}[ \t]*(?::[ \t]*String[ \t]*)?(?:=[ \t]*|\([ \t]*|[ \t]+)(?:"(?P<double>[^"\r\n]*)"|'(?P<single>[^'\r\n]*)')"#,
+        regex::escape(name)
+    );
+    Ok(Regex::new(&pattern)?)
+}
+
+/// Byte ranges of the `/* */` comments in `content`
+fn block_comments(content: &str) -> Vec<Range<usize>> {
+    let mut comments = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = content[pos..].find("/*").map(|i| pos + i) {
+        let end = content[start + 2..]
+            .find("*/")
+            .map_or(content.len(), |i| start + 2 + i + 2);
+        comments.push(start..end);
+        pos = end;
+    }
+    comments
+}
+
+/// Byte range of the string assigned to `name`, which must be assigned
+/// exactly once outside of comments and without `$` interpolation
+pub fn locate(content: &str, name: &str) -> Result<Option<Range<usize>>> {
+    let comments = block_comments(content);
+    let values: Vec<_> = pattern(name)?
+        .captures_iter(content)
+        .filter(|captures| {
+            let start = captures.get(0).unwrap().start();
+            !comments.iter().any(|comment| comment.contains(&start))
+        })
+        .map(|captures| captures.name("double").or(captures.name("single")).unwrap())
+        .collect();
+    match values.as_slice() {
+        [] => Ok(None),
+        [value] if value.as_str().contains('$') => anyhow::bail!(
+            "{} is interpolated ({}), so it can't be bumped in place",
+            name,
+            value.as_str()
+        ),
+        [value] => Ok(Some(value.range())),
+        _ => anyhow::bail!(
+            "{} is assigned {} times; use --type regex to choose one",
+            name,
+            values.len()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() -> Result<()> {
+        let content = r#"
+plugins {
+    id 'java'
+}
+
+group = 'com.example'
+version = "1.2.3"
+// version = "0.0.1"
+/*
+version = "0.0.2"
+*/
+def appVersion '2.0.0'
+val libVersion: String = "3.1.0"
+sourceCompatibility("17")
+description = "v${version}"
+"#;
+        let value =
+            |name| locate(content, name).map(|range| range.map(|range| content[range].to_string()));
+        assert_eq!(value("version")?.as_deref(), Some("1.2.3"));
+        assert_eq!(value("group")?.as_deref(), Some("com.example"));
+        assert_eq!(value("appVersion")?.as_deref(), Some("2.0.0"));
+        assert_eq!(value("libVersion")?.as_deref(), Some("3.1.0"));
+        assert_eq!(value("sourceCompatibility")?.as_deref(), Some("17"));
+        assert_eq!(value("missing")?, None);
+        assert!(value("description").is_err());
+
+        let twice = "version = '1.0.0'\nsubprojects {\n    version = '1.0.0'\n}\n";
+        assert!(locate(twice, "version").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,436 @@
+//! Locating string attributes in HCL files such as Terraform modules and
+//! Packer templates by their block path: the block type and labels of each
+//! enclosing block, then the attribute, then any keys of an object it holds,
+//! as in `module.vpc.version` or `terraform.required_providers.aws.version`
+
+use crate::binding::{self, Binding};
+use anyhow::Result;
+use std::ops::Range;
+
+struct Scanner<'a> {
+    content: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    bindings: Vec<Binding>,
+}
+
+/// Every attribute and object key in `content`, with its full path
+fn bindings(content: &str) -> Result<Vec<Binding>> {
+    let mut scanner = Scanner {
+        content,
+        bytes: content.as_bytes(),
+        pos: 0,
+        bindings: Vec::new(),
+    };
+    scanner.body(&[], false)?;
+    Ok(scanner.bindings)
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).copied()
+    }
+
+    /// Read the attributes and blocks of a body under `prefix`, up to and
+    /// including the `}` closing it when `nested`
+    fn body(&mut self, prefix: &[String], nested: bool) -> Result<()> {
+        loop {
+            self.skip_trivia();
+            let name = match self.peek() {
+                None if nested => anyhow::bail!("Unbalanced braces in HCL"),
+                None => return Ok(()),
+                Some(b'}') if nested => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(c) if is_ident_start(c) => self.ident().to_string(),
+                Some(c) => anyhow::bail!("Unexpected {:?} at byte {} in HCL", c as char, self.pos),
+            };
+            let mut path: Vec<String> = prefix.iter().cloned().chain([name]).collect();
+            self.skip_inline();
+            if self.peek() == Some(b'=') && self.peek_at(1) != Some(b'=') {
+                self.pos += 1;
+                let value = self.expr(Some(&path))?;
+                self.bindings.push(Binding { path, value });
+                continue;
+            }
+            // A block: its labels, then its body
+            loop {
+                self.skip_inline();
+                match self.peek() {
+                    Some(b'{') => break,
+                    Some(b'"') => {
+                        let start = self.pos;
+                        self.skip_string()?;
+                        path.push(self.content[start + 1..self.pos - 1].to_string());
+                    }
+                    Some(c) if is_ident_start(c) => path.push(self.ident().to_string()),
+                    _ => anyhow::bail!("Expected a block label or {{ at byte {}", self.pos),
+                }
+            }
+            self.pos += 1;
+            self.body(&path, true)?;
+        }
+    }
+
+    /// Skip one expression, stopping before the newline, `,` or closing
+    /// bracket that ends it. The keys of objects are recorded under `path`.
+    /// Returns the range of the contents when it is just a quoted string.
+    fn expr(&mut self, path: Option<&[String]>) -> Result<Option<Range<usize>>> {
+        self.skip_inline();
+        let start = self.pos;
+        let mut string = None;
+        loop {
+            self.skip_inline();
+            match self.peek() {
+                None | Some(b'\n' | b',' | b'}' | b']' | b')') => break,
+                Some(b'"') => {
+                    let first = self.pos == start;
+                    self.skip_string()?;
+                    if first {
+                        string = Some(start + 1..self.pos - 1);
+                        continue;
+                    }
+                }
+                Some(b'<') if self.peek_at(1) == Some(b'<') => self.skip_heredoc()?,
+                Some(b'{') => {
+                    self.pos += 1;
+                    self.object((self.pos == start + 1).then_some(path).flatten())?;
+                }
+                Some(open @ (b'[' | b'(')) => {
+                    self.pos += 1;
+                    self.group(if open == b'[' { b']' } else { b')' })?;
+                }
+                Some(_) => self.pos += 1,
+            }
+            string = None;
+        }
+        Ok(string)
+    }
+
+    /// Read the items of an object up to and including its `}`, recording
+    /// each `key = value` or `key: value` under `path`
+    fn object(&mut self, path: Option<&[String]>) -> Result<()> {
+        loop {
+            self.skip_trivia();
+            let key = match self.peek() {
+                None => anyhow::bail!("Unbalanced braces in HCL"),
+                Some(b',') => {
+                    self.pos += 1;
+                    continue;
+                }
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(b'"') => {
+                    let start = self.pos;
+                    self.skip_string()?;
+                    Some(self.content[start + 1..self.pos - 1].to_string())
+                }
+                Some(c) if is_ident_start(c) => Some(self.ident().to_string()),
+                Some(c @ (b']' | b')')) => {
+                    anyhow::bail!("Unexpected {:?} at byte {} in HCL", c as char, self.pos)
+                }
+                _ => None,
+            };
+            if key.as_deref() == Some("for") {
+                // `{ for k, v in x : k => v }` has no keys of its own
+                return self.group(b'}');
+            }
+            self.skip_inline();
+            let is_separator = match self.peek() {
+                Some(b'=') => !matches!(self.peek_at(1), Some(b'=' | b'>')),
+                Some(b':') => true,
+                _ => false,
+            };
+            if !is_separator {
+                self.expr(None)?;
+                continue;
+            }
+            self.pos += 1;
+            let child = path.zip(key).map(|(path, key)| {
+                let mut child = path.to_vec();
+                child.push(key);
+                child
+            });
+            let value = self.expr(child.as_deref())?;
+            if let Some(path) = child {
+                self.bindings.push(Binding { path, value });
+            }
+        }
+    }
+
+    /// Skip the expressions of a tuple, parentheses or `for` up to and
+    /// including `close`
+    fn group(&mut self, close: u8) -> Result<()> {
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None => anyhow::bail!("Missing {:?} in HCL", close as char),
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(b',' | b'}' | b']' | b')') => self.pos += 1,
+                Some(_) => {
+                    self.expr(None)?;
+                }
+            }
+        }
+    }
+
+    fn ident(&mut self) -> &str {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-'))
+        {
+            self.pos += 1;
+        }
+        &self.content[start..self.pos]
+    }
+
+    /// Skip spaces and comments, but not the newline ending a line
+    fn skip_inline(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\r') => self.pos += 1,
+                Some(b'#') => self.skip_line_comment(),
+                Some(b'/') if self.peek_at(1) == Some(b'/') => self.skip_line_comment(),
+                Some(b'/') if self.peek_at(1) == Some(b'*') => {
+                    self.pos = self.content[self.pos + 2..]
+                        .find("*/")
+                        .map_or(self.bytes.len(), |end| self.pos + 2 + end + 2);
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn skip_line_comment(&mut self) {
+        while self.peek().is_some_and(|c| c != b'\n') {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip spaces, comments and newlines
+    fn skip_trivia(&mut self) {
+        loop {
+            self.skip_inline();
+            if self.peek() != Some(b'\n') {
+                return;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn skip_string(&mut self) -> Result<()> {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.peek() {
+                None | Some(b'\n') => anyhow::bail!("Unterminated string at byte {}", start),
+                Some(b'\\') => self.pos = (self.pos + 2).min(self.bytes.len()),
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                // `$${` and `%%{` are escapes for a literal `${` and `%{`
+                Some(b'$' | b'%') if self.peek_at(1) == self.peek() => self.pos += 2,
+                Some(b'$' | b'%') if self.peek_at(1) == Some(b'{') => self.skip_template()?,
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// Skip a `${...}` interpolation or `%{...}` directive
+    fn skip_template(&mut self) -> Result<()> {
+        let start = self.pos;
+        self.pos += 2;
+        let mut depth = 1;
+        loop {
+            match self.peek() {
+                None => anyhow::bail!("Unterminated template at byte {}", start),
+                Some(b'"') => self.skip_string()?,
+                Some(b'{') => {
+                    depth += 1;
+                    self.pos += 1;
+                }
+                Some(b'}') => {
+                    depth -= 1;
+                    self.pos += 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// Skip a `<<EOF` or `<<-EOF` heredoc up to the newline ending its
+    /// closing line
+    fn skip_heredoc(&mut self) -> Result<()> {
+        let start = self.pos;
+        self.pos += 2;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        let marker = self.ident().to_string();
+        if marker.is_empty() {
+            // `<<` as an operator, which HCL doesn't have, or a typo
+            anyhow::bail!("Expected a heredoc marker at byte {}", start);
+        }
+        let mut lines = self.content[self.pos..].split_inclusive('\n');
+        self.pos += lines.next().map_or(0, str::len);
+        for line in lines {
+            if line.trim() == marker {
+                self.pos += line.trim_end_matches('\n').len();
+                return Ok(());
+            }
+            self.pos += line.len();
+        }
+        anyhow::bail!("Unterminated heredoc at byte {}", start)
+    }
+}
+
+fn is_ident_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_'
+}
+
+/// Byte range of the string at `path`, which must be set exactly once, to a
+/// double-quoted string without interpolation
+pub fn locate(content: &str, path: &[String]) -> Result<Option<Range<usize>>> {
+    binding::locate(content, path, bindings, is_interpolated)
+}
+
+/// Check that replacing the string at `path` turned `old` into `new` without
+/// changing anything else about its structure, by scanning `new` again
+pub fn check_edit(old: &str, new: &str, path: &[String], version: &str) -> Result<()> {
+    binding::check_edit(old, new, path, version, bindings, is_interpolated)
+}
+
+/// Whether a quoted string holds a `${` interpolation or `%{` directive,
+/// which `$${` and `%%{` escape
+fn is_interpolated(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    let mut i = 0;
+    while i + 1 < bytes.len() {
+        match (bytes[i], bytes[i + 1], bytes.get(i + 2)) {
+            (b'$', b'$', Some(b'{')) | (b'%', b'%', Some(b'{')) => i += 3,
+            (b'$' | b'%', b'{', _) => return true,
+            _ => i += 1,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_paths() -> Result<()> {
+        let content = r#"# version = "0.0.1"
+terraform {
+  required_version = ">= 1.5.0"
+  required_providers {
+    aws = {
+      source  = "hashicorp/aws" // the provider
+      version = "~> 5.31"
+    }
+  }
+}
+
+module "vpc" {
+  source  = "terraform-aws-modules/vpc/aws"
+  version = "5.1.2" /* pinned { */
+  azs     = ["eu-west-1a", "eu-west-1b"]
+  tags    = { for k, v in var.tags : k => "${v}-x" }
+  name    = "${var.name}-vpc"
+  policy  = <<-EOT
+    { "version": "0.0.0" }
+  EOT
+}
+
+variable "app_version" { default = "1.2.3" }
+
+packer {
+  required_plugins {
+    amazon = { version = ">= 1.2.8", source = "github.com/hashicorp/amazon" }
+  }
+}
+
+locals {
+  labels = { "app.kubernetes.io/version" = "3.0.0" }
+  count = 1 == 1 ? 2 : 3
+}
+"#;
+        let value = |selector: &[&str]| {
+            let path: Vec<String> = selector.iter().map(|s| s.to_string()).collect();
+            locate(content, &path).map(|range| range.map(|range| &content[range]))
+        };
+        assert_eq!(value(&["terraform", "required_version"])?, Some(">= 1.5.0"));
+        assert_eq!(
+            value(&["terraform", "required_providers", "aws", "version"])?,
+            Some("~> 5.31")
+        );
+        assert_eq!(value(&["module", "vpc", "version"])?, Some("5.1.2"));
+        assert_eq!(
+            value(&["variable", "app_version", "default"])?,
+            Some("1.2.3")
+        );
+        assert_eq!(
+            value(&["packer", "required_plugins", "amazon", "version"])?,
+            Some(">= 1.2.8")
+        );
+        assert_eq!(
+            value(&["locals", "labels", "app.kubernetes.io/version"])?,
+            Some("3.0.0")
+        );
+        assert_eq!(value(&["version"])?, None);
+        assert_eq!(value(&["module", "vpc", "policy", "version"])?, None);
+        assert!(value(&["module", "vpc", "name"]).is_err());
+        assert!(value(&["module", "vpc", "azs"]).is_err());
+        assert!(value(&["locals", "count"]).is_err());
+
+        // Blocks with the same type and labels set the same path
+        let twice =
+            "provider \"aws\" {\n  version = \"1\"\n}\nprovider \"aws\" {\n  version = \"2\"\n}\n";
+        let path = ["provider", "aws", "version"].map(String::from);
+        assert!(locate(twice, &path).is_err());
+        assert!(locate("module \"vpc\" {\n", &path).is_err());
+        assert!(locate("locals {\n  a = { ] }\n}\n", &path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpolation() -> Result<()> {
+        let content = r#"locals {
+  name     = "${var.name}-vpc"
+  suffix   = "%{ if var.beta }-beta%{ endif }"
+  template = "$${version} 100%%{x}"
+  version  = "5.1.2"
+}
+"#;
+        let path = |name: &str| ["locals", name].map(String::from);
+        assert!(locate(content, &path("name")).is_err());
+        assert!(locate(content, &path("suffix")).is_err());
+        let range = locate(content, &path("template"))?.unwrap();
+        assert_eq!(&content[range], "$${version} 100%%{x}");
+
+        // A version that would start an interpolation isn't written
+        let range = locate(content, &path("version"))?.unwrap();
+        for version in ["${x}", "%{x}"] {
+            let mut new = content.to_string();
+            new.replace_range(range.clone(), version);
+            assert!(check_edit(content, &new, &path("version"), version).is_err());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+//! Shell commands run before and after a version is written
+
+use crate::Change;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{io, process::Command};
+
+/// Commands given on the command line, run after those in svbump.toml
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct HookOptions {
+    /// Shell command to run before writing, with SVBUMP_OLD_VERSION and
+    /// SVBUMP_NEW_VERSION set; repeat to run several
+    #[arg(long, value_name = "COMMAND")]
+    pub pre_hook: Vec<String>,
+
+    /// Shell command to run after writing, e.g. to refresh a lockfile; repeat
+    /// to run several
+    #[arg(long, value_name = "COMMAND")]
+    pub post_hook: Vec<String>,
+}
+
+impl HookOptions {
+    /// The hooks of `config` followed by those given on the command line
+    pub fn with_config(&self, config: &Hooks) -> Hooks {
+        Hooks {
+            pre: config.pre.iter().chain(&self.pre_hook).cloned().collect(),
+            post: config.post.iter().chain(&self.post_hook).cloned().collect(),
+        }
+    }
+}
+
+/// The `[hooks]` table of svbump.toml
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post: Vec<String>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre.is_empty() && self.post.is_empty()
+    }
+
+    /// Run the pre-hooks, stopping at the first that fails
+    pub fn run_pre(&self, change: &Change) -> Result<()> {
+        self.pre.iter().try_for_each(|command| run(command, change))
+    }
+
+    /// Run the post-hooks, stopping at the first that fails
+    pub fn run_post(&self, change: &Change) -> Result<()> {
+        self.post
+            .iter()
+            .try_for_each(|command| run(command, change))
+    }
+}
+
+/// Run `command` with the shell, sending its output to stderr so it stays out
+/// of svbump's own output
+fn run(command: &str, change: &Change) -> Result<()> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let status = Command::new(shell)
+        .arg(flag)
+        .arg(command)
+        .env("SVBUMP_OLD_VERSION", &change.old)
+        .env("SVBUMP_NEW_VERSION", &change.new)
+        .stdout(io::stderr())
+        .status()
+        .with_context(|| format!("Failed to run hook `{}`", command))?;
+    if !status.success() {
+        anyhow::bail!("Hook `{}` failed with {}", command, status);
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hooks_see_versions() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let out = dir.path().join("out");
+        let hooks = HookOptions {
+            pre_hook: vec![format!(
+                "echo \"$SVBUMP_OLD_VERSION -> $SVBUMP_NEW_VERSION\" > {}",
+                out.display()
+            )],
+            post_hook: vec!["exit 3".to_string()],
+        }
+        .with_config(&Hooks::default());
+        let change = Change {
+            old: "1.2.3".to_string(),
+            new: "1.3.0".to_string(),
+        };
+        hooks.run_pre(&change)?;
+        assert_eq!(std::fs::read_to_string(&out)?, "1.2.3 -> 1.3.0\n");
+        assert!(hooks.run_post(&change).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+//! Container image references such as `ghcr.io/acme/app:1.2.3@sha256:...`,
+//! whose tag is bumped on its own
+
+use std::ops::Range;
+
+/// The segment selecting the tag of an image reference held in a string, as
+/// in `spec.template.spec.containers[0].image.tag`
+pub const TAG_SEGMENT: &str = "tag";
+
+/// Byte range of the tag in `reference`, between the repository (which may
+/// have a registry port, `localhost:5000/app`) and any `@digest`
+pub fn tag_range(reference: &str) -> Option<Range<usize>> {
+    let name_end = reference.find('@').unwrap_or(reference.len());
+    let colon = reference[..name_end].rfind(':')?;
+    let tag = colon + 1..name_end;
+    (!tag.is_empty() && !reference[tag.clone()].contains('/')).then_some(tag)
+}
+
+/// Whether `reference` is pinned to a digest, which wins over its tag
+pub fn has_digest(reference: &str) -> bool {
+    reference.contains('@')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_range() {
+        let tag = |reference: &'static str| tag_range(reference).map(|range| &reference[range]);
+        assert_eq!(tag("nginx:1.25.3"), Some("1.25.3"));
+        assert_eq!(tag("ghcr.io/acme/app:2.0.0-rc.1"), Some("2.0.0-rc.1"));
+        assert_eq!(tag("localhost:5000/app:1.0.0@sha256:abc"), Some("1.0.0"));
+        assert_eq!(tag("localhost:5000/app"), None);
+        assert_eq!(tag("app@sha256:abc"), None);
+        assert_eq!(tag("app:"), None);
+    }
+}
@@ -0,0 +1,73 @@
+//! Locating values in INI files such as setup.cfg and .bumpversion.cfg
+
+use std::ops::Range;
+
+/// Byte range of the value of `key` in `section`, or before any section when
+/// `section` is empty; inline `;` and `#` comments are left out
+pub fn locate(content: &str, section: &str, key: &str) -> Option<Range<usize>> {
+    let mut current = "";
+    let mut pos = 0;
+    for line in content.split_inclusive('\n') {
+        let start = pos;
+        pos += line.len();
+        let trimmed = line.trim();
+        if trimmed.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(name) = trimmed.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            current = name.trim();
+            continue;
+        }
+        if current != section {
+            continue;
+        }
+        let Some(separator) = line.find(['=', ':']) else {
+            continue;
+        };
+        if line[..separator].trim() != key {
+            continue;
+        }
+        let value = &line[separator + 1..];
+        let value = strip_comment(value).trim_end();
+        let leading = value.len() - value.trim_start().len();
+        let value_start = start + separator + 1 + leading;
+        return Some(value_start..start + separator + 1 + value.len());
+    }
+    None
+}
+
+/// The value up to an inline comment, which starts with whitespace
+fn strip_comment(value: &str) -> &str {
+    let bytes = value.as_bytes();
+    let end = (1..bytes.len())
+        .find(|&i| matches!(bytes[i], b';' | b'#') && bytes[i - 1].is_ascii_whitespace())
+        .unwrap_or(value.len());
+    &value[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() {
+        let content = "\
+name = top
+; a comment
+[metadata]
+name = demo
+version = 1.2.3  ; release
+
+[bumpversion:file:setup.py]
+search: {current_version}
+[bumpversion]
+current_version:0.4.0
+";
+        let value = |section, key| locate(content, section, key).map(|range| &content[range]);
+        assert_eq!(value("metadata", "version"), Some("1.2.3"));
+        assert_eq!(value("bumpversion", "current_version"), Some("0.4.0"));
+        assert_eq!(value("", "name"), Some("top"));
+        assert_eq!(value("metadata", "missing"), None);
+        assert_eq!(value("options", "version"), None);
+    }
+}
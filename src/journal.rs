@@ -0,0 +1,176 @@
+//! A journal of the versions each write replaced, kept in
+//! `.svbump/history.jsonl` so `svbump undo` can put them back
+
+use crate::{load_document, write_file, InputOptions};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+/// Directory holding the journal, relative to where svbump runs
+pub const JOURNAL_DIR: &str = ".svbump";
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// A version replaced in one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replaced {
+    pub file: PathBuf,
+    pub selector: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// One bump: every version it replaced, as a line of the journal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// RFC 3339 time of the write
+    pub timestamp: String,
+    pub changes: Vec<Replaced>,
+}
+
+fn history_path(dir: &Path) -> PathBuf {
+    dir.join(JOURNAL_DIR).join(HISTORY_FILE)
+}
+
+/// Append a bump to the journal in `dir`. Files are stored as absolute paths
+/// so undo works from anywhere in the project; stdout writes aren't recorded.
+pub fn record(dir: &Path, changes: Vec<Replaced>) -> Result<()> {
+    let changes: Vec<Replaced> = changes
+        .into_iter()
+        .filter(|change| !crate::is_stdio(&change.file))
+        .map(|change| {
+            Ok(Replaced {
+                file: std::path::absolute(&change.file)?,
+                ..change
+            })
+        })
+        .collect::<Result<_>>()?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let journal = dir.join(JOURNAL_DIR);
+    if !journal.exists() {
+        fs::create_dir_all(&journal)
+            .with_context(|| format!("Failed to create {}", journal.display()))?;
+        // Keep the journal out of `git status` without touching .gitignore
+        fs::write(journal.join(".gitignore"), "*\n")?;
+    }
+    let entry = Entry {
+        timestamp: chrono::Local::now().to_rfc3339(),
+        changes,
+    };
+    let path = history_path(dir);
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+/// Every bump in the journal in `dir`, oldest first
+pub fn entries(dir: &Path) -> Result<Vec<Entry>> {
+    let path = history_path(dir);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Invalid entry on line {} of {}", i + 1, path.display()))
+        })
+        .collect()
+}
+
+/// Put back the versions replaced by the last bump in the journal in `dir`
+/// and drop it from the journal, so the next undo goes one bump further back.
+/// Fails without writing if a version has changed since, unless `force`.
+/// Returns the undone bump, or None when the journal is empty.
+pub fn undo(dir: &Path, input: InputOptions, force: bool) -> Result<Option<Entry>> {
+    let mut entries = entries(dir)?;
+    let Some(last) = entries.pop() else {
+        return Ok(None);
+    };
+
+    let mut docs = Vec::new();
+    for change in &last.changes {
+        let index = match docs.iter().position(|(file, _)| file == &change.file) {
+            Some(index) => index,
+            None => {
+                docs.push((change.file.clone(), load_document(&change.file, input)?));
+                docs.len() - 1
+            }
+        };
+        let doc = &mut docs[index].1;
+        let current = doc.read_version(&change.selector)?;
+        if current != change.new && !force {
+            anyhow::bail!(
+                "{} {} is {}, not {} as written on {} (pass --force to undo anyway)",
+                change.file.display(),
+                change.selector,
+                current,
+                change.new,
+                last.timestamp
+            );
+        }
+        doc.set_version(&change.selector, &change.old)?;
+    }
+    for (file, doc) in &docs {
+        write_file(file, doc.render()?)?;
+    }
+
+    let rest: String = entries
+        .iter()
+        .map(|entry| Ok(serde_json::to_string(entry)? + "\n"))
+        .collect::<Result<_>>()?;
+    write_file(history_path(dir), rest)?;
+    Ok(Some(last))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_undo() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let manifest = dir.path().join("package.json");
+        let replaced = |old: &str, new: &str| Replaced {
+            file: manifest.clone(),
+            selector: "version".to_string(),
+            old: old.to_string(),
+            new: new.to_string(),
+        };
+        let input = InputOptions::default();
+
+        fs::write(&manifest, r#"{"version": "1.2.4"}"#)?;
+        record(dir.path(), vec![replaced("1.2.3", "1.2.4")])?;
+        fs::write(&manifest, r#"{"version": "1.3.0"}"#)?;
+        record(dir.path(), vec![replaced("1.2.4", "1.3.0")])?;
+        assert_eq!(entries(dir.path())?.len(), 2);
+        assert!(dir.path().join(".svbump/.gitignore").exists());
+
+        let undone = undo(dir.path(), input, false)?.unwrap();
+        assert_eq!(undone.changes, [replaced("1.2.4", "1.3.0")]);
+        assert_eq!(fs::read_to_string(&manifest)?, r#"{"version": "1.2.4"}"#);
+
+        // A version changed since the write is only undone with force
+        fs::write(&manifest, r#"{"version": "9.9.9"}"#)?;
+        assert!(undo(dir.path(), input, false).is_err());
+        assert_eq!(entries(dir.path())?.len(), 1);
+        undo(dir.path(), input, true)?;
+        assert_eq!(fs::read_to_string(&manifest)?, r#"{"version": "1.2.3"}"#);
+        assert!(undo(dir.path(), input, false)?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,340 @@
+//! Format-preserving JSON output: edited strings and keys are replaced in the
+//! original source, so indentation, spacing, comments and the trailing newline
+//! are kept
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::ops::Range;
+
+/// A replacement in the original source
+struct Edit {
+    range: Range<usize>,
+    text: String,
+}
+
+/// Parse JSON that may hold `//` and `/* */` comments and trailing commas, as
+/// in tsconfig.json or .jsonc files
+pub fn parse(source: &str) -> serde_json::Result<JsonValue> {
+    serde_json::from_str(&strip(source))
+}
+
+/// Blank out comments and trailing commas with spaces, keeping every other
+/// byte at its offset so ranges found in the result apply to `source`
+fn strip(source: &str) -> String {
+    let mut bytes = source.as_bytes().to_vec();
+    let mut pos = 0;
+    // the last comma outside a string, cleared if a value follows it
+    let mut comma = None;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'"' => {
+                comma = None;
+                pos += 1;
+                while pos < bytes.len() && bytes[pos] != b'"' {
+                    pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'/') => {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    if bytes[pos] != b'\r' {
+                        bytes[pos] = b' ';
+                    }
+                    pos += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'*') => {
+                let end = source[pos + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| pos + 2 + end + 2);
+                for byte in &mut bytes[pos..end] {
+                    if !matches!(byte, b'\n' | b'\r') {
+                        *byte = b' ';
+                    }
+                }
+                pos = end;
+                continue;
+            }
+            b',' => comma = Some(pos),
+            b'}' | b']' => {
+                if let Some(comma) = comma.take() {
+                    bytes[comma] = b' ';
+                }
+            }
+            b' ' | b'\t' | b'\n' | b'\r' => {}
+            _ => comma = None,
+        }
+        pos += 1;
+    }
+    String::from_utf8(bytes).expect("only ASCII bytes are replaced")
+}
+
+/// Render `value`, which was parsed from `source` and then edited. Changed
+/// strings and object keys are patched in place; anything else is
+/// re-serialized with the source's indentation and trailing newline.
+pub fn render(source: &str, value: &JsonValue) -> serde_json::Result<String> {
+    match patch(source, value) {
+        Some(patched) => Ok(patched),
+        None => reformat(source, value),
+    }
+}
+
+fn patch(source: &str, value: &JsonValue) -> Option<String> {
+    let stripped = strip(source);
+    let original: JsonValue = serde_json::from_str(&stripped).ok()?;
+    let mut edits = Vec::new();
+    diff(&stripped, &original, value, &mut Vec::new(), &mut edits)?;
+
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+    let mut patched = source.to_string();
+    for edit in edits {
+        patched.replace_range(edit.range, &edit.text);
+    }
+
+    let reparsed = parse(&patched).ok()?;
+    (&reparsed == value).then_some(patched)
+}
+
+/// Pretty-print with the indentation of the first indented line in `source`
+fn reformat(source: &str, value: &JsonValue) -> serde_json::Result<String> {
+    let indent = source
+        .lines()
+        .skip(1)
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ");
+    let mut out = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    value.serialize(&mut serde_json::Serializer::with_formatter(
+        &mut out, formatter,
+    ))?;
+    let mut out = String::from_utf8(out).expect("serde_json writes UTF-8");
+    if source.ends_with("\r\n") {
+        out.push_str("\r\n");
+    } else if source.ends_with('\n') || source.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Collect the edits turning `old` into `new`, or None if they differ in a
+/// way that can't be patched in place
+fn diff(
+    source: &str,
+    old: &JsonValue,
+    new: &JsonValue,
+    path: &mut Vec<String>,
+    edits: &mut Vec<Edit>,
+) -> Option<()> {
+    if old == new {
+        return Some(());
+    }
+    match (old, new) {
+        (JsonValue::String(_), JsonValue::String(text)) => {
+            edits.push(Edit {
+                range: locate(source, path, Part::Value)?,
+                text: serde_json::to_string(text).ok()?,
+            });
+        }
+        (JsonValue::Object(old_map), JsonValue::Object(new_map))
+            if old_map.len() == new_map.len() =>
+        {
+            for ((old_key, old_value), (new_key, new_value)) in old_map.iter().zip(new_map) {
+                path.push(old_key.clone());
+                if old_key != new_key {
+                    edits.push(Edit {
+                        range: locate(source, path, Part::Key)?,
+                        text: serde_json::to_string(new_key).ok()?,
+                    });
+                }
+                diff(source, old_value, new_value, path, edits)?;
+                path.pop();
+            }
+        }
+        (JsonValue::Array(old_items), JsonValue::Array(new_items))
+            if old_items.len() == new_items.len() =>
+        {
+            for (index, (old_item, new_item)) in old_items.iter().zip(new_items).enumerate() {
+                path.push(index.to_string());
+                diff(source, old_item, new_item, path, edits)?;
+                path.pop();
+            }
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+#[derive(Clone, Copy)]
+enum Part {
+    Key,
+    Value,
+}
+
+/// Byte range, quotes included, of the key or string value at `path`
+fn locate(source: &str, path: &[String], part: Part) -> Option<Range<usize>> {
+    let mut scanner = Scanner {
+        src: source.as_bytes(),
+        pos: 0,
+    };
+    scanner.find(path, part)
+}
+
+/// Just enough of a JSON reader to find where members start and end
+struct Scanner<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn find(&mut self, path: &[String], part: Part) -> Option<Range<usize>> {
+        let (segment, rest) = path.split_first()?;
+        self.skip_whitespace();
+        let key = match self.next()? {
+            b'{' => Some(self.member(segment)?),
+            b'[' => {
+                self.element(segment.parse().ok()?)?;
+                None
+            }
+            _ => return None,
+        };
+        self.skip_whitespace();
+        match (rest.is_empty(), part) {
+            (true, Part::Key) => key,
+            (true, Part::Value) => self.string(),
+            (false, _) => self.find(rest, part),
+        }
+    }
+
+    /// Move to the value of the member called `name`, returning its key
+    fn member(&mut self, name: &str) -> Option<Range<usize>> {
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            let key_name: String = serde_json::from_slice(&self.src[key.clone()]).ok()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            self.skip_whitespace();
+            if key_name == name {
+                return Some(key);
+            }
+            self.skip_value()?;
+            self.skip_whitespace();
+            self.expect(b',')?;
+        }
+    }
+
+    /// Move to the array element at `index`
+    fn element(&mut self, index: usize) -> Option<()> {
+        for _ in 0..index {
+            self.skip_whitespace();
+            self.skip_value()?;
+            self.skip_whitespace();
+            self.expect(b',')?;
+        }
+        Some(())
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = *self.src.get(self.pos)?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.next()? == byte).then_some(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.src.get(self.pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn string(&mut self) -> Option<Range<usize>> {
+        let start = self.pos;
+        self.expect(b'"')?;
+        loop {
+            match self.next()? {
+                b'\\' => self.pos += 1,
+                b'"' => return Some(start..self.pos),
+                _ => {}
+            }
+        }
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        match *self.src.get(self.pos)? {
+            b'"' => self.string().map(|_| ()),
+            b'{' | b'[' => {
+                let mut depth = 0;
+                loop {
+                    match *self.src.get(self.pos)? {
+                        b'"' => {
+                            self.string()?;
+                            continue;
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => depth -= 1,
+                        _ => {}
+                    }
+                    self.pos += 1;
+                    if depth == 0 {
+                        return Some(());
+                    }
+                }
+            }
+            _ => {
+                while !matches!(
+                    self.src.get(self.pos),
+                    None | Some(b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r')
+                ) {
+                    self.pos += 1;
+                }
+                Some(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_preserves_formatting() -> serde_json::Result<()> {
+        let source = "{\n\t\"name\": \"demo\",\n\t\"tags\": [\"a\", {\"b\": \"x\"}],\n\t\"engines\": {\"node\": \">=18\"},\n\t\"version\":  \"1.2.3\"\n}";
+        let mut value: JsonValue = serde_json::from_str(source)?;
+        value["version"] = JsonValue::String("1.3.0".into());
+        assert_eq!(render(source, &value)?, source.replace("1.2.3", "1.3.0"));
+
+        value["tags"][1]["b"] = JsonValue::String("c".into());
+        value["tags"][0] = JsonValue::String("z".into());
+        assert_eq!(
+            render(source, &value)?,
+            source
+                .replace("1.2.3", "1.3.0")
+                .replace("[\"a\", {\"b\": \"x\"}]", "[\"z\", {\"b\": \"c\"}]")
+        );
+
+        // Structural changes are re-serialized with the same indentation
+        value["private"] = JsonValue::Bool(true);
+        let rendered = render(source, &value)?;
+        assert!(rendered.starts_with("{\n\t\"name\": \"demo\",\n"));
+        assert!(rendered.ends_with("\t\"private\": true\n}"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_comments_and_trailing_commas() -> serde_json::Result<()> {
+        let source = "// tsconfig\n{\n  /* the \"version\": \"0.0.0\" */\n  \"url\": \"http://x//y\", // why\n  \"version\": \"1.2.3\",\n  \"list\": [1, 2,],\n}\n";
+        let mut value = parse(source)?;
+        assert_eq!(value["url"], "http://x//y");
+        assert_eq!(value["list"], serde_json::json!([1, 2]));
+
+        value["version"] = JsonValue::String("1.3.0".into());
+        assert_eq!(render(source, &value)?, source.replace("1.2.3", "1.3.0"));
+        assert!(parse("{\"a\": 1,, }").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,3199 @@
+//! Reading and bumping versions in JSON, TOML, YAML and text files while
+//! keeping the rest of each file intact.
+//!
+//! ```no_run
+//! use svbump::{Document, VersionBump};
+//!
+//! let mut doc = Document::open("Cargo.toml")?;
+//! let change = doc.bump("package.version", &VersionBump::Minor)?;
+//! println!("{} -> {}", change.old, change.new);
+//! doc.save("Cargo.toml")?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate, Utc};
+use clap::ValueEnum;
+use encoding::{strip_bom, Encoding};
+use semver::{BuildMetadata, Prerelease, Version, VersionReq};
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+use std::{
+    fs,
+    io::{self, Write},
+    ops::Range,
+    path::Path,
+};
+use toml_edit::{DocumentMut, Item, Table, TableLike, Value as TomlValue};
+
+pub mod backup;
+pub mod binding;
+pub mod calver;
+pub mod changelog;
+pub mod config;
+pub mod conventional;
+pub mod cue;
+pub mod defaults;
+pub mod dhall;
+pub mod dockerfile;
+pub mod encoding;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod git;
+pub mod gradle;
+pub mod hcl;
+pub mod hook;
+pub mod image;
+pub mod ini;
+pub mod journal;
+pub mod json;
+pub mod lock;
+pub mod maven;
+pub mod nix;
+pub mod npm;
+pub mod pep440;
+pub mod plist;
+pub mod plugin;
+pub mod prompt;
+pub mod properties;
+pub mod remote;
+pub mod selector;
+pub mod text;
+pub mod verbosity;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod workspace;
+pub mod xml;
+pub mod yaml;
+
+use pep440::Pep440;
+use remote::RemotePath;
+use text::{TextDocument, TextFormat};
+
+#[derive(Debug, Clone)]
+pub enum VersionBump {
+    Major,
+    Minor,
+    Patch,
+    /// Pre-release of the current version (`alpha`, `beta`, `rc`), or `pre`
+    /// with the identifier from `--pre-id`
+    Pre(Option<String>),
+    /// Drop the pre-release and build metadata of a pre-release version
+    Release,
+    /// Set the build metadata from `--build-template`, keeping the rest
+    Build,
+    /// Next post-release (`1.2.3.post1`), for `--scheme pep440`
+    Post,
+    /// Next dev release (`1.2.3.dev1`), for `--scheme pep440`
+    Dev,
+    /// Fourth component of a four-part version, for `--scheme quad`
+    Revision,
+    /// Next epoch (`1:1.2.3` -> `2:1.2.3`), for `--scheme epoch`
+    Epoch,
+    Specific(Version),
+    /// A version that isn't semver, such as `1.2.3.4` or `1.2.3.post1`, for
+    /// schemes with their own syntax
+    Literal(String),
+}
+
+impl VersionBump {
+    /// The version a specific or literal level sets
+    fn target(&self) -> Option<String> {
+        match self {
+            VersionBump::Specific(version) => Some(version.to_string()),
+            VersionBump::Literal(version) => Some(version.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for VersionBump {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "major" => Ok(VersionBump::Major),
+            "minor" => Ok(VersionBump::Minor),
+            "patch" => Ok(VersionBump::Patch),
+            id @ ("alpha" | "beta" | "rc") => Ok(VersionBump::Pre(Some(id.to_string()))),
+            "pre" => Ok(VersionBump::Pre(None)),
+            "release" => Ok(VersionBump::Release),
+            "build" => Ok(VersionBump::Build),
+            "post" => Ok(VersionBump::Post),
+            "dev" => Ok(VersionBump::Dev),
+            "revision" => Ok(VersionBump::Revision),
+            "epoch" => Ok(VersionBump::Epoch),
+            _ => match Version::parse(s) {
+                Ok(new_version) => Ok(VersionBump::Specific(new_version)),
+                Err(_) if s.starts_with(|c: char| c.is_ascii_digit()) => {
+                    Ok(VersionBump::Literal(s.to_string()))
+                }
+                Err(err) => Err(err.into()),
+            },
+        }
+    }
+}
+
+/// Options that choose which version in a document is bumped
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct TargetOptions {
+    /// Treat the last selector segment as a version-valued key and rename it
+    #[arg(long)]
+    pub bump_key: bool,
+
+    /// Select the `[[package]]` entry with this name in a Cargo.lock
+    #[arg(long, conflicts_with = "bump_key")]
+    pub package: Option<String>,
+
+    /// Also increment the integer build number at this selector in the same
+    /// file, e.g. CFBundleVersion in an Info.plist
+    #[arg(long, value_name = "SELECTOR")]
+    pub build_number: Option<String>,
+
+    /// Create the selector's key, and any tables or maps on the way to it,
+    /// when it doesn't exist yet; the level must then be a version
+    #[arg(long, conflicts_with_all = ["bump_key", "package"])]
+    pub create: bool,
+}
+
+/// Options that adjust how a new version is computed
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct BumpOptions {
+    /// Lowercase pre-release identifiers of the new version
+    #[arg(long, conflicts_with = "preserve_pre_case")]
+    pub normalize_pre: bool,
+
+    /// Keep pre-release identifiers exactly as given (the default)
+    #[arg(long)]
+    pub preserve_pre_case: bool,
+
+    /// Refuse bumps larger than this level
+    #[arg(long, value_enum)]
+    pub max_level: Option<BumpLevel>,
+
+    /// Only bump if the current version matches this requirement (e.g. "<2.0.0")
+    #[arg(long)]
+    pub only_if: Option<VersionReq>,
+
+    /// Skip instead of failing when a specific version is not greater than the current one
+    #[arg(long)]
+    pub set_if_greater: bool,
+
+    /// Allow a specific version lower than the current one, e.g. to revert a release
+    #[arg(long, conflicts_with = "set_if_greater")]
+    pub force: bool,
+
+    /// Treat setting the current version as a change rather than a skipped
+    /// bump, so re-runs succeed even with --no-op-exit-nonzero
+    #[arg(long, conflicts_with = "set_if_greater")]
+    pub allow_equal: bool,
+
+    /// Allow a specific version that is a lower pre-release of the same
+    /// release, e.g. 2.0.0-alpha.1 when the current version is 2.0.0-beta.5
+    #[arg(long)]
+    pub allow_prerelease_downgrade: bool,
+
+    /// Carry a `YYYYMMDD` pre-release segment into the new version, updated to today
+    #[arg(long)]
+    pub refresh_date_pre: bool,
+
+    /// Pre-release identifier for the `pre` level (e.g. "canary")
+    #[arg(long, value_name = "ID")]
+    pub pre_id: Option<String>,
+
+    /// Build metadata set by the build level, with `{git_short_sha}`,
+    /// `{git_sha}`, `{timestamp}` (UTC `YYYYMMDDhhmmss`) and `{date}`
+    /// placeholders (default: {git_short_sha})
+    #[arg(long, value_name = "TEMPLATE")]
+    pub build_template: Option<String>,
+
+    /// Keep an integer build number after `+` (`1.2.3+45`, as in a Flutter
+    /// pubspec.yaml) and increment it with every bump; the build level then
+    /// increments only the build number
+    #[arg(long, conflicts_with = "build_template")]
+    pub bump_build_number: bool,
+
+    /// Version scheme; with calver the release level sets a date-based version
+    #[arg(long, value_enum, default_value_t = Scheme::Semver)]
+    pub scheme: Scheme,
+
+    /// Format of calendar versions, using calver.org tokens (default: YYYY.MM.MICRO)
+    #[arg(long, value_name = "FORMAT")]
+    pub calver_format: Option<String>,
+
+    /// Fixed date used instead of the system clock
+    #[arg(skip)]
+    pub today: Option<NaiveDate>,
+}
+
+/// How versions are numbered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Scheme {
+    /// Semantic versions, bumped by level
+    #[default]
+    Semver,
+    /// Calendar versions such as 2024.5.0, set from the date by the release level
+    Calver,
+    /// Python versions such as 1.2.3a1 or 1.2.3.post1 (PEP 440)
+    Pep440,
+    /// Four-component versions such as 1.2.3.4 (.NET assembly versions)
+    Quad,
+    /// Semantic versions behind an epoch such as 2:1.4.0 (Debian), which
+    /// levels other than epoch keep
+    Epoch,
+}
+
+impl BumpOptions {
+    fn today(&self) -> NaiveDate {
+        self.today.unwrap_or_else(|| Local::now().date_naive())
+    }
+
+    /// Build metadata rendered from `--build-template`, with git placeholders
+    /// filled in from the repository in the current directory
+    fn build_metadata(&self) -> Result<BuildMetadata> {
+        let template = self.build_template.as_deref().unwrap_or("{git_short_sha}");
+        let mut metadata = template.strip_prefix('+').unwrap_or(template).to_string();
+        for (placeholder, short) in [("{git_short_sha}", true), ("{git_sha}", false)] {
+            if metadata.contains(placeholder) {
+                let sha = git::head_sha(Path::new("."), short)?;
+                metadata = metadata.replace(placeholder, &sha);
+            }
+        }
+        let metadata = metadata
+            .replace(
+                "{timestamp}",
+                &Utc::now().format("%Y%m%d%H%M%S").to_string(),
+            )
+            .replace("{date}", &self.today().format("%Y%m%d").to_string());
+        BuildMetadata::new(&metadata)
+            .with_context(|| format!("Invalid build metadata: {}", metadata))
+    }
+}
+
+/// Returned by `bump_semver` when a bump is deliberately not applied
+#[derive(Debug)]
+pub struct Skipped {
+    pub current: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for Skipped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "skipped: {}", self.reason)
+    }
+}
+
+impl std::error::Error for Skipped {}
+
+/// A version replaced in a document
+#[derive(Debug)]
+pub struct Change {
+    pub old: String,
+    pub new: String,
+}
+
+/// Size of a change between two versions, ordered from smallest to largest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum BumpLevel {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl BumpLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BumpLevel::Patch => "patch",
+            BumpLevel::Minor => "minor",
+            BumpLevel::Major => "major",
+        }
+    }
+
+    /// The most significant release segment that differs between two versions
+    pub fn between(from: &Version, to: &Version) -> Option<Self> {
+        if from.major != to.major {
+            Some(BumpLevel::Major)
+        } else if from.minor != to.minor {
+            Some(BumpLevel::Minor)
+        } else if from.patch != to.patch {
+            Some(BumpLevel::Patch)
+        } else {
+            None
+        }
+    }
+}
+
+/// Options that control how input files are parsed
+#[derive(Debug, Clone, Copy, Default, clap::Args)]
+pub struct InputOptions {
+    /// Force specific file type
+    #[arg(short = 't', long = "type", value_enum)]
+    pub file_type: Option<FileType>,
+
+    /// Sniff the file type from content if the given or detected type fails to parse
+    #[arg(long, global = true)]
+    pub type_fallback: bool,
+}
+
+/// A parsed file in one of the supported formats
+pub enum Document {
+    /// The parsed document and the BOM and line ending of its source
+    Toml(DocumentMut, Encoding),
+    /// The parsed value and the source it was parsed from
+    Yaml(YamlValue, String),
+    /// The parsed value and the source it was parsed from
+    Json(JsonValue, String),
+    Text(TextDocument),
+}
+
+impl Document {
+    pub fn parse(content: &str, file_type: &str) -> Result<Self> {
+        match file_type {
+            "toml" => Ok(Document::Toml(
+                strip_bom(content).parse::<DocumentMut>()?,
+                Encoding::detect(content),
+            )),
+            "makefile" => Ok(Document::Text(TextDocument::new(
+                content,
+                TextFormat::Makefile,
+            ))),
+            "env" => Ok(Document::Text(TextDocument::new(content, TextFormat::Env))),
+            "ini" => Ok(Document::Text(TextDocument::new(content, TextFormat::Ini))),
+            "properties" => Ok(Document::Text(TextDocument::new(
+                content,
+                TextFormat::Properties,
+            ))),
+            "gradle" => Ok(Document::Text(TextDocument::new(
+                content,
+                TextFormat::Gradle,
+            ))),
+            "xml" => Ok(Document::Text(TextDocument::new(content, TextFormat::Xml))),
+            "plist" => Ok(Document::Text(TextDocument::new(
+                content,
+                TextFormat::Plist,
+            ))),
+            "nix" => Ok(Document::Text(TextDocument::new(content, TextFormat::Nix))),
+            "hcl" => Ok(Document::Text(TextDocument::new(content, TextFormat::Hcl))),
+            "cue" => Ok(Document::Text(TextDocument::new(content, TextFormat::Cue))),
+            "dhall" => Ok(Document::Text(TextDocument::new(
+                content,
+                TextFormat::Dhall,
+            ))),
+            "dockerfile" => Ok(Document::Text(TextDocument::new(
+                content,
+                TextFormat::Dockerfile,
+            ))),
+            "plain" => Ok(Document::Text(TextDocument::new(
+                content,
+                TextFormat::Plain,
+            ))),
+            "regex" => Ok(Document::Text(TextDocument::new(
+                content,
+                TextFormat::Regex,
+            ))),
+            "yml" | "yaml" => Ok(Document::Yaml(
+                serde_yaml::from_str(strip_bom(content))?,
+                content.to_string(),
+            )),
+            _ => Ok(Document::Json(
+                json::parse(strip_bom(content))
+                    .context("Failed to parse JSON with preserved ordering")?,
+                content.to_string(),
+            )),
+        }
+    }
+
+    /// Open a local file, with its type detected from the file name
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        load_document(path.as_ref(), InputOptions::default())
+    }
+
+    /// Bump the version at `selector` with the default options
+    pub fn bump(&mut self, selector: &str, level: &VersionBump) -> Result<Change> {
+        self.bump_version(selector, level, &BumpOptions::default())
+    }
+
+    /// Write the document to `path`, keeping the original formatting
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_file(path, self.render()?)
+    }
+
+    pub fn read_version(&self, selector: &str) -> Result<String> {
+        let selector = &self.resolve_selector(selector)?;
+        match self {
+            Document::Toml(doc, _) => read_version_toml(doc, selector),
+            Document::Yaml(value, _) => read_version_yaml(value, selector),
+            Document::Json(value, _) => read_version_json(value, selector),
+            Document::Text(doc) => doc.read_version(selector),
+        }
+    }
+
+    /// Replace the version at `selector` without any ordering checks
+    pub fn set_version(&mut self, selector: &str, version: &str) -> Result<Change> {
+        let selector = &self.resolve_selector(selector)?;
+        let update = |_: &str| Ok(version.to_string());
+        match self {
+            Document::Toml(doc, _) => update_version_toml(doc, selector, update),
+            Document::Yaml(value, _) => update_version_yaml(value, selector, update),
+            Document::Json(value, _) => update_version_json(value, selector, update),
+            Document::Text(doc) => {
+                let old = doc.read_version(selector)?;
+                doc.set_version(selector, version)?;
+                Ok(Change {
+                    old,
+                    new: version.to_string(),
+                })
+            }
+        }
+    }
+
+    pub fn bump_version(
+        &mut self,
+        selector: &str,
+        level: &VersionBump,
+        opts: &BumpOptions,
+    ) -> Result<Change> {
+        let selector = &self.resolve_selector(selector)?;
+        match self {
+            Document::Toml(doc, _) => bump_version_toml(doc, selector, level, opts),
+            Document::Yaml(value, _) => bump_version_yaml(value, selector, level, opts),
+            Document::Json(value, _) => bump_version_json(value, selector, level, opts),
+            Document::Text(doc) => {
+                let old = doc.read_version(selector)?;
+                let new = bump_semver(&old, level, opts)?;
+                doc.set_version(selector, &new)?;
+                Ok(Change { old, new })
+            }
+        }
+    }
+
+    /// Rename a version-valued key to its bumped version
+    pub fn bump_key(
+        &mut self,
+        selector: &str,
+        level: &VersionBump,
+        opts: &BumpOptions,
+    ) -> Result<Change> {
+        match self {
+            Document::Toml(doc, _) => bump_key_toml(doc, selector, level, opts),
+            Document::Yaml(value, _) => bump_key_yaml(value, selector, level, opts),
+            Document::Json(value, _) => bump_key_json(value, selector, level, opts),
+            Document::Text(_) => anyhow::bail!("--bump-key is not supported for this file type"),
+        }
+    }
+
+    /// Expand `*` wildcards in selector segments, requiring each to match
+    /// exactly one key so that a single version is chosen
+    fn resolve_selector(&self, selector: &str) -> Result<String> {
+        // A regex selector is a pattern, not a path to expand
+        let is_regex = matches!(self, Document::Text(doc) if doc.format() == TextFormat::Regex);
+        if is_regex || !selector.contains('*') {
+            return Ok(selector.to_string());
+        }
+
+        let mut resolved: Vec<String> = Vec::new();
+        for part in selector::parse(selector)? {
+            if !part.contains('*') {
+                resolved.push(part);
+                continue;
+            }
+            let pattern = glob::Pattern::new(&part)?;
+            let parents: Vec<&str> = resolved.iter().map(String::as_str).collect();
+            let mut matches = self.keys_at(&parents)?;
+            matches.retain(|key| pattern.matches(key));
+            match matches.len() {
+                0 => anyhow::bail!("No keys match {} in selector {}", part, selector),
+                1 => resolved.extend(matches),
+                _ => anyhow::bail!(
+                    "Selector {} is ambiguous, found fields matching {}: {}",
+                    selector,
+                    part,
+                    matches.join(", ")
+                ),
+            }
+        }
+        let resolved = selector::join(&resolved);
+        verbosity::note(format_args!(
+            "selector {} resolved to {}",
+            selector, resolved
+        ));
+        Ok(resolved)
+    }
+
+    /// Every selector a selector with `*` segments matches, keeping only
+    /// the paths that hold a version. Unlike reading, where a wildcard has to
+    /// match exactly one key, this is how `packages.*.version` reaches each
+    /// package.
+    pub fn expand_selector(&self, selector: &str) -> Result<Vec<String>> {
+        let mut paths: Vec<Vec<String>> = vec![Vec::new()];
+        for part in selector::parse(selector)? {
+            if !part.contains('*') {
+                paths.iter_mut().for_each(|path| path.push(part.clone()));
+                continue;
+            }
+            let pattern = glob::Pattern::new(&part)?;
+            let mut expanded = Vec::new();
+            for path in paths {
+                let parents: Vec<&str> = path.iter().map(String::as_str).collect();
+                let Ok(keys) = self.keys_at(&parents) else {
+                    continue;
+                };
+                for key in keys.into_iter().filter(|key| pattern.matches(key)) {
+                    expanded.push(path.iter().cloned().chain([key]).collect());
+                }
+            }
+            paths = expanded;
+        }
+        let found: Vec<String> = paths
+            .iter()
+            .map(|path| selector::join(path))
+            .filter(|path| self.read_version(path).is_ok())
+            .collect();
+        if found.is_empty() {
+            anyhow::bail!("No versions match selector {}", selector);
+        }
+        Ok(found)
+    }
+
+    /// Bump each of `paths` from its own version, failing with every path
+    /// that can't be bumped for a reason other than a skip; the document is
+    /// then partly bumped and shouldn't be written
+    pub fn bump_paths(
+        &mut self,
+        paths: &[String],
+        level: &VersionBump,
+        opts: &BumpOptions,
+    ) -> Result<Vec<(String, std::result::Result<Change, Skipped>)>> {
+        let mut bumped = Vec::new();
+        let mut failed = Vec::new();
+        for path in paths {
+            match self.bump_version(path, level, opts) {
+                Ok(change) => bumped.push((path.clone(), Ok(change))),
+                Err(err) => match err.downcast::<Skipped>() {
+                    Ok(skipped) => bumped.push((path.clone(), Err(skipped))),
+                    Err(err) => failed.push(format!("  {}: {:#}", path, err)),
+                },
+            }
+        }
+        if !failed.is_empty() {
+            anyhow::bail!("Could not bump:\n{}", failed.join("\n"));
+        }
+        Ok(bumped)
+    }
+
+    /// Every string value that is a semver version, with its selector, in
+    /// document order
+    pub fn find_versions(&self) -> Result<Vec<(String, String)>> {
+        let mut found = Vec::new();
+        match self {
+            Document::Toml(doc, _) => {
+                find_versions_toml(doc.as_item(), &mut Vec::new(), &mut found)
+            }
+            Document::Yaml(value, _) => find_versions_yaml(value, &mut Vec::new(), &mut found),
+            Document::Json(value, _) => find_versions_json(value, &mut Vec::new(), &mut found),
+            Document::Text(doc) if doc.format() == TextFormat::Plain => {
+                let version = doc.read_version(".")?;
+                if Version::parse(&version).is_ok() {
+                    found.push((".".to_string(), version));
+                }
+            }
+            Document::Text(doc) => anyhow::bail!(
+                "Finding versions is not supported for {} files",
+                doc.format().as_str()
+            ),
+        }
+        Ok(found)
+    }
+
+    /// Keys of the table or map found at `parts`
+    fn keys_at(&self, parts: &[&str]) -> Result<Vec<String>> {
+        let not_a_map = || format!("No table found at {}", parts.join("."));
+        match self {
+            Document::Toml(doc, _) => {
+                let mut item = doc.as_item();
+                for part in parts {
+                    item = toml_child(item, part).with_context(not_a_map)?;
+                }
+                let table = item.as_table_like().with_context(not_a_map)?;
+                Ok(table.iter().map(|(key, _)| key.to_string()).collect())
+            }
+            Document::Yaml(value, _) => {
+                let target = if parts.is_empty() {
+                    value
+                } else {
+                    walk_yaml(value, parts)?
+                };
+                let map = target.as_mapping().with_context(not_a_map)?;
+                Ok(map
+                    .keys()
+                    .filter_map(|key| key.as_str())
+                    .map(String::from)
+                    .collect())
+            }
+            Document::Json(value, _) => {
+                let target = if parts.is_empty() {
+                    value
+                } else {
+                    walk_json(value, parts)?
+                };
+                let map = target.as_object().with_context(not_a_map)?;
+                Ok(map.keys().cloned().collect())
+            }
+            Document::Text(_) => {
+                anyhow::bail!("Wildcard selectors are not supported for this file type")
+            }
+        }
+    }
+
+    pub fn read_package_version(&self, package: &str, selector: &str) -> Result<String> {
+        match self {
+            Document::Toml(doc, _) => read_version_toml(lock_package(doc, package)?, selector),
+            _ => anyhow::bail!("--package is only supported for Cargo.lock files"),
+        }
+    }
+
+    pub fn bump_package_version(
+        &mut self,
+        package: &str,
+        selector: &str,
+        level: &VersionBump,
+        opts: &BumpOptions,
+    ) -> Result<Change> {
+        match self {
+            Document::Toml(doc, _) => {
+                bump_version_toml(lock_package_mut(doc, package)?, selector, level, opts)
+            }
+            _ => anyhow::bail!("--package is only supported for Cargo.lock files"),
+        }
+    }
+
+    /// Bump the version chosen by `selector` and the target options
+    pub fn apply_bump(
+        &mut self,
+        selector: &str,
+        level: &VersionBump,
+        target: &TargetOptions,
+        opts: &BumpOptions,
+    ) -> Result<Change> {
+        let change = if target.bump_key {
+            self.bump_key(selector, level, opts)?
+        } else if let Some(package) = &target.package {
+            self.bump_package_version(package, selector, level, opts)?
+        } else if target.create && !self.contains(selector)? {
+            let version = level.target().with_context(|| {
+                format!(
+                    "{} doesn't exist yet, so --create needs a version like 1.0.0 instead of a level",
+                    selector
+                )
+            })?;
+            self.create_version(selector, &version)?
+        } else {
+            self.bump_version(selector, level, opts)?
+        };
+        if let Some(build_number) = &target.build_number {
+            self.increment_build_number(build_number)?;
+        }
+        Ok(change)
+    }
+
+    /// Whether anything exists at `selector`
+    pub fn contains(&self, selector: &str) -> Result<bool> {
+        let selector = &self.resolve_selector(selector)?;
+        let parts = selector::parse(selector)?;
+        Ok(match self {
+            Document::Toml(doc, _) => parts
+                .iter()
+                .try_fold(doc.as_item(), |item, part| toml_child(item, part))
+                .is_some(),
+            Document::Yaml(value, _) => walk_yaml(value, &parts).is_ok(),
+            Document::Json(value, _) => walk_json(value, &parts).is_ok(),
+            Document::Text(doc) => doc.read_version(selector).is_ok(),
+        })
+    }
+
+    /// Add the version at `selector`, creating the tables or maps on the way
+    /// to it. The old version of the returned change is empty.
+    pub fn create_version(&mut self, selector: &str, version: &str) -> Result<Change> {
+        if self.contains(selector)? {
+            anyhow::bail!("{} already exists", selector);
+        }
+        let parts = selector::parse(selector)?;
+        match self {
+            Document::Toml(doc, _) => create_toml(doc.as_item_mut(), &parts, version)?,
+            Document::Yaml(value, _) => create_yaml(value, &parts, version)?,
+            Document::Json(value, _) => create_json(value, &parts, version)?,
+            Document::Text(doc) => anyhow::bail!(
+                "Creating {} isn't supported in {} files",
+                selector,
+                doc.format().as_str()
+            ),
+        }
+        Ok(Change {
+            old: String::new(),
+            new: version.to_string(),
+        })
+    }
+
+    /// Add one to the integer at `selector`
+    fn increment_build_number(&mut self, selector: &str) -> Result<Change> {
+        let current = self.read_version(selector)?;
+        let number: u64 = current
+            .trim()
+            .parse()
+            .with_context(|| format!("{} is not an integer build number: {}", selector, current))?;
+        self.set_version(selector, &(number + 1).to_string())
+    }
+
+    pub fn render(&self) -> Result<String> {
+        match self {
+            Document::Toml(doc, encoding) => Ok(encoding.apply(doc.to_string())),
+            Document::Yaml(value, source) => {
+                let rendered = yaml::render(strip_bom(source), value)?;
+                Ok(Encoding::detect(source).apply(rendered))
+            }
+            Document::Json(value, source) => {
+                let rendered = json::render(strip_bom(source), value)?;
+                Ok(Encoding::detect(source).apply(rendered))
+            }
+            Document::Text(doc) => Ok(doc.render()),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Document::Toml(..) => "toml",
+            Document::Yaml(..) => "yaml",
+            Document::Json(..) => "json",
+            Document::Text(doc) => doc.format().as_str(),
+        }
+    }
+}
+
+/// Try each format in turn, accepting only documents with a top-level table
+fn sniff_document(content: &str) -> Option<Document> {
+    ["toml", "json", "yaml"].into_iter().find_map(|file_type| {
+        match Document::parse(content, file_type).ok()? {
+            Document::Json(value, _) if !value.is_object() => None,
+            Document::Yaml(value, _) if !value.is_mapping() => None,
+            doc => Some(doc),
+        }
+    })
+}
+
+pub fn parse_document(path: &Path, content: &str, input: InputOptions) -> Result<Document> {
+    if is_stdio(path) && input.file_type.is_none() {
+        let doc = sniff_document(content)
+            .context("Could not detect the type of stdin, pass it with --type")?;
+        verbosity::note(format_args!("stdin: detected {}", doc.type_name()));
+        return Ok(doc);
+    }
+    // Files like Chartfile have no name to go by either, only their content
+    if input.file_type.is_none()
+        && path.extension().is_none()
+        && plugin::command_for(path).is_none()
+        && get_file_type(path, None).is_err()
+    {
+        let doc = sniff_document(content).with_context(|| {
+            format!(
+                "Could not detect the type of {}, which has no extension, pass it with --type",
+                path.display()
+            )
+        })?;
+        verbosity::note(format_args!(
+            "{}: detected {}",
+            path.display(),
+            doc.type_name()
+        ));
+        return Ok(doc);
+    }
+    if input.file_type.is_none() {
+        if let Some(command) = plugin::command_for(path) {
+            verbosity::note(format_args!(
+                "{}: parsing with plugin {}",
+                path.display(),
+                command
+            ));
+            return Ok(Document::Text(TextDocument::plugin(content, command)));
+        }
+    }
+    let parsed = get_file_type(path, input.file_type).and_then(|file_type| {
+        verbosity::note(format_args!("{}: parsing as {}", path.display(), file_type));
+        Document::parse(content, file_type)
+    });
+    match parsed {
+        Ok(doc) => Ok(doc),
+        Err(err) if input.type_fallback => {
+            let doc = sniff_document(content)
+                .ok_or_else(|| err.context("Content did not parse as any supported type"))?;
+            eprintln!(
+                "warning: falling back to {} for {}",
+                doc.type_name(),
+                path.display()
+            );
+            Ok(doc)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// The `-` path, standing for stdin when reading and stdout when writing
+pub const STDIO_PATH: &str = "-";
+
+pub fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO_PATH
+}
+
+/// Write `contents` to a temp file next to `path` and rename it into place, so
+/// an interrupted write never leaves a truncated file. An existing file's
+/// permissions, and on Unix its owner where allowed, carry over.
+pub fn write_file(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+    // Replace the file a symlink points to rather than the link itself
+    let path = fs::canonicalize(path.as_ref()).unwrap_or_else(|_| path.as_ref().to_path_buf());
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut temp = tempfile::NamedTempFile::new_in(dir)
+        .with_context(|| format!("Failed to create a temp file in {}", dir.display()))?;
+    temp.write_all(contents.as_ref())?;
+    if let Ok(metadata) = fs::metadata(&path) {
+        temp.as_file().set_permissions(metadata.permissions())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            // Only root can give a file away; otherwise the writer owns it
+            let _ = std::os::unix::fs::fchown(
+                temp.as_file(),
+                Some(metadata.uid()),
+                Some(metadata.gid()),
+            );
+        }
+    }
+    temp.as_file().sync_all()?;
+    temp.persist(&path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+pub fn load_document(path: &Path, input: InputOptions) -> Result<Document> {
+    parse_document(path, &read_source(path)?, input)
+}
+
+/// The content of `path`, which may be a remote file or `-` for stdin
+pub fn read_source(path: &Path) -> Result<String> {
+    Ok(match RemotePath::parse(path) {
+        Some(remote) => remote.fetch()?,
+        None if is_stdio(path) => io::read_to_string(io::stdin())?,
+        None => fs::read_to_string(path)?,
+    })
+}
+
+/// Find the `[[package]]` entry with the given name in a Cargo.lock
+fn lock_package<'a>(table: &'a Table, name: &str) -> Result<&'a Table> {
+    table
+        .get("package")
+        .and_then(Item::as_array_of_tables)
+        .context("No [[package]] entries found")?
+        .iter()
+        .find(|package| package.get("name").and_then(Item::as_str) == Some(name))
+        .with_context(|| format!("No package named {} found", name))
+}
+
+fn lock_package_mut<'a>(table: &'a mut Table, name: &str) -> Result<&'a mut Table> {
+    table
+        .get_mut("package")
+        .and_then(Item::as_array_of_tables_mut)
+        .context("No [[package]] entries found")?
+        .iter_mut()
+        .find(|package| package.get("name").and_then(Item::as_str) == Some(name))
+        .with_context(|| format!("No package named {} found", name))
+}
+
+pub fn read_version_file(path: &Path, input: InputOptions, selector: &str) -> Result<String> {
+    load_document(path, input)?.read_version(selector)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileType {
+    Json,
+    /// JSON with comments and trailing commas, such as tsconfig.json
+    #[value(alias = "json5")]
+    #[serde(alias = "json5")]
+    Jsonc,
+    Yaml,
+    Toml,
+    Makefile,
+    /// A `.env` file of `KEY=value` lines
+    Env,
+    /// An INI file such as setup.cfg, with `section.key` selectors
+    Ini,
+    /// A Java .properties file of `key=value` lines
+    Properties,
+    /// A Groovy or Kotlin Gradle script such as build.gradle.kts
+    Gradle,
+    Xml,
+    /// An Apple XML property list such as Info.plist
+    Plist,
+    /// A Nix expression such as default.nix, with attribute path selectors
+    Nix,
+    /// An HCL file such as main.tf or a Packer template, with block path
+    /// selectors
+    Hcl,
+    /// A CUE file, with field path selectors
+    Cue,
+    /// A Dhall expression, with record field path selectors
+    Dhall,
+    /// A Dockerfile, with `ARG.NAME`, `LABEL.KEY` or `FROM.IMAGE` selectors
+    Dockerfile,
+    /// A file holding nothing but the version, such as VERSION
+    Plain,
+    /// Any text file, with a regex selector capturing the version
+    Regex,
+}
+
+impl FileType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FileType::Json | FileType::Jsonc => "json",
+            FileType::Yaml => "yaml",
+            FileType::Toml => "toml",
+            FileType::Makefile => "makefile",
+            FileType::Env => "env",
+            FileType::Ini => "ini",
+            FileType::Properties => "properties",
+            FileType::Gradle => "gradle",
+            FileType::Xml => "xml",
+            FileType::Plist => "plist",
+            FileType::Nix => "nix",
+            FileType::Hcl => "hcl",
+            FileType::Cue => "cue",
+            FileType::Dhall => "dhall",
+            FileType::Dockerfile => "dockerfile",
+            FileType::Plain => "plain",
+            FileType::Regex => "regex",
+        }
+    }
+}
+
+pub fn get_file_type<'a>(path: &Path, override_type: Option<FileType>) -> Result<&'a str> {
+    if let Some(typ) = override_type {
+        Ok(typ.as_str())
+    } else if path.file_name() == Some("Cargo.lock".as_ref()) {
+        Ok("toml")
+    } else if path.file_name() == Some(".babelrc".as_ref()) {
+        Ok("json")
+    } else if matches!(
+        path.file_name().and_then(|name| name.to_str()),
+        Some("Makefile" | "makefile" | "GNUmakefile")
+    ) {
+        Ok("makefile")
+    } else if matches!(
+        path.file_name().and_then(|name| name.to_str()),
+        Some("VERSION" | ".version" | "VERSION.txt" | "version.txt")
+    ) {
+        Ok("plain")
+    } else if path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name == ".env" || name.starts_with(".env."))
+    {
+        Ok("env")
+    } else if path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(".gradle.kts"))
+    {
+        Ok("gradle")
+    } else if path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| {
+            ["Dockerfile", "Containerfile"].iter().any(|base| {
+                name.strip_prefix(base)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            })
+        })
+    {
+        Ok("dockerfile")
+    } else {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .ok_or_else(|| anyhow::anyhow!("File has no extension"))?;
+
+        match ext {
+            "json" | "jsonc" | "json5" => Ok("json"),
+            "yml" | "yaml" => Ok("yaml"),
+            "toml" => Ok("toml"),
+            "mk" => Ok("makefile"),
+            "env" => Ok("env"),
+            "ini" | "cfg" => Ok("ini"),
+            "properties" => Ok("properties"),
+            "gradle" => Ok("gradle"),
+            "plist" => Ok("plist"),
+            "nix" => Ok("nix"),
+            "tf" | "tfvars" | "hcl" => Ok("hcl"),
+            "cue" => Ok("cue"),
+            "dhall" => Ok("dhall"),
+            "dockerfile" | "containerfile" => Ok("dockerfile"),
+            "xml" | "csproj" | "fsproj" | "vbproj" | "props" => Ok("xml"),
+            _ => anyhow::bail!("Unsupported file extension: {}", ext),
+        }
+    }
+}
+
+/// Substitute `{version}`, `{major}`, `{minor}`, `{patch}`, `{prerelease}`
+/// and `{build}` placeholders with parts of a version. The version is only
+/// parsed, leniently, when a placeholder other than `{version}` is used, so
+/// any version renders through `{version}` alone.
+pub fn render_template(template: &str, version: &str) -> Result<String> {
+    let rendered = template.replace("{version}", version);
+    let parts = ["{major}", "{minor}", "{patch}", "{prerelease}", "{build}"];
+    if !parts.iter().any(|part| rendered.contains(part)) {
+        return Ok(rendered);
+    }
+    let parsed = parse_loose(version)?;
+    Ok(rendered
+        .replace("{major}", &parsed.major.to_string())
+        .replace("{minor}", &parsed.minor.to_string())
+        .replace("{patch}", &parsed.patch.to_string())
+        .replace("{prerelease}", parsed.pre.as_str())
+        .replace("{build}", parsed.build.as_str()))
+}
+
+/// Check that `version` is valid under `scheme`, calendar versions being laid
+/// out as `calver_format` or the default
+pub fn validate_version(version: &str, scheme: Scheme, calver_format: Option<&str>) -> Result<()> {
+    match scheme {
+        Scheme::Semver => {
+            Version::parse(version)
+                .with_context(|| format!("{} is not a valid semver", version))?;
+        }
+        Scheme::Calver => {
+            calver::validate(version, calver_format.unwrap_or(calver::DEFAULT_FORMAT))?
+        }
+        Scheme::Pep440 => {
+            Pep440::parse(version)?;
+        }
+        Scheme::Quad => {
+            parse_quad(version)?;
+        }
+        Scheme::Epoch => {
+            let (_, version) = split_epoch(version);
+            Version::parse(version)
+                .with_context(|| format!("{} is not a valid semver after the epoch", version))?;
+        }
+    }
+    Ok(())
+}
+
+/// Parse a version leniently, trimming whitespace and filling in missing
+/// minor and patch segments
+pub fn parse_loose(version: &str) -> Result<Version> {
+    let version = version.trim();
+    let core_end = version.find(['-', '+']).unwrap_or(version.len());
+    let (core, suffix) = version.split_at(core_end);
+    let padding = match core.split('.').count() {
+        1 => ".0.0",
+        2 => ".0",
+        _ => "",
+    };
+    Version::parse(&format!("{}{}{}", core, padding, suffix))
+        .with_context(|| format!("Invalid version: {}", version))
+}
+
+pub fn bump_semver(version: &str, level: &VersionBump, opts: &BumpOptions) -> Result<String> {
+    // Requirements with several comparators (`>=1.0.190, <2`) bump the first
+    // and keep the rest
+    if let Some((first, rest)) = version.split_once(',') {
+        let trimmed = first.trim_end();
+        return Ok(format!(
+            "{}{},{}",
+            bump_semver(trimmed, level, opts)?,
+            &first[trimmed.len()..],
+            rest
+        ));
+    }
+    // Dependency constraints such as `^1.2.3` keep their operator
+    let (operator, version) = split_range_operator(version);
+    if !operator.is_empty() {
+        return Ok(format!(
+            "{}{}",
+            operator,
+            bump_requirement(version, level, opts)?
+        ));
+    }
+    if opts.bump_build_number
+        && matches!(opts.scheme, Scheme::Calver | Scheme::Pep440 | Scheme::Quad)
+    {
+        anyhow::bail!("--bump-build-number only applies to semantic versions");
+    }
+    if (opts.normalize_pre || opts.refresh_date_pre)
+        && matches!(opts.scheme, Scheme::Pep440 | Scheme::Quad)
+    {
+        anyhow::bail!("--normalize-pre and --refresh-date-pre only apply to semantic versions");
+    }
+    if opts.scheme == Scheme::Calver {
+        return bump_calver(version, level, opts);
+    }
+    if opts.scheme == Scheme::Pep440 {
+        return bump_pep440(version, level, opts);
+    }
+    if opts.scheme == Scheme::Quad {
+        return bump_quad(version, level, opts);
+    }
+    if opts.scheme == Scheme::Epoch {
+        return bump_epoch(version, level, opts);
+    }
+    let current = Version::parse(version)?;
+    let parsed;
+    let level = match level {
+        VersionBump::Literal(raw) => {
+            parsed = VersionBump::Specific(Version::parse(raw)?);
+            &parsed
+        }
+        VersionBump::Specific(target) if opts.bump_build_number && target.build.is_empty() => {
+            let mut target = target.clone();
+            target.build = next_build_number(&current)?;
+            parsed = VersionBump::Specific(target);
+            &parsed
+        }
+        level => level,
+    };
+
+    check_only_if(version, &current, opts)?;
+
+    // Level bumps drop any pre-release and build metadata; build keeps the
+    // pre-release
+    let mut new_version = match level {
+        VersionBump::Major => Version::new(current.major + 1, 0, 0),
+        VersionBump::Minor => Version::new(current.major, current.minor + 1, 0),
+        VersionBump::Patch => Version::new(current.major, current.minor, current.patch + 1),
+        VersionBump::Pre(id) => {
+            let id = match (id, &opts.pre_id) {
+                (Some(_), Some(_)) => anyhow::bail!("--pre-id only applies to the pre level"),
+                (Some(id), None) | (None, Some(id)) => id,
+                (None, None) => anyhow::bail!("The pre level requires --pre-id"),
+            };
+            let mut new_version = Version::new(current.major, current.minor, current.patch);
+            new_version.pre = next_pre(&current.pre, id)?;
+            new_version
+        }
+        VersionBump::Release => {
+            if current.pre.is_empty() {
+                anyhow::bail!("Version {} has no pre-release to release", current);
+            }
+            Version::new(current.major, current.minor, current.patch)
+        }
+        VersionBump::Build => {
+            let mut new_version = current.clone();
+            new_version.build = match opts.bump_build_number {
+                true => next_build_number(&current)?,
+                false => opts.build_metadata()?,
+            };
+            if new_version.build == current.build {
+                return Err(Skipped {
+                    current: version.to_string(),
+                    reason: format!("already at {}", current),
+                }
+                .into());
+            }
+            new_version
+        }
+        VersionBump::Specific(target) => {
+            if target <= &current && opts.set_if_greater {
+                return Err(Skipped {
+                    current: version.to_string(),
+                    reason: format!("current {} is already >= {}", current, target),
+                }
+                .into());
+            }
+            let same_release = (target.major, target.minor, target.patch)
+                == (current.major, current.minor, current.patch);
+            let pre_downgrade =
+                opts.allow_prerelease_downgrade && same_release && !target.pre.is_empty();
+            if target < &current && !opts.force && !pre_downgrade {
+                anyhow::bail!(
+                    "New version {} must be greater than current version {} (use --force to set it anyway)",
+                    target,
+                    current
+                );
+            }
+            if target == &current && !opts.allow_equal {
+                return Err(Skipped {
+                    current: version.to_string(),
+                    reason: format!("already at {}", current),
+                }
+                .into());
+            }
+            target.clone()
+        }
+        VersionBump::Post | VersionBump::Dev => {
+            anyhow::bail!("The post and dev levels only apply to --scheme pep440")
+        }
+        VersionBump::Revision => {
+            anyhow::bail!("The revision level only applies to --scheme quad")
+        }
+        VersionBump::Epoch => anyhow::bail!("The epoch level only applies to --scheme epoch"),
+        VersionBump::Literal(_) => unreachable!("parsed above"),
+    };
+
+    check_max_level(&current, &new_version, opts)?;
+
+    if opts.refresh_date_pre {
+        let source = match level {
+            VersionBump::Specific(target) => Some(&target.pre),
+            VersionBump::Pre(_) => None,
+            _ => Some(&current.pre),
+        };
+        if let Some(source) = source {
+            if let Some(pre) = refresh_date_pre(source, opts.today())? {
+                new_version.pre = pre;
+            }
+        }
+    }
+
+    if opts.normalize_pre {
+        new_version.pre = Prerelease::new(&new_version.pre.as_str().to_lowercase())?;
+    }
+
+    if opts.bump_build_number && new_version.build.is_empty() {
+        new_version.build = next_build_number(&current)?;
+    }
+
+    Ok(new_version.to_string())
+}
+
+/// The build number after the one of `current`, which counts from 1 when it
+/// has none
+fn next_build_number(current: &Version) -> Result<BuildMetadata> {
+    let number = match current.build.as_str() {
+        "" => 0,
+        build => build.parse::<u64>().with_context(|| {
+            format!(
+                "{} has build metadata {}, not an integer build number",
+                current, build
+            )
+        })?,
+    };
+    Ok(BuildMetadata::new(&(number + 1).to_string())?)
+}
+
+/// Bump the version of a requirement, which may leave out its minor and patch
+/// (`^1.2`); the result keeps as few components as it can without losing any
+/// that aren't zero (`^1.2` -> `^1.3` or `^1.2.1`)
+fn bump_requirement(version: &str, level: &VersionBump, opts: &BumpOptions) -> Result<String> {
+    let components = version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .count();
+    if opts.scheme != Scheme::Semver || components >= 3 {
+        return bump_semver(version, level, opts);
+    }
+    let new_version = Version::parse(&bump_semver(
+        &parse_loose(version)?.to_string(),
+        level,
+        opts,
+    )?)?;
+    if !new_version.pre.is_empty() || !new_version.build.is_empty() {
+        return Ok(new_version.to_string());
+    }
+    let parts = [new_version.major, new_version.minor, new_version.patch];
+    let significant = parts.iter().rposition(|n| *n != 0).map_or(1, |i| i + 1);
+    let parts: Vec<String> = parts[..components.max(significant)]
+        .iter()
+        .map(u64::to_string)
+        .collect();
+    Ok(parts.join("."))
+}
+
+/// Split a leading range operator (`^`, `~`, `>=`, ...) and the whitespace
+/// after it from a version
+fn split_range_operator(version: &str) -> (&str, &str) {
+    let rest = version.trim_start_matches(['^', '~', '>', '<', '=']);
+    let rest = rest.trim_start();
+    version.split_at(version.len() - rest.len())
+}
+
+/// The next calendar version for the release level
+fn bump_calver(version: &str, level: &VersionBump, opts: &BumpOptions) -> Result<String> {
+    if !matches!(level, VersionBump::Release) {
+        anyhow::bail!("Only the release level applies to --scheme calver");
+    }
+    let format = opts
+        .calver_format
+        .as_deref()
+        .unwrap_or(calver::DEFAULT_FORMAT);
+    let new_version = calver::next(version, format, opts.today())?;
+    if new_version == version {
+        return Err(Skipped {
+            current: version.to_string(),
+            reason: format!("already at {}", version),
+        }
+        .into());
+    }
+    Ok(new_version)
+}
+
+/// Skip the bump unless `current`, read from `version`, matches `--only-if`
+fn check_only_if(version: &str, current: &Version, opts: &BumpOptions) -> Result<()> {
+    match &opts.only_if {
+        Some(req) if !req.matches(current) => Err(Skipped {
+            current: version.to_string(),
+            reason: format!("current {} does not match --only-if {}", version, req),
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Fail when the bump from `current` to `new_version` changes a higher
+/// component than `--max-level` allows
+fn check_max_level(current: &Version, new_version: &Version, opts: &BumpOptions) -> Result<()> {
+    let Some(max_level) = opts.max_level else {
+        return Ok(());
+    };
+    match BumpLevel::between(current, new_version).filter(|level| *level > max_level) {
+        Some(level) => anyhow::bail!(
+            "Bump from {} to {} is a {} change, which exceeds --max-level {}",
+            current,
+            new_version,
+            level.as_str(),
+            max_level.as_str()
+        ),
+        None => Ok(()),
+    }
+}
+
+/// The major, minor and patch of a version's numbers, for the guards of
+/// schemes that aren't semver
+fn release_semver(release: &[u64]) -> Version {
+    let part = |i: usize| release.get(i).copied().unwrap_or(0);
+    Version::new(part(0), part(1), part(2))
+}
+
+/// The next PEP 440 version; the pre level takes `a`, `b` or `rc` (or
+/// `alpha`, `beta`) and the build level sets the local version label
+fn bump_pep440(version: &str, level: &VersionBump, opts: &BumpOptions) -> Result<String> {
+    let current = Pep440::parse(version)?;
+    check_only_if(version, &release_semver(&current.release), opts)?;
+    let new_version = match level {
+        VersionBump::Major => current.bump_release(0),
+        VersionBump::Minor => current.bump_release(1),
+        VersionBump::Patch => current.bump_release(2),
+        VersionBump::Pre(id) => {
+            let id = match (id, &opts.pre_id) {
+                (Some(_), Some(_)) => anyhow::bail!("--pre-id only applies to the pre level"),
+                (Some(id), None) | (None, Some(id)) => id,
+                (None, None) => anyhow::bail!("The pre level requires --pre-id"),
+            };
+            let phase = pep440::Phase::parse(id)?;
+            let n = match current.pre {
+                Some((current_phase, n)) if current_phase == phase => n + 1,
+                _ => 1,
+            };
+            Pep440 {
+                pre: Some((phase, n)),
+                ..current.final_release()
+            }
+        }
+        VersionBump::Post => Pep440 {
+            post: Some(current.post.map_or(1, |n| n + 1)),
+            dev: None,
+            local: None,
+            ..current.clone()
+        },
+        VersionBump::Dev => Pep440 {
+            dev: Some(current.dev.map_or(1, |n| n + 1)),
+            local: None,
+            ..current.clone()
+        },
+        VersionBump::Release => {
+            if current.pre.is_none() && current.dev.is_none() {
+                anyhow::bail!("Version {} has no pre-release to release", current);
+            }
+            Pep440 {
+                pre: None,
+                dev: None,
+                local: None,
+                ..current.clone()
+            }
+        }
+        VersionBump::Build => Pep440 {
+            local: Some(opts.build_metadata()?.to_string()),
+            ..current.clone()
+        },
+        VersionBump::Revision => {
+            anyhow::bail!("The revision level only applies to --scheme quad")
+        }
+        VersionBump::Epoch => anyhow::bail!("The epoch level only applies to --scheme epoch"),
+        VersionBump::Specific(_) | VersionBump::Literal(_) => {
+            let target = Pep440::parse(&level.target().unwrap())?;
+            if target <= current && opts.set_if_greater {
+                return Err(Skipped {
+                    current: version.to_string(),
+                    reason: format!("current {} is already >= {}", current, target),
+                }
+                .into());
+            }
+            let pre_downgrade = opts.allow_prerelease_downgrade
+                && target.final_release() == current.final_release()
+                && (target.pre.is_some() || target.dev.is_some());
+            if target < current && !opts.force && !pre_downgrade {
+                anyhow::bail!(
+                    "New version {} must be greater than current version {} (use --force to set it anyway)",
+                    target,
+                    current
+                );
+            }
+            if target.to_string() == version && opts.allow_equal {
+                return Ok(target.to_string());
+            }
+            target
+        }
+    };
+    check_max_level(
+        &release_semver(&current.release),
+        &release_semver(&new_version.release),
+        opts,
+    )?;
+    let new_version = new_version.to_string();
+    if new_version == version {
+        return Err(Skipped {
+            current: version.to_string(),
+            reason: format!("already at {}", version),
+        }
+        .into());
+    }
+    Ok(new_version)
+}
+
+/// The four numbers of a version such as `1.2.3.4`
+fn parse_quad(version: &str) -> Result<[u64; 4]> {
+    let parts = version
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()
+        .and_then(|parts| <[u64; 4]>::try_from(parts).ok());
+    parts.with_context(|| format!("Invalid four-component version: {}", version))
+}
+
+/// The next four-component version; major, minor, patch and revision bump
+/// their component and zero the ones after it
+fn bump_quad(version: &str, level: &VersionBump, opts: &BumpOptions) -> Result<String> {
+    let current = parse_quad(version)?;
+    check_only_if(version, &release_semver(&current), opts)?;
+    let index = match level {
+        VersionBump::Major => 0,
+        VersionBump::Minor => 1,
+        VersionBump::Patch => 2,
+        VersionBump::Revision => 3,
+        VersionBump::Specific(_) | VersionBump::Literal(_) => {
+            let target = level.target().unwrap();
+            let parsed = parse_quad(&target)?;
+            if parsed <= current && opts.set_if_greater {
+                return Err(Skipped {
+                    current: version.to_string(),
+                    reason: format!("current {} is already >= {}", version, target),
+                }
+                .into());
+            }
+            if parsed < current && !opts.force {
+                anyhow::bail!(
+                    "New version {} must be greater than current version {} (use --force to set it anyway)",
+                    target,
+                    version
+                );
+            }
+            if parsed == current && opts.allow_equal {
+                return Ok(version.to_string());
+            }
+            check_max_level(&release_semver(&current), &release_semver(&parsed), opts)?;
+            return quad_change(version, parsed);
+        }
+        _ => anyhow::bail!("Only major, minor, patch and revision apply to --scheme quad"),
+    };
+    let mut new_version = current;
+    new_version[index] += 1;
+    new_version[index + 1..].iter_mut().for_each(|n| *n = 0);
+    check_max_level(
+        &release_semver(&current),
+        &release_semver(&new_version),
+        opts,
+    )?;
+    quad_change(version, new_version)
+}
+
+/// Split the epoch from a version such as `2:1.4.0`
+fn split_epoch(version: &str) -> (Option<u64>, &str) {
+    version
+        .split_once(':')
+        .and_then(|(epoch, rest)| {
+            let epoch = epoch
+                .bytes()
+                .all(|b| b.is_ascii_digit())
+                .then(|| epoch.parse().ok())??;
+            Some((Some(epoch), rest))
+        })
+        .unwrap_or((None, version))
+}
+
+/// The next version under `--scheme epoch`: the epoch level increments the
+/// epoch (from 0 when there is none) and keeps the rest, and other levels bump
+/// the semver after the epoch. A version to set may leave out the epoch to
+/// keep the current one; a higher epoch makes any version after it greater.
+fn bump_epoch(version: &str, level: &VersionBump, opts: &BumpOptions) -> Result<String> {
+    let (epoch, current) = split_epoch(version);
+    let with_epoch = |epoch: Option<u64>, version: &str| match epoch {
+        Some(epoch) => format!("{}:{}", epoch, version),
+        None => version.to_string(),
+    };
+    let semver = BumpOptions {
+        scheme: Scheme::Semver,
+        ..opts.clone()
+    };
+    match level {
+        VersionBump::Epoch => {
+            Version::parse(current)?;
+            Ok(with_epoch(Some(epoch.map_or(1, |n| n + 1)), current))
+        }
+        VersionBump::Specific(_) | VersionBump::Literal(_) => {
+            let target = level.target().unwrap();
+            let (target_epoch, target_version) = split_epoch(&target);
+            let (from, to) = (epoch.unwrap_or(0), target_epoch.or(epoch).unwrap_or(0));
+            if to == from {
+                let level = VersionBump::Specific(Version::parse(target_version)?);
+                let new_version = bump_semver(current, &level, &semver)?;
+                return Ok(with_epoch(target_epoch.or(epoch), &new_version));
+            }
+            Version::parse(target_version)?;
+            if to < from && opts.set_if_greater {
+                return Err(Skipped {
+                    current: version.to_string(),
+                    reason: format!("current {} is already >= {}", version, target),
+                }
+                .into());
+            }
+            if to < from && !opts.force {
+                anyhow::bail!(
+                    "New version {} must be greater than current version {} (use --force to set it anyway)",
+                    target,
+                    version
+                );
+            }
+            Ok(target)
+        }
+        level => Ok(with_epoch(epoch, &bump_semver(current, level, &semver)?)),
+    }
+}
+
+/// `new_version` as a string, or skipped when it is `version`
+fn quad_change(version: &str, new_version: [u64; 4]) -> Result<String> {
+    let new_version = new_version.map(|n| n.to_string()).join(".");
+    if new_version == version {
+        return Err(Skipped {
+            current: version.to_string(),
+            reason: format!("already at {}", version),
+        }
+        .into());
+    }
+    Ok(new_version)
+}
+
+/// The pre-release after `pre` for identifier `id`: `id.1` when starting a new
+/// identifier, otherwise the counter after `id` incremented (`rc.1` -> `rc.2`)
+fn next_pre(pre: &Prerelease, id: &str) -> Result<Prerelease> {
+    let mut parts = pre.as_str().split('.');
+    let counter = match (parts.next(), parts.next()) {
+        (Some(current), Some(n)) if current == id => n.parse::<u64>().ok(),
+        (Some(current), None) if current == id => Some(0),
+        _ => None,
+    };
+    let next = format!("{}.{}", id, counter.map_or(1, |n| n + 1));
+    Prerelease::new(&next).with_context(|| format!("Invalid pre-release identifier {:?}", id))
+}
+
+/// Replace the first 8-digit date identifier in a pre-release with `today`,
+/// or return None if there is no date identifier
+fn refresh_date_pre(pre: &Prerelease, today: NaiveDate) -> Result<Option<Prerelease>> {
+    let is_date = |id: &str| {
+        id.len() == 8
+            && id.bytes().all(|b| b.is_ascii_digit())
+            && NaiveDate::parse_from_str(id, "%Y%m%d").is_ok()
+    };
+    let mut ids: Vec<String> = pre.as_str().split('.').map(String::from).collect();
+    let Some(date) = ids.iter_mut().find(|id| is_date(id)) else {
+        return Ok(None);
+    };
+    *date = today.format("%Y%m%d").to_string();
+    Ok(Some(Prerelease::new(&ids.join("."))?))
+}
+
+fn bump_version_toml(
+    table: &mut Table,
+    selector: &str,
+    level: &VersionBump,
+    opts: &BumpOptions,
+) -> Result<Change> {
+    update_version_toml(table, selector, |version| bump_semver(version, level, opts))
+}
+
+/// Replace the version at `selector` with the result of `update`
+fn update_version_toml(
+    table: &mut Table,
+    selector: &str,
+    update: impl FnOnce(&str) -> Result<String>,
+) -> Result<Change> {
+    let parts = selector::parse(selector)?;
+    let (first, rest) = parts.split_first().context("Empty selector")?;
+    let mut current = table
+        .get_mut(first)
+        .with_context(|| format!("Missing key: {}", first))?;
+    for part in rest {
+        current = toml_child_mut(current, part)
+            .with_context(|| format!("No table or array found at selector {}", part))?;
+    }
+
+    let value = current
+        .as_value_mut()
+        .filter(|value| value.is_str())
+        .with_context(|| format!("No string value found at {}", selector))?;
+    let old = value.as_str().unwrap().to_string();
+    let new_version = update(&old)?;
+    let decor = value.decor().clone();
+    *value = TomlValue::from(&new_version);
+    *value.decor_mut() = decor;
+    Ok(Change {
+        old,
+        new: new_version,
+    })
+}
+
+/// Record `value` as found at `path` if it is a semver version
+fn push_if_version(path: &[String], value: &str, found: &mut Vec<(String, String)>) {
+    if !path.is_empty() && Version::parse(value).is_ok() {
+        found.push((selector::join(path), value.to_string()));
+    }
+}
+
+fn find_versions_toml(item: &Item, path: &mut Vec<String>, found: &mut Vec<(String, String)>) {
+    if let Some(value) = item.as_str() {
+        push_if_version(path, value, found);
+    } else if let Some(table) = item.as_table_like() {
+        for (key, child) in table.iter() {
+            path.push(key.to_string());
+            find_versions_toml(child, path, found);
+            path.pop();
+        }
+    } else if let Some(tables) = item.as_array_of_tables() {
+        for (index, table) in tables.iter().enumerate() {
+            path.push(index.to_string());
+            for (key, child) in table.iter() {
+                path.push(key.to_string());
+                find_versions_toml(child, path, found);
+                path.pop();
+            }
+            path.pop();
+        }
+    } else if let Some(array) = item.as_array() {
+        for (index, value) in array.iter().enumerate() {
+            path.push(index.to_string());
+            find_versions_toml(&Item::Value(value.clone()), path, found);
+            path.pop();
+        }
+    }
+}
+
+fn find_versions_yaml(
+    value: &YamlValue,
+    path: &mut Vec<String>,
+    found: &mut Vec<(String, String)>,
+) {
+    match value {
+        YamlValue::String(value) => push_if_version(path, value, found),
+        YamlValue::Mapping(map) => {
+            for (key, child) in map {
+                if let Some(key) = key.as_str() {
+                    path.push(key.to_string());
+                    find_versions_yaml(child, path, found);
+                    path.pop();
+                }
+            }
+        }
+        YamlValue::Sequence(items) => {
+            for (index, child) in items.iter().enumerate() {
+                path.push(index.to_string());
+                find_versions_yaml(child, path, found);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn find_versions_json(
+    value: &JsonValue,
+    path: &mut Vec<String>,
+    found: &mut Vec<(String, String)>,
+) {
+    match value {
+        JsonValue::String(value) => push_if_version(path, value, found),
+        JsonValue::Object(map) => {
+            for (key, child) in map {
+                path.push(key.clone());
+                find_versions_json(child, path, found);
+                path.pop();
+            }
+        }
+        JsonValue::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                path.push(index.to_string());
+                find_versions_json(child, path, found);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The item at `part` in a table, or at index `part` in an array
+fn toml_child<'a>(item: &'a Item, part: &str) -> Option<&'a Item> {
+    match selector::index(part) {
+        Some(index) if item.is_array() || item.is_array_of_tables() => item.get(index),
+        _ => item.get(part),
+    }
+}
+
+fn toml_child_mut<'a>(item: &'a mut Item, part: &str) -> Option<&'a mut Item> {
+    match selector::index(part) {
+        Some(index) if item.is_array() || item.is_array_of_tables() => item.get_mut(index),
+        _ => item.get_mut(part),
+    }
+}
+
+/// Insert the missing keys of `parts` below `item`, the last holding
+/// `version`. New tables are implicit, so only the one holding the version
+/// gets a header; inside an inline table they are inline too.
+fn create_toml(item: &mut Item, parts: &[String], version: &str) -> Result<()> {
+    let (part, rest) = parts.split_first().context("Empty selector")?;
+    if toml_child(item, part).is_none() {
+        match item {
+            Item::Table(table) if rest.is_empty() => {
+                table.insert(part, toml_edit::value(version));
+            }
+            Item::Table(table) => {
+                let mut child = Table::new();
+                child.set_implicit(true);
+                table.insert(part, Item::Table(child));
+            }
+            Item::Value(TomlValue::InlineTable(table)) if rest.is_empty() => {
+                table.insert(part, TomlValue::from(version));
+            }
+            Item::Value(TomlValue::InlineTable(table)) => {
+                table.insert(part, TomlValue::InlineTable(Default::default()));
+            }
+            _ => anyhow::bail!("Can't add {} to a value that isn't a table", part),
+        }
+    }
+    match rest {
+        [] => Ok(()),
+        _ => create_toml(toml_child_mut(item, part).unwrap(), rest, version),
+    }
+}
+
+fn create_yaml(value: &mut YamlValue, parts: &[String], version: &str) -> Result<()> {
+    let (part, rest) = parts.split_first().context("Empty selector")?;
+    if walk_yaml(value, &parts[..1]).is_err() {
+        let map = value
+            .as_mapping_mut()
+            .with_context(|| format!("Can't add {} to a value that isn't a map", part))?;
+        let child = match rest {
+            [] => YamlValue::String(version.to_string()),
+            _ => YamlValue::Mapping(Default::default()),
+        };
+        map.insert(YamlValue::String(part.clone()), child);
+    }
+    match rest {
+        [] => Ok(()),
+        _ => create_yaml(walk_yaml_mut(value, &parts[..1])?, rest, version),
+    }
+}
+
+fn create_json(value: &mut JsonValue, parts: &[String], version: &str) -> Result<()> {
+    let (part, rest) = parts.split_first().context("Empty selector")?;
+    if walk_json(value, &parts[..1]).is_err() {
+        let map = value
+            .as_object_mut()
+            .with_context(|| format!("Can't add {} to a value that isn't an object", part))?;
+        let child = match rest {
+            [] => JsonValue::String(version.to_string()),
+            _ => JsonValue::Object(Default::default()),
+        };
+        map.insert(part.clone(), child);
+    }
+    match rest {
+        [] => Ok(()),
+        _ => create_json(walk_json_mut(value, &parts[..1])?, rest, version),
+    }
+}
+
+/// Split a `--bump-key` selector into its parent path and the version-valued key,
+/// e.g. "versions.1.2.3" becomes (["versions"], "1.2.3")
+fn split_key_selector(selector: &str) -> Result<(Vec<String>, String)> {
+    let parts = selector::parse(selector)?;
+    for i in 0..parts.len() {
+        let key = parts[i..].join(".");
+        if Version::parse(&key).is_ok() {
+            return Ok((parts[..i].to_vec(), key));
+        }
+    }
+    anyhow::bail!("No version key found at the end of selector {}", selector)
+}
+
+fn bump_key_toml(
+    doc: &mut DocumentMut,
+    selector: &str,
+    level: &VersionBump,
+    opts: &BumpOptions,
+) -> Result<Change> {
+    let (parents, key) = split_key_selector(selector)?;
+    let mut current: &mut dyn TableLike = doc.as_table_mut();
+
+    // Parents may be standard tables, dotted keys or inline tables
+    for part in &parents {
+        current = current
+            .get_mut(part)
+            .and_then(Item::as_table_like_mut)
+            .with_context(|| format!("No table found at selector {}", part))?;
+    }
+
+    let new_key = bump_semver(&key, level, opts)?;
+    let item = current
+        .remove(&key)
+        .with_context(|| format!("Missing key: {}", key))?;
+    current.insert(&new_key, item);
+    Ok(Change {
+        old: key,
+        new: new_key,
+    })
+}
+
+fn bump_key_json(
+    value: &mut JsonValue,
+    selector: &str,
+    level: &VersionBump,
+    opts: &BumpOptions,
+) -> Result<Change> {
+    let (parents, key) = split_key_selector(selector)?;
+    let target = if parents.is_empty() {
+        value
+    } else {
+        walk_json_mut(value, &parents)?
+    };
+    let map = target
+        .as_object_mut()
+        .with_context(|| format!("No object found at {}", parents.join(".")))?;
+    if !map.contains_key(&key) {
+        anyhow::bail!("Missing key: {}", key);
+    }
+
+    // Rebuild the map so the renamed key keeps its position
+    let new_key = bump_semver(&key, level, opts)?;
+    *map = std::mem::take(map)
+        .into_iter()
+        .map(|(k, v)| {
+            if k == key {
+                (new_key.clone(), v)
+            } else {
+                (k, v)
+            }
+        })
+        .collect();
+    Ok(Change {
+        old: key,
+        new: new_key,
+    })
+}
+
+fn bump_key_yaml(
+    value: &mut YamlValue,
+    selector: &str,
+    level: &VersionBump,
+    opts: &BumpOptions,
+) -> Result<Change> {
+    let (parents, key) = split_key_selector(selector)?;
+    let target = if parents.is_empty() {
+        value
+    } else {
+        walk_yaml_mut(value, &parents)?
+    };
+    let map = target
+        .as_mapping_mut()
+        .with_context(|| format!("No mapping found at {}", parents.join(".")))?;
+    if !map.contains_key(key.as_str()) {
+        anyhow::bail!("Missing key: {}", key);
+    }
+
+    // Rebuild the mapping so the renamed key keeps its position
+    let new_key = bump_semver(&key, level, opts)?;
+    *map = std::mem::take(map)
+        .into_iter()
+        .map(|(k, v)| {
+            if k.as_str() == Some(key.as_str()) {
+                (YamlValue::String(new_key.clone()), v)
+            } else {
+                (k, v)
+            }
+        })
+        .collect();
+    Ok(Change {
+        old: key,
+        new: new_key,
+    })
+}
+
+fn walk_json_mut<'a>(
+    value: &'a mut JsonValue,
+    parts: &[impl AsRef<str>],
+) -> Result<&'a mut JsonValue> {
+    let part = parts[0].as_ref();
+    let value = match selector::index(part) {
+        Some(index) if value.is_array() => value.get_mut(index),
+        _ => value.get_mut(part),
+    }
+    .with_context(|| format!("Missing key: {}", part))?;
+
+    if parts.len() == 1 {
+        Ok(value)
+    } else {
+        walk_json_mut(value, &parts[1..])
+    }
+}
+
+fn walk_yaml_mut<'a>(
+    value: &'a mut YamlValue,
+    parts: &[impl AsRef<str>],
+) -> Result<&'a mut YamlValue> {
+    let part = parts[0].as_ref();
+    let value = match selector::index(part) {
+        Some(index) if value.is_sequence() => value.get_mut(index),
+        _ => value.get_mut(part),
+    }
+    .with_context(|| format!("Missing key: {}", part))?;
+
+    if parts.len() == 1 {
+        Ok(value)
+    } else {
+        walk_yaml_mut(value, &parts[1..])
+    }
+}
+
+fn bump_version_yaml(
+    value: &mut YamlValue,
+    selector: &str,
+    bump: &VersionBump,
+    opts: &BumpOptions,
+) -> Result<Change> {
+    update_version_yaml(value, selector, |version| bump_semver(version, bump, opts))
+}
+
+/// The path of the image reference when `parts` selects its tag, as in
+/// `containers[0].image.tag` with `image` a string rather than a map
+fn image_reference_path<'a>(value: &YamlValue, parts: &'a [String]) -> Option<&'a [String]> {
+    let (last, reference) = parts.split_last()?;
+    if last != image::TAG_SEGMENT || reference.is_empty() {
+        return None;
+    }
+    walk_yaml(value, reference).ok()?.as_str()?;
+    Some(reference)
+}
+
+/// Byte range of the tag in an image `reference` found at `selector`
+fn image_tag(reference: &str, selector: &str) -> Result<Range<usize>> {
+    image::tag_range(reference)
+        .with_context(|| format!("Image {} at {} has no tag", reference, selector))
+}
+
+/// Replace the version at `selector` with the result of `update`
+fn update_version_yaml(
+    value: &mut YamlValue,
+    selector: &str,
+    update: impl FnOnce(&str) -> Result<String>,
+) -> Result<Change> {
+    let parts = selector::parse(selector)?;
+    if let Some(path) = image_reference_path(value, &parts) {
+        let target = walk_yaml_mut(value, path)?;
+        let mut reference = target.as_str().unwrap_or_default().to_string();
+        let range = image_tag(&reference, selector)?;
+        let old = reference[range.clone()].to_string();
+        let new = update(&old)?;
+        reference.replace_range(range, &new);
+        if image::has_digest(&reference) {
+            eprintln!(
+                "warning: {} is pinned to a digest, which still picks the image tagged {}",
+                selector, old
+            );
+        }
+        *target = YamlValue::String(reference);
+        return Ok(Change { old, new });
+    }
+    let target = walk_yaml_mut(value, &parts)?;
+
+    let version = target
+        .as_str()
+        .with_context(|| format!("Version field is not a string at {}", selector))?;
+
+    let old = version.to_string();
+    let new_version = update(version)?;
+    *target = YamlValue::String(new_version.clone());
+    Ok(Change {
+        old,
+        new: new_version,
+    })
+}
+
+fn bump_version_json(
+    value: &mut JsonValue,
+    selector: &str,
+    bump: &VersionBump,
+    opts: &BumpOptions,
+) -> Result<Change> {
+    update_version_json(value, selector, |version| bump_semver(version, bump, opts))
+}
+
+/// Replace the version at `selector` with the result of `update`
+fn update_version_json(
+    value: &mut JsonValue,
+    selector: &str,
+    update: impl FnOnce(&str) -> Result<String>,
+) -> Result<Change> {
+    let parts = selector::parse(selector)?;
+    let target = walk_json_mut(value, &parts)?;
+
+    let version = target
+        .as_str()
+        .with_context(|| format!("Version field is not a string at {}", selector))?;
+
+    let old = version.to_string();
+    let new_version = update(version)?;
+    *target = JsonValue::String(new_version.clone());
+    Ok(Change {
+        old,
+        new: new_version,
+    })
+}
+fn walk_json<'a>(value: &'a JsonValue, parts: &[impl AsRef<str>]) -> Result<&'a JsonValue> {
+    let part = parts[0].as_ref();
+    let value = match selector::index(part) {
+        Some(index) if value.is_array() => value.get(index),
+        _ => value.get(part),
+    }
+    .with_context(|| format!("Missing key: {}", part))?;
+
+    if parts.len() == 1 {
+        Ok(value)
+    } else {
+        walk_json(value, &parts[1..])
+    }
+}
+
+fn walk_yaml<'a>(value: &'a YamlValue, parts: &[impl AsRef<str>]) -> Result<&'a YamlValue> {
+    let part = parts[0].as_ref();
+    let value = match selector::index(part) {
+        Some(index) if value.is_sequence() => value.get(index),
+        _ => value.get(part),
+    }
+    .with_context(|| format!("Missing key: {}", part))?;
+
+    if parts.len() == 1 {
+        Ok(value)
+    } else {
+        walk_yaml(value, &parts[1..])
+    }
+}
+
+fn read_version_json(value: &JsonValue, selector: &str) -> Result<String> {
+    let parts = selector::parse(selector)?;
+    let target = walk_json(value, &parts)?;
+
+    target
+        .as_str()
+        .with_context(|| format!("Version field is not a string at {}", selector))
+        .map(String::from)
+}
+
+fn read_version_yaml(value: &YamlValue, selector: &str) -> Result<String> {
+    let parts = selector::parse(selector)?;
+    if let Some(path) = image_reference_path(value, &parts) {
+        let reference = walk_yaml(value, path)?.as_str().unwrap_or_default();
+        return Ok(reference[image_tag(reference, selector)?].to_string());
+    }
+    let target = walk_yaml(value, &parts)?;
+
+    target
+        .as_str()
+        .with_context(|| format!("Version field is not a string at {}", selector))
+        .map(String::from)
+}
+
+fn read_version_toml(table: &Table, selector: &str) -> Result<String> {
+    let parts = selector::parse(selector)?;
+    let (first, rest) = parts.split_first().context("Empty selector")?;
+    let mut current = table
+        .get(first)
+        .with_context(|| format!("Missing key: {}", first))?;
+    for part in rest {
+        current = toml_child(current, part)
+            .with_context(|| format!("No table or array found at selector {}", part))?;
+    }
+
+    current
+        .as_str()
+        .with_context(|| format!("No string value found at {}", selector))
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_json_version_bump() -> Result<()> {
+        let json_content = r#"{
+            "name": "test-package",
+            "version": "1.2.3"
+        }"#;
+
+        let temp_file = NamedTempFile::new()?;
+        fs::write(&temp_file, json_content)?;
+
+        let content = fs::read_to_string(temp_file.path())?;
+        let mut value: JsonValue = serde_json::from_str(&content)?;
+        bump_version_json(
+            &mut value,
+            "version",
+            &VersionBump::Patch,
+            &BumpOptions::default(),
+        )?;
+
+        assert_eq!(value["version"], "1.2.4");
+        Ok(())
+    }
+
+    #[test]
+    fn test_toml_version_bump() -> Result<()> {
+        let toml_content = r#"
+[package]
+name = "test-package"
+version = "1.2.3"
+"#;
+
+        let temp_file = NamedTempFile::new()?;
+        fs::write(&temp_file, toml_content)?;
+
+        let content = fs::read_to_string(temp_file.path())?;
+        let mut doc = content.parse::<DocumentMut>()?;
+        bump_version_toml(
+            doc.as_table_mut(),
+            "package.version",
+            &VersionBump::Minor,
+            &BumpOptions::default(),
+        )?;
+
+        assert_eq!(doc["package"]["version"].as_str().unwrap(), "1.3.0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_specific_version_bump() -> Result<()> {
+        let json_content = r#"{
+            "name": "test-package",
+            "version": "1.2.3"
+        }"#;
+
+        let temp_file = NamedTempFile::new()?;
+        fs::write(&temp_file, json_content)?;
+
+        let content = fs::read_to_string(temp_file.path())?;
+        let mut value: JsonValue = serde_json::from_str(&content)?;
+        bump_version_json(
+            &mut value,
+            "version",
+            &VersionBump::Specific(Version::new(2, 5, 0)),
+            &BumpOptions::default(),
+        )?;
+        assert_eq!(value["version"], "2.5.0");
+
+        // Test that setting a lower version fails
+        let result = bump_version_json(
+            &mut value,
+            "version",
+            &VersionBump::Specific(Version::new(1, 0, 0)),
+            &BumpOptions::default(),
+        );
+
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_yaml_version_bump() -> Result<()> {
+        let yaml_content = r#"
+name: test-package
+version: 1.2.3
+"#;
+
+        let temp_file = NamedTempFile::new()?;
+        fs::write(&temp_file, yaml_content)?;
+
+        let content = fs::read_to_string(temp_file.path())?;
+        let mut value: YamlValue = serde_yaml::from_str(&content)?;
+        bump_version_yaml(
+            &mut value,
+            "version",
+            &VersionBump::Major,
+            &BumpOptions::default(),
+        )?;
+
+        assert_eq!(value["version"].as_str().unwrap(), "2.0.0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_bump_key_preserves_contents() -> Result<()> {
+        let toml_content = r#"
+[versions."1.2.3"]
+url = "https://example.com/1.2.3.tar.gz"
+checksum = "abc123"
+"#;
+        let mut doc = Document::parse(toml_content, "toml")?;
+        let change = doc.bump_key(
+            "versions.1.2.3",
+            &VersionBump::Patch,
+            &BumpOptions::default(),
+        )?;
+        assert_eq!(change.new, "1.2.4");
+
+        let Document::Toml(toml, _) = &doc else {
+            unreachable!()
+        };
+        assert!(toml["versions"].get("1.2.3").is_none());
+        assert_eq!(
+            toml["versions"]["1.2.4"]["checksum"].as_str(),
+            Some("abc123")
+        );
+        assert!(doc.render()?.contains(r#"[versions."1.2.4"]"#));
+
+        let json_content = r#"{"versions": {"1.2.3": {"url": "a"}, "latest": true}}"#;
+        let mut doc = Document::parse(json_content, "json")?;
+        doc.bump_key(
+            "versions.1.2.3",
+            &VersionBump::Patch,
+            &BumpOptions::default(),
+        )?;
+        let Document::Json(json, _) = &doc else {
+            unreachable!()
+        };
+        let keys: Vec<_> = json["versions"].as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["1.2.4", "latest"]);
+        assert_eq!(json["versions"]["1.2.4"]["url"], "a");
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_level_guard() -> Result<()> {
+        let capped = |max_level| BumpOptions {
+            max_level: Some(max_level),
+            ..Default::default()
+        };
+
+        let err = bump_semver("1.2.3", &VersionBump::Minor, &capped(BumpLevel::Patch)).unwrap_err();
+        assert!(err.to_string().contains("exceeds --max-level patch"));
+        assert_eq!(
+            bump_semver("1.2.3", &VersionBump::Patch, &capped(BumpLevel::Patch))?,
+            "1.2.4"
+        );
+        assert_eq!(
+            bump_semver("1.2.3", &VersionBump::Minor, &capped(BumpLevel::Minor))?,
+            "1.3.0"
+        );
+
+        let specific = VersionBump::Specific(Version::new(2, 0, 0));
+        assert!(bump_semver("1.2.3", &specific, &capped(BumpLevel::Minor)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plist_build_number() -> Result<()> {
+        let content = "<plist version=\"1.0\">\n<dict>\n\t<key>CFBundleShortVersionString</key>\n\t<string>1.2.3</string>\n\t<key>CFBundleVersion</key>\n\t<string>41</string>\n</dict>\n</plist>\n";
+        let mut doc = Document::parse(content, "plist")?;
+        let target = TargetOptions {
+            build_number: Some("CFBundleVersion".to_string()),
+            ..TargetOptions::default()
+        };
+        let change = doc.apply_bump(
+            "CFBundleShortVersionString",
+            &VersionBump::Minor,
+            &target,
+            &BumpOptions::default(),
+        )?;
+        assert_eq!(change.new, "1.3.0");
+        assert_eq!(
+            doc.render()?,
+            content.replace("1.2.3", "1.3.0").replace("41", "42")
+        );
+
+        let mut dotted = Document::parse(&content.replace("41", "41.1"), "plist")?;
+        let result = dotted.apply_bump(
+            "CFBundleShortVersionString",
+            &VersionBump::Minor,
+            &target,
+            &BumpOptions::default(),
+        );
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cargo_lock_package_bump() -> Result<()> {
+        let lock_content = r#"# This file is automatically @generated by Cargo.
+version = 4
+
+[[package]]
+name = "anyhow"
+version = "1.2.3"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "svbump"
+version = "1.2.3"
+dependencies = [
+ "anyhow",
+]
+"#;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("Cargo.lock");
+        fs::write(&path, lock_content)?;
+
+        let mut doc = load_document(&path, InputOptions::default())?;
+        doc.bump_package_version(
+            "svbump",
+            "version",
+            &VersionBump::Minor,
+            &BumpOptions::default(),
+        )?;
+        assert_eq!(doc.read_package_version("svbump", "version")?, "1.3.0");
+        assert_eq!(doc.read_package_version("anyhow", "version")?, "1.2.3");
+        assert_eq!(
+            doc.render()?,
+            lock_content.replacen(
+                "version = \"1.2.3\"\ndependencies",
+                "version = \"1.3.0\"\ndependencies",
+                1
+            )
+        );
+
+        assert!(doc.read_package_version("missing", "version").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_refresh_date_pre() -> Result<()> {
+        let opts = BumpOptions {
+            refresh_date_pre: true,
+            today: NaiveDate::from_ymd_opt(2024, 5, 1),
+            ..Default::default()
+        };
+        assert_eq!(
+            bump_semver("1.2.3-20240101", &VersionBump::Patch, &opts)?,
+            "1.2.4-20240501"
+        );
+        assert_eq!(
+            bump_semver("1.2.3-nightly.20240101.2", &VersionBump::Minor, &opts)?,
+            "1.3.0-nightly.20240501.2"
+        );
+        assert_eq!(
+            bump_semver("1.2.3-beta.1", &VersionBump::Patch, &opts)?,
+            "1.2.4"
+        );
+        assert_eq!(
+            bump_semver("1.2.3-12345678", &VersionBump::Patch, &opts)?,
+            "1.2.4"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ambiguous_wildcard_selector() -> Result<()> {
+        let lock_content = r#"{
+            "name": "app",
+            "version": "1.2.3",
+            "lockfileVersion": 3
+        }"#;
+        let doc = Document::parse(lock_content, "json")?;
+
+        let err = doc.read_version("*ersion").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Selector *ersion is ambiguous, found fields matching *ersion: version, lockfileVersion"
+        );
+        assert_eq!(doc.read_version("vers*")?, "1.2.3");
+        assert!(doc.read_version("*Version").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_wildcard_paths() -> Result<()> {
+        let content = r#"{
+  "packages": {
+    "a": { "version": "1.2.3" },
+    "b": { "version": "0.4.0" },
+    "c": { "private": true },
+    "d.e": { "version": "2.0.0-rc.1" }
+  },
+  "dependencies": { "left-pad": "^1.3.0", "local": "file:../local" }
+}"#;
+        let mut doc = Document::parse(content, "json")?;
+        let paths = doc.expand_selector("packages.*.version")?;
+        assert_eq!(
+            paths,
+            [
+                "packages.a.version",
+                "packages.b.version",
+                "packages.\"d.e\".version"
+            ]
+        );
+        let bumped = doc.bump_paths(&paths, &VersionBump::Patch, &BumpOptions::default())?;
+        let news: Vec<_> = bumped
+            .iter()
+            .map(|(_, result)| result.as_ref().map(|change| change.new.as_str()).ok())
+            .collect();
+        assert_eq!(news, [Some("1.2.4"), Some("0.4.1"), Some("2.0.1")]);
+
+        let deps = doc.expand_selector("dependencies.*")?;
+        assert_eq!(deps, ["dependencies.left-pad", "dependencies.local"]);
+        assert!(doc
+            .bump_paths(&deps, &VersionBump::Minor, &BumpOptions::default())
+            .is_err());
+        assert!(doc.expand_selector("missing.*").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_version() -> Result<()> {
+        let create = TargetOptions {
+            create: true,
+            ..Default::default()
+        };
+        let version: VersionBump = "1.0.0".parse()?;
+        let opts = BumpOptions::default();
+
+        let mut doc = Document::parse("[package]\nname = \"app\"\n", "toml")?;
+        doc.apply_bump("package.metadata.app.version", &version, &create, &opts)?;
+        doc.apply_bump("package.version", &version, &create, &opts)?;
+        assert_eq!(
+            doc.render()?,
+            "[package]\nname = \"app\"\nversion = \"1.0.0\"\n\n[package.metadata.app]\nversion = \"1.0.0\"\n"
+        );
+        // An existing version is bumped as usual
+        let change = doc.apply_bump("package.version", &VersionBump::Minor, &create, &opts)?;
+        assert_eq!(
+            (change.old.as_str(), change.new.as_str()),
+            ("1.0.0", "1.1.0")
+        );
+        assert!(doc
+            .apply_bump("tool.version", &VersionBump::Minor, &create, &opts)
+            .is_err());
+        assert!(doc.create_version("package.name.version", "1.0.0").is_err());
+
+        let mut doc = Document::parse("deps = { a = \"1\" }\n", "toml")?;
+        doc.create_version("deps.b.version", "1.0.0")?;
+        let doc = Document::parse(&doc.render()?, "toml")?;
+        assert_eq!(doc.read_version("deps.b.version")?, "1.0.0");
+
+        let mut doc = Document::parse("{\n  \"name\": \"app\"\n}\n", "json")?;
+        doc.create_version("tool.version", "1.0.0")?;
+        assert_eq!(
+            doc.render()?,
+            "{\n  \"name\": \"app\",\n  \"tool\": {\n    \"version\": \"1.0.0\"\n  }\n}\n"
+        );
+
+        let mut doc = Document::parse("name: app\n", "yaml")?;
+        doc.create_version("image.tag", "1.0.0")?;
+        assert_eq!(doc.read_version("image.tag")?, "1.0.0");
+        assert!(doc.create_version("name.version", "1.0.0").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_template() -> Result<()> {
+        let docker = "myapp:{major}.{minor} myapp:{major}";
+        assert_eq!(render_template(docker, "1.2.3")?, "myapp:1.2 myapp:1");
+        assert_eq!(
+            render_template("{version} {prerelease} {build}", "2.0.0-rc.1+abc")?,
+            "2.0.0-rc.1+abc rc.1 abc"
+        );
+        assert_eq!(render_template("{major}.{minor}.{patch}", "1.2")?, "1.2.0");
+        assert_eq!(
+            render_template("v{version}", "2024.10.1a1")?,
+            "v2024.10.1a1"
+        );
+        assert!(render_template("{major}", "2024.10.1a1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonical_read() -> Result<()> {
+        assert_eq!(parse_loose("  1.2.3\n")?.to_string(), "1.2.3");
+        assert_eq!(parse_loose("1.2")?.to_string(), "1.2.0");
+        assert_eq!(parse_loose(" 2 ")?.to_string(), "2.0.0");
+        assert_eq!(
+            parse_loose("1.2-beta.1+build")?.to_string(),
+            "1.2.0-beta.1+build"
+        );
+        assert!(parse_loose("1.x").is_err());
+
+        let doc = Document::parse("version: \"1.2\"\n", "yaml")?;
+        assert_eq!(
+            parse_loose(&doc.read_version("version")?)?.to_string(),
+            "1.2.0"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_prerelease_levels() -> Result<()> {
+        let bump = |version: &str, level: &str| {
+            bump_semver(version, &level.parse()?, &BumpOptions::default())
+        };
+        assert_eq!(bump("1.2.3", "rc")?, "1.2.3-rc.1");
+        assert_eq!(bump("1.2.3-rc.1", "rc")?, "1.2.3-rc.2");
+        assert_eq!(bump("1.2.3-rc", "rc")?, "1.2.3-rc.1");
+        assert_eq!(bump("1.2.3-alpha.4+build.5", "beta")?, "1.2.3-beta.1");
+        assert_eq!(bump("1.2.3-rc.2", "patch")?, "1.2.4");
+
+        let canary = BumpOptions {
+            pre_id: Some("canary".to_string()),
+            ..Default::default()
+        };
+        let pre = VersionBump::Pre(None);
+        assert_eq!(bump_semver("1.2.3", &pre, &canary)?, "1.2.3-canary.1");
+        assert_eq!(
+            bump_semver("1.2.3-canary.1", &pre, &canary)?,
+            "1.2.3-canary.2"
+        );
+        assert!(bump("1.2.3", "pre").is_err());
+        assert!(bump_semver("1.2.3", &"rc".parse()?, &canary).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_array_index_selectors() -> Result<()> {
+        let level = VersionBump::Minor;
+        let opts = BumpOptions::default();
+
+        let mut doc = Document::parse(
+            r#"{"packages": [{"version": "1.0.0"}, {"version": "2.0.0"}]}"#,
+            "json",
+        )?;
+        assert_eq!(doc.read_version("packages[1].version")?, "2.0.0");
+        assert_eq!(
+            doc.bump_version("packages.0.version", &level, &opts)?.new,
+            "1.1.0"
+        );
+        assert_eq!(
+            doc.render()?,
+            r#"{"packages": [{"version": "1.1.0"}, {"version": "2.0.0"}]}"#
+        );
+
+        let mut doc = Document::parse("versions:\n  - 1.0.0\n  - 3.0.0\n", "yaml")?;
+        assert_eq!(doc.bump_version("versions[1]", &level, &opts)?.new, "3.1.0");
+        assert_eq!(doc.read_version("versions.1")?, "3.1.0");
+
+        let mut doc = Document::parse(
+            "compat = [\"1.0.0\", \"2.0.0\"]\n\n[[bin]]\nname = \"a\"\nversion = \"0.1.0\" # pinned\n",
+            "toml",
+        )?;
+        assert_eq!(doc.read_version("compat[1]")?, "2.0.0");
+        doc.bump_version("bin[0].version", &level, &opts)?;
+        doc.bump_version("compat.0", &level, &opts)?;
+        assert_eq!(
+            doc.render()?,
+            "compat = [\"1.1.0\", \"2.0.0\"]\n\n[[bin]]\nname = \"a\"\nversion = \"0.2.0\" # pinned\n"
+        );
+
+        assert!(doc.read_version("bin[1].version").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quoted_selector_keys() -> Result<()> {
+        let selector = r#"apps."io.github.myapp".version"#;
+        for (content, file_type) in [
+            (
+                r#"{"apps": {"io.github.myapp": {"version": "1.0.0"}}}"#,
+                "json",
+            ),
+            ("apps:\n  io.github.myapp:\n    version: 1.0.0\n", "yaml"),
+            ("[apps.\"io.github.myapp\"]\nversion = \"1.0.0\"\n", "toml"),
+        ] {
+            let mut doc = Document::parse(content, file_type)?;
+            doc.bump_version(selector, &VersionBump::Patch, &BumpOptions::default())?;
+            assert_eq!(doc.read_version(selector)?, "1.0.1", "{}", file_type);
+            assert_eq!(doc.render()?, content.replace("1.0.0", "1.0.1"));
+            assert_eq!(doc.read_version("apps.'io.github*'.version")?, "1.0.1");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_stdio_documents() -> Result<()> {
+        let stdin = Path::new(STDIO_PATH);
+        let doc = parse_document(
+            stdin,
+            "kind: Deployment\nversion: 1.2.3\n",
+            InputOptions::default(),
+        )?;
+        assert_eq!(doc.type_name(), "yaml");
+        let forced = InputOptions {
+            file_type: Some(FileType::Plain),
+            ..InputOptions::default()
+        };
+        assert_eq!(
+            parse_document(stdin, "1.2.3\n", forced)?.read_version(".")?,
+            "1.2.3"
+        );
+        assert!(parse_document(stdin, "1.2.3\n", InputOptions::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_image_tags() -> Result<()> {
+        let content = "\
+apiVersion: apps/v1
+kind: Deployment
+spec:
+  template:
+    spec:
+      containers:
+        - name: app
+          image: ghcr.io/acme/app:1.2.3 # bumped on release
+        - name: proxy
+          image: \"localhost:5000/proxy:0.4.0@sha256:abc\"
+        - name: latest
+          image: nginx
+";
+        let mut doc = Document::parse(content, "yaml")?;
+        let app = "spec.template.spec.containers[0].image.tag";
+        let proxy = "spec.template.spec.containers[1].image.tag";
+        assert_eq!(doc.read_version(app)?, "1.2.3");
+        assert_eq!(doc.read_version(proxy)?, "0.4.0");
+        assert_eq!(
+            doc.read_version("spec.template.spec.containers[0].image")?,
+            "ghcr.io/acme/app:1.2.3"
+        );
+        assert!(doc
+            .read_version("spec.template.spec.containers[2].image.tag")
+            .is_err());
+
+        let change = doc.bump(app, &VersionBump::Minor)?;
+        assert_eq!(
+            (change.old.as_str(), change.new.as_str()),
+            ("1.2.3", "1.3.0")
+        );
+        doc.bump(proxy, &VersionBump::Patch)?;
+        assert_eq!(
+            doc.render()?,
+            content
+                .replace("app:1.2.3", "app:1.3.0")
+                .replace("proxy:0.4.0", "proxy:0.4.1")
+        );
+
+        // A map with a tag key, as in helm values, is left to the usual path
+        let mut values = Document::parse("image:\n  repository: app\n  tag: 1.0.0\n", "yaml")?;
+        values.bump("image.tag", &VersionBump::Major)?;
+        assert_eq!(
+            values.render()?,
+            "image:\n  repository: app\n  tag: 2.0.0\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sniff_extensionless_files() -> Result<()> {
+        let input = InputOptions::default();
+        for (content, file_type) in [
+            ("[package]\nversion = \"1.2.3\"\n", "toml"),
+            ("{\"package\": {\"version\": \"1.2.3\"}}\n", "json"),
+            ("package:\n  version: 1.2.3\n", "yaml"),
+        ] {
+            let doc = parse_document(Path::new("Chartfile"), content, input)?;
+            assert_eq!(doc.type_name(), file_type);
+            assert_eq!(doc.read_version("package.version")?, "1.2.3");
+        }
+        // Names svbump knows, and --type, still come first
+        let doc = parse_document(Path::new("VERSION"), "1.2.3\n", input)?;
+        assert_eq!(doc.type_name(), "plain");
+        let forced = InputOptions {
+            file_type: Some(FileType::Env),
+            ..input
+        };
+        let doc = parse_document(Path::new("Chartfile"), "VERSION=1.2.3\n", forced)?;
+        assert_eq!(doc.read_version("VERSION")?, "1.2.3");
+        assert!(parse_document(Path::new("Chartfile"), "just text", input).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_document_api() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("Cargo.toml");
+        fs::write(
+            &path,
+            "[package]\nname = \"demo\" # keep\nversion = \"0.3.1\"\n",
+        )?;
+
+        let mut doc = Document::open(&path)?;
+        assert_eq!(doc.read_version("package.version")?, "0.3.1");
+        let change = doc.bump("package.version", &VersionBump::Minor)?;
+        assert_eq!(
+            (change.old.as_str(), change.new.as_str()),
+            ("0.3.1", "0.4.0")
+        );
+        doc.save(&path)?;
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "[package]\nname = \"demo\" # keep\nversion = \"0.4.0\"\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_calver_scheme() -> Result<()> {
+        let opts = BumpOptions {
+            scheme: Scheme::Calver,
+            today: NaiveDate::from_ymd_opt(2024, 5, 10),
+            ..Default::default()
+        };
+        let mut doc = Document::parse(r#"{"version": "2024.5.2"}"#, "json")?;
+        doc.bump_version("version", &VersionBump::Release, &opts)?;
+        assert_eq!(doc.read_version("version")?, "2024.5.3");
+        assert!(bump_semver("2024.5.2", &VersionBump::Minor, &opts).is_err());
+
+        let daily = BumpOptions {
+            calver_format: Some("YYYY.0M.0D".into()),
+            ..opts
+        };
+        let err = bump_semver("2024.05.10", &VersionBump::Release, &daily).unwrap_err();
+        assert_eq!(err.to_string(), "skipped: already at 2024.05.10");
+        Ok(())
+    }
+
+    #[test]
+    fn test_pep440_scheme() -> Result<()> {
+        let opts = BumpOptions {
+            scheme: Scheme::Pep440,
+            ..Default::default()
+        };
+        let bump = |version, level: &str| bump_semver(version, &level.parse()?, &opts);
+        assert_eq!(bump("1.2.3", "minor")?, "1.3.0");
+        assert_eq!(bump("2024.1", "patch")?, "2024.1.1");
+        assert_eq!(bump("1.2.3rc1.post2", "major")?, "2.0.0");
+        assert_eq!(bump("1.2.3", "alpha")?, "1.2.3a1");
+        assert_eq!(bump("1.2.3a1", "alpha")?, "1.2.3a2");
+        assert_eq!(bump("1.2.3a2", "rc")?, "1.2.3rc1");
+        assert_eq!(bump("1.2.3", "post")?, "1.2.3.post1");
+        assert_eq!(bump("1.2.3.post1.dev2", "post")?, "1.2.3.post2");
+        assert_eq!(bump("2024.1.0.dev0", "dev")?, "2024.1.0.dev1");
+        assert_eq!(bump("1.2.3rc2.dev1", "release")?, "1.2.3");
+        assert_eq!(bump("1.2.3", "2.0.0-rc.1")?, "2.0.0rc1");
+        assert_eq!(bump("1.2.3", "1.2.3.post1")?, "1.2.3.post1");
+        assert!(bump("1.2.3", "release").is_err());
+        assert!(bump("1.2.3", "1.0.0").is_err());
+        assert!(bump_semver("1.2.3", &VersionBump::Post, &BumpOptions::default()).is_err());
+
+        let mut doc = Document::parse("[project]\nversion = \"0.4.0b1\"\n", "toml")?;
+        doc.bump_version(
+            "project.version",
+            &VersionBump::Pre(None),
+            &BumpOptions {
+                pre_id: Some("beta".into()),
+                ..opts
+            },
+        )?;
+        assert_eq!(doc.read_version("project.version")?, "0.4.0b2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_pep440_guards() -> Result<()> {
+        let opts = |guard: BumpOptions| BumpOptions {
+            scheme: Scheme::Pep440,
+            ..guard
+        };
+        let capped = opts(BumpOptions {
+            max_level: Some(BumpLevel::Patch),
+            ..Default::default()
+        });
+        assert!(bump_semver("1.2.3", &VersionBump::Major, &capped).is_err());
+        assert!(bump_semver("1.2.3rc1", &"2.0.0".parse()?, &capped).is_err());
+        assert_eq!(bump_semver("1.2.3", &VersionBump::Patch, &capped)?, "1.2.4");
+        assert_eq!(
+            bump_semver("1.2.3", &VersionBump::Post, &capped)?,
+            "1.2.3.post1"
+        );
+
+        let only_pre_1 = opts(BumpOptions {
+            only_if: Some(VersionReq::parse("<1.0.0")?),
+            ..Default::default()
+        });
+        let err = bump_semver("1.2.3", &VersionBump::Minor, &only_pre_1).unwrap_err();
+        assert!(err.downcast_ref::<Skipped>().is_some());
+        assert_eq!(
+            bump_semver("0.4.0b1", &VersionBump::Minor, &only_pre_1)?,
+            "0.5.0"
+        );
+
+        for pre in [
+            BumpOptions {
+                normalize_pre: true,
+                ..Default::default()
+            },
+            BumpOptions {
+                refresh_date_pre: true,
+                ..Default::default()
+            },
+        ] {
+            assert!(bump_semver("1.2.3", &VersionBump::Patch, &opts(pre)).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_quad_scheme() -> Result<()> {
+        let opts = BumpOptions {
+            scheme: Scheme::Quad,
+            ..Default::default()
+        };
+        let bump = |version, level: &str| bump_semver(version, &level.parse()?, &opts);
+        assert_eq!(bump("1.2.3.4", "major")?, "2.0.0.0");
+        assert_eq!(bump("1.2.3.4", "minor")?, "1.3.0.0");
+        assert_eq!(bump("1.2.3.4", "patch")?, "1.2.4.0");
+        assert_eq!(bump("1.2.3.4", "revision")?, "1.2.3.5");
+        assert_eq!(bump("1.2.3.4", "1.2.4.0")?, "1.2.4.0");
+        assert!(bump("1.2.3.4", "1.2.3.3").is_err());
+        assert!(bump("1.2.3.4", "1.2.4").is_err());
+        assert!(bump("1.2.3", "patch").is_err());
+        assert!(bump("1.2.3.4", "rc").is_err());
+        assert!(bump_semver("1.2.3", &"1.2.3.4".parse()?, &BumpOptions::default()).is_err());
+        assert!("latest".parse::<VersionBump>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quad_guards() -> Result<()> {
+        let capped = BumpOptions {
+            scheme: Scheme::Quad,
+            max_level: Some(BumpLevel::Patch),
+            ..Default::default()
+        };
+        assert!(bump_semver("1.2.3.4", &VersionBump::Major, &capped).is_err());
+        assert!(bump_semver("1.2.3.4", &"1.3.0.0".parse()?, &capped).is_err());
+        assert_eq!(
+            bump_semver("1.2.3.4", &VersionBump::Patch, &capped)?,
+            "1.2.4.0"
+        );
+        assert_eq!(
+            bump_semver("1.2.3.4", &VersionBump::Revision, &capped)?,
+            "1.2.3.5"
+        );
+
+        let only_pre_1 = BumpOptions {
+            scheme: Scheme::Quad,
+            only_if: Some(VersionReq::parse("<1.0.0")?),
+            ..Default::default()
+        };
+        let err = bump_semver("1.2.3.4", &VersionBump::Minor, &only_pre_1).unwrap_err();
+        assert!(err.downcast_ref::<Skipped>().is_some());
+        assert_eq!(
+            bump_semver("0.2.3.4", &VersionBump::Minor, &only_pre_1)?,
+            "0.3.0.0"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_epoch_scheme() -> Result<()> {
+        let opts = BumpOptions {
+            scheme: Scheme::Epoch,
+            ..Default::default()
+        };
+        let bump = |version, level: &str| bump_semver(version, &level.parse()?, &opts);
+        assert_eq!(bump("2:1.4.0", "minor")?, "2:1.5.0");
+        assert_eq!(bump("2:1.4.0", "rc")?, "2:1.4.0-rc.1");
+        assert_eq!(bump("2:1.4.0", "epoch")?, "3:1.4.0");
+        assert_eq!(bump("1.4.0", "epoch")?, "1:1.4.0");
+        assert_eq!(bump("1.4.0", "patch")?, "1.4.1");
+        assert_eq!(bump("2:1.4.0", "1.5.0")?, "2:1.5.0");
+        assert_eq!(bump("2:1.4.0", "3:1.0.0")?, "3:1.0.0");
+        assert!(bump("2:1.4.0", "1:2.0.0").is_err());
+        assert!(bump("2:1.4.0", "2:1.3.0").is_err());
+        assert!(bump("x:1.4.0", "patch").is_err());
+        assert!(bump_semver("1.4.0", &VersionBump::Epoch, &BumpOptions::default()).is_err());
+
+        let greater = BumpOptions {
+            set_if_greater: true,
+            ..opts.clone()
+        };
+        let skipped = bump_semver("2:1.4.0", &"1:2.0.0".parse()?, &greater).unwrap_err();
+        assert!(skipped.is::<Skipped>());
+        assert!(validate_version("2:1.4.0", Scheme::Epoch, None).is_ok());
+        assert!(validate_version("2:1.4", Scheme::Epoch, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_force_downgrade() -> Result<()> {
+        let target = VersionBump::Specific(Version::new(1, 2, 0));
+        assert!(bump_semver("1.3.0", &target, &BumpOptions::default()).is_err());
+        let force = BumpOptions {
+            force: true,
+            ..Default::default()
+        };
+        assert_eq!(bump_semver("1.3.0", &target, &force)?, "1.2.0");
+        assert!(bump_semver("1.2.0", &target, &force)
+            .unwrap_err()
+            .is::<Skipped>());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ordering_policies() -> Result<()> {
+        let bump =
+            |version, level: &str, opts: &BumpOptions| bump_semver(version, &level.parse()?, opts);
+        let strict = BumpOptions::default();
+        let allow_equal = BumpOptions {
+            allow_equal: true,
+            ..Default::default()
+        };
+        assert!(bump("2.0.0", "2.0.0", &strict).unwrap_err().is::<Skipped>());
+        assert_eq!(bump("2.0.0", "2.0.0", &allow_equal)?, "2.0.0");
+        assert!(bump("2.0.1", "2.0.0", &allow_equal).is_err());
+
+        let pre_downgrade = BumpOptions {
+            allow_prerelease_downgrade: true,
+            ..Default::default()
+        };
+        assert!(bump("2.0.0-beta.5", "2.0.0-alpha.1", &strict).is_err());
+        assert_eq!(
+            bump("2.0.0-beta.5", "2.0.0-alpha.1", &pre_downgrade)?,
+            "2.0.0-alpha.1"
+        );
+        assert_eq!(bump("2.0.0", "2.0.0-rc.1", &pre_downgrade)?, "2.0.0-rc.1");
+        assert!(bump("2.0.0-beta.5", "1.9.0-rc.1", &pre_downgrade).is_err());
+        assert!(bump("2.0.0-beta.5", "1.9.0", &pre_downgrade).is_err());
+
+        let pep440 = BumpOptions {
+            scheme: Scheme::Pep440,
+            ..pre_downgrade
+        };
+        assert_eq!(bump("2.0.0b5", "2.0.0a1", &pep440)?, "2.0.0a1");
+        let quad = BumpOptions {
+            scheme: Scheme::Quad,
+            ..allow_equal
+        };
+        assert_eq!(bump("1.2.3.4", "1.2.3.4", &quad)?, "1.2.3.4");
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_operators() -> Result<()> {
+        let source =
+            r#"{"dependencies": {"lodash": "^1.2.3", "left-pad": ">= 0.1.0", "tiny": "~2.0.0"}}"#;
+        let mut doc = Document::parse(source, "json")?;
+        doc.bump_version(
+            "dependencies.lodash",
+            &VersionBump::Minor,
+            &Default::default(),
+        )?;
+        doc.bump_version(
+            "dependencies.left-pad",
+            &VersionBump::Patch,
+            &Default::default(),
+        )?;
+        doc.bump_version("dependencies.tiny", &"2.1.0".parse()?, &Default::default())?;
+        assert_eq!(
+            doc.render()?,
+            r#"{"dependencies": {"lodash": "^1.3.0", "left-pad": ">= 0.1.1", "tiny": "~2.1.0"}}"#
+        );
+        assert!(bump_semver("^1.2.3", &"1.0.0".parse()?, &Default::default()).is_err());
+
+        let bump =
+            |version, level: &str| bump_semver(version, &level.parse()?, &Default::default());
+        assert_eq!(bump(">=1.0.190, <2", "patch")?, ">=1.0.191, <2");
+        assert_eq!(bump("^1.2", "minor")?, "^1.3");
+        assert_eq!(bump("^1.2", "patch")?, "^1.2.1");
+        assert_eq!(bump("~1", "major")?, "~2");
+        assert_eq!(bump("^0.2", "rc")?, "^0.2.0-rc.1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_toml_dotted_and_inline_tables() -> Result<()> {
+        let source = r#"package = { name = "demo", version = "1.2.3" } # inline
+[tool.metadata]
+docs.rs.version = "0.1.0"
+[[bin]]
+name = "a"
+version = "0.0.1"
+[dependencies]
+serde = { version = "^1.0.1", features = ["derive"] }
+tokio = {version=">=1.35, <2",default-features=false}   # pinned
+[history]
+releases = { "1.0.0" = "2024-01-01" }
+"#;
+        let mut doc = Document::parse(source, "toml")?;
+        let opts = BumpOptions::default();
+        doc.bump_version("package.version", &VersionBump::Patch, &opts)?;
+        doc.bump_version("tool.metadata.docs.rs.version", &VersionBump::Minor, &opts)?;
+        doc.bump_version("bin[0].version", &VersionBump::Patch, &opts)?;
+        doc.bump_version("dependencies.serde.version", &VersionBump::Patch, &opts)?;
+        doc.bump_version("dependencies.tokio.version", &VersionBump::Minor, &opts)?;
+        doc.bump_key("history.releases.1.0.0", &VersionBump::Major, &opts)?;
+        assert_eq!(
+            doc.render()?,
+            source
+                .replace("1.2.3", "1.2.4")
+                .replace("0.1.0", "0.2.0")
+                .replace("0.0.1", "0.0.2")
+                .replace("1.0.1", "1.0.2")
+                .replace("1.35", "1.36")
+                .replace("\"1.0.0\"", "\"2.0.0\"")
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_file_keeps_permissions() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("version.sh");
+        fs::write(&path, "1.2.3\n")?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        let link = dir.path().join("link.sh");
+        std::os::unix::fs::symlink(&path, &link)?;
+
+        write_file(&link, "1.3.0\n")?;
+        assert_eq!(fs::read_to_string(&path)?, "1.3.0\n");
+        assert!(fs::symlink_metadata(&link)?.file_type().is_symlink());
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o755);
+        assert_eq!(fs::read_dir(dir.path())?.count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bom_and_crlf_are_kept() -> Result<()> {
+        for (content, file_type, selector) in [
+            (
+                "\u{feff}[package]\r\nversion = \"1.2.3\"\r\n",
+                "toml",
+                "package.version",
+            ),
+            (
+                "\u{feff}{\r\n  \"version\": \"1.2.3\"\r\n}\r\n",
+                "json",
+                "version",
+            ),
+            ("\u{feff}name: x\r\nversion: 1.2.3\r\n", "yaml", "version"),
+            ("\u{feff}VERSION := 1.2.3\r\n", "makefile", "VERSION"),
+            ("\u{feff}1.2.3\r\n", "plain", "."),
+        ] {
+            let mut doc = Document::parse(content, file_type)?;
+            doc.bump(selector, &VersionBump::Minor)?;
+            assert_eq!(doc.render()?, content.replace("1.2.3", "1.3.0"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_level() -> Result<()> {
+        let opts = BumpOptions {
+            build_template: Some("+nightly.{date}".to_string()),
+            today: NaiveDate::from_ymd_opt(2024, 5, 1),
+            ..Default::default()
+        };
+        assert_eq!(
+            bump_semver("1.2.3-rc.1+old", &VersionBump::Build, &opts)?,
+            "1.2.3-rc.1+nightly.20240501"
+        );
+        let err = bump_semver("1.2.3+nightly.20240501", &VersionBump::Build, &opts).unwrap_err();
+        assert!(err.downcast_ref::<Skipped>().is_some());
+
+        let invalid = BumpOptions {
+            build_template: Some("a b".to_string()),
+            ..Default::default()
+        };
+        assert!(bump_semver("1.2.3", &VersionBump::Build, &invalid).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bump_build_number() -> Result<()> {
+        let opts = BumpOptions {
+            bump_build_number: true,
+            ..Default::default()
+        };
+        let bump = |version, level: &str| bump_semver(version, &level.parse()?, &opts);
+        assert_eq!(bump("1.2.3+45", "minor")?, "1.3.0+46");
+        assert_eq!(bump("1.2.3+45", "rc")?, "1.2.3-rc.1+46");
+        assert_eq!(bump("1.2.3+45", "build")?, "1.2.3+46");
+        assert_eq!(bump("1.2.3+45", "1.2.3")?, "1.2.3+46");
+        assert_eq!(bump("1.2.3+45", "2.0.0+100")?, "2.0.0+100");
+        assert_eq!(bump("1.2.3", "patch")?, "1.2.4+1");
+        assert_eq!(bump("^1.2.3+45", "minor")?, "^1.3.0+46");
+        assert!(bump("1.2.3+abc", "patch").is_err());
+
+        let pep440 = BumpOptions {
+            scheme: Scheme::Pep440,
+            ..opts.clone()
+        };
+        assert!(bump_semver("1.2.3", &VersionBump::Patch, &pep440).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_versions() -> Result<()> {
+        let toml = "[package]\nname = \"a\"\nversion = \"1.2.3\"\n\n[dependencies]\nserde = \"1.0\"\nanyhow = { version = \"1.0.81\" }\n\n[[bin]]\nversion = \"0.1.0\"\n";
+        let doc = Document::parse(toml, "toml")?;
+        assert_eq!(
+            doc.find_versions()?,
+            [
+                ("package.version".to_string(), "1.2.3".to_string()),
+                (
+                    "dependencies.anyhow.version".to_string(),
+                    "1.0.81".to_string()
+                ),
+                ("bin.0.version".to_string(), "0.1.0".to_string()),
+            ]
+        );
+
+        let json = r#"{"version": "2.0.0-rc.1", "engines": {"node": ">=18"}, "tags": ["3.0.0"]}"#;
+        let found = Document::parse(json, "json")?.find_versions()?;
+        let selectors: Vec<&str> = found
+            .iter()
+            .map(|(selector, _)| selector.as_str())
+            .collect();
+        assert_eq!(selectors, ["version", "tags.0"]);
+
+        let yaml = "apiVersion: v2\nversion: 0.4.0\n\"app.version\": 1.2.3\n";
+        let found = Document::parse(yaml, "yaml")?.find_versions()?;
+        assert_eq!(found[1].0, "\"app.version\"");
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+//! Advisory locking around writes
+
+use anyhow::{Context, Result};
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+/// Longest wait between attempts to take a held lock
+const MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Options for locking a file while it is rewritten
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct LockOptions {
+    /// Hold an advisory lock on `<file>.lock` while reading and writing
+    #[arg(long)]
+    pub lock: bool,
+
+    /// Retry a held lock up to N times with backoff before failing
+    #[arg(long, value_name = "N", requires = "lock")]
+    pub retry_on_lock: Option<u32>,
+}
+
+/// A held lock, released on drop
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl LockOptions {
+    /// Lock `path` if `--lock` was given
+    pub fn acquire(&self, path: &Path) -> Result<Option<FileLock>> {
+        if !self.lock {
+            return Ok(None);
+        }
+
+        let lock_path = lock_path(path);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+
+        let retries = self.retry_on_lock.unwrap_or(0);
+        let mut delay = Duration::from_millis(50);
+        for attempt in 0..=retries {
+            match file.try_lock() {
+                Ok(()) => return Ok(Some(FileLock { _file: file })),
+                Err(TryLockError::WouldBlock) if attempt < retries => {
+                    thread::sleep(delay);
+                    delay = (delay * 2).min(MAX_BACKOFF);
+                }
+                Err(TryLockError::WouldBlock) => break,
+                Err(TryLockError::Error(err)) => return Err(err.into()),
+            }
+        }
+        anyhow::bail!(
+            "{} is locked by another process ({})",
+            path.display(),
+            lock_path.display()
+        )
+    }
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    PathBuf::from(lock_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_retry_on_lock() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("package.json");
+        let opts = |retry_on_lock| LockOptions {
+            lock: true,
+            retry_on_lock,
+        };
+
+        let held = opts(None).acquire(&path)?;
+        assert!(opts(None).acquire(&path).is_err());
+
+        let holder = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            drop(held);
+        });
+        let start = Instant::now();
+        let lock = opts(Some(10)).acquire(&path)?;
+        assert!(lock.is_some());
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        holder.join().unwrap();
+        Ok(())
+    }
+}
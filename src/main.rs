@@ -1,494 +1,4671 @@
 use anyhow::{Context, Result};
-use clap::{Parser, ValueEnum};
+use chrono::Local;
+use clap::{ArgAction, CommandFactory, Parser, ValueEnum};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::{CompleteEnv, Shell};
+use rayon::prelude::*;
 use semver::Version;
 use serde_json::Value as JsonValue;
-use serde_yaml::Value as YamlValue;
 use std::{
-    fs,
+    fs, io,
     path::{Path, PathBuf},
+    process::ExitCode,
 };
-use toml_edit::{DocumentMut, Item, Value as TomlValue};
 
-#[derive(Debug, Clone)]
-enum VersionBump {
-    Major,
-    Minor,
-    Patch,
-    Specific(Version),
+use svbump::backup::BackupOptions;
+use svbump::config::{self, Config, Location, Package};
+use svbump::defaults;
+use svbump::git::{self, GitOptions, TagOptions};
+use svbump::hook::{HookOptions, Hooks};
+use svbump::journal::{self, Replaced};
+use svbump::lock::{FileLock, LockOptions};
+use svbump::maven;
+use svbump::npm;
+use svbump::plugin;
+use svbump::prompt;
+use svbump::remote::RemotePath;
+use svbump::verbosity::{self, Verbosity};
+use svbump::workspace;
+use svbump::{
+    conventional, is_stdio, load_document, parse_document, parse_loose, read_source,
+    read_version_file, render_template, validate_version, write_file, BumpLevel, BumpOptions,
+    Change, Document, FileType, InputOptions, Scheme, Skipped, TargetOptions, VersionBump,
+    STDIO_PATH,
+};
+
+/// Well-known selectors offered when completing a selector
+fn selector_candidates() -> Vec<CompletionCandidate> {
+    defaults::known_selectors()
+        .into_iter()
+        .map(|(selector, files)| {
+            CompletionCandidate::new(selector).help(Some(files.join(", ").into()))
+        })
+        .collect()
+}
+
+/// Resolve the `[LEVEL] [SELECTOR FILE]` positionals of `write` and
+/// `preview`. With `--level-file` the level is omitted and the rest shift
+/// left; without a selector and file the config file's locations are used.
+fn resolve_positionals(
+    level: Option<String>,
+    selector: Option<String>,
+    file: Option<PathBuf>,
+    level_file: Option<PathBuf>,
+) -> Result<(VersionBump, Option<(String, PathBuf)>)> {
+    let (level, selector, file) = match level_file {
+        None => {
+            let level = level.context("A level or --level-file is required")?;
+            (level.parse()?, selector, file)
+        }
+        Some(level_file) => {
+            if file.is_some() {
+                anyhow::bail!("Pass either a level or --level-file, not both");
+            }
+            let content = fs::read_to_string(&level_file)
+                .with_context(|| format!("Failed to read {}", level_file.display()))?;
+            let line = content.lines().next().unwrap_or_default().trim();
+            let parsed = line
+                .parse()
+                .with_context(|| format!("Invalid level {:?} in {}", line, level_file.display()))?;
+            (parsed, level, selector.map(PathBuf::from))
+        }
+    };
+    match (selector, file) {
+        (Some(selector), file) => Ok((level, Some(plain_file_location(selector, file)))),
+        (None, _) => Ok((level, None)),
+    }
+}
+
+/// Resolve the `[SELECTOR] FILE` positionals of `write --interactive` and ask
+/// for the level on the terminal, or None if the user backs out
+fn choose_interactively(
+    selector: Option<String>,
+    file: Option<String>,
+    no_file: Option<PathBuf>,
+    bump: &BumpOptions,
+    input: InputOptions,
+    pattern: Option<&str>,
+    config: Option<&Path>,
+) -> Result<Option<(VersionBump, (String, PathBuf))>> {
+    let selector = selector.context("--interactive needs a file")?;
+    if no_file.is_some() {
+        anyhow::bail!("Pass either a level or --interactive, not both");
+    }
+    let (selector, file) = plain_file_location(selector, file.map(PathBuf::from));
+    let selector = infer_selector(selector, &file, pattern, config)?;
+    let current = read_version_file(&file, input, &selector)?;
+    let target = format!("{} ({})", file.display(), selector);
+    let level = prompt::choose_level(
+        &current,
+        &target,
+        bump,
+        &mut io::stdin().lock(),
+        &mut io::stderr(),
+    )?;
+    Ok(level.map(|level| (level, (selector, file))))
+}
+
+/// Fill in the selector of a file given on its own (the `.` selector): the
+/// `--pattern` for regex files, else the selector svbump.toml or the built-in
+/// table gives its file name, else `.` for a plain version file
+fn infer_selector(
+    selector: String,
+    file: &Path,
+    pattern: Option<&str>,
+    config: Option<&Path>,
+) -> Result<String> {
+    if selector != "." {
+        return Ok(selector);
+    }
+    if let Some(pattern) = pattern {
+        return Ok(pattern.to_string());
+    }
+    let config = Config::load(config)?.unwrap_or_default();
+    let (selector, source) = match config.selector_for(file)? {
+        Some(selector) => (selector, "the config file"),
+        None => match defaults::selector_for(file) {
+            Some(selector) => (selector, "the defaults for its name"),
+            None => (".", "no other match"),
+        },
+    };
+    verbosity::note(format_args!(
+        "{}: using selector {} from {}",
+        file.display(),
+        selector,
+        source
+    ));
+    Ok(selector.to_string())
+}
+
+/// A lone positional names a plain version file, read with the `.` selector
+fn plain_file_location(selector: String, file: Option<PathBuf>) -> (String, PathBuf) {
+    match file {
+        Some(file) => (selector, file),
+        None => (".".to_string(), PathBuf::from(selector)),
+    }
+}
+
+/// Locations set to the bumped version along with the selector and file
+#[derive(Debug, Clone, Default, clap::Args)]
+struct ExtraTargets {
+    /// More files set to the same new version, as FILE:SELECTOR
+    #[arg(value_name = "FILE:SELECTOR")]
+    more: Vec<String>,
+
+    /// Another location set to the same new version, as SELECTOR@FILE (repeatable)
+    #[arg(long = "target", value_name = "SELECTOR@FILE")]
+    targets: Vec<String>,
+
+    /// Another selector in the same file set to the same new version
+    /// (repeatable); a file given without a selector takes the first
+    #[arg(long = "selector", id = "selectors", value_name = "SELECTOR")]
+    selectors: Vec<String>,
 }
 
-impl std::str::FromStr for VersionBump {
-    type Err = anyhow::Error;
+impl ExtraTargets {
+    fn is_empty(&self) -> bool {
+        self.more.is_empty() && self.targets.is_empty() && self.selectors.is_empty()
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "major" => Ok(VersionBump::Major),
-            "minor" => Ok(VersionBump::Minor),
-            "patch" => Ok(VersionBump::Patch),
-            version => {
-                let new_version = Version::parse(version)?;
-                Ok(VersionBump::Specific(new_version))
+    /// The extra locations, with any `--selector` in the file of `location`
+    fn locations(&self, location: &mut Option<(String, PathBuf)>) -> Result<Vec<Location>> {
+        let mut same_file = Vec::new();
+        if !self.selectors.is_empty() {
+            let Some((selector, file)) = location else {
+                anyhow::bail!("--selector needs a file");
+            };
+            let mut selectors = self.selectors.iter().cloned();
+            if selector == "." {
+                *selector = selectors.next().unwrap();
             }
+            same_file.extend(selectors.map(|selector| Location::new(file.clone(), selector)));
         }
+        let more = self
+            .more
+            .iter()
+            .map(|arg| Location::parse_file_selector(arg));
+        let targets = self
+            .targets
+            .iter()
+            .map(|arg| Location::parse_selector_at_file(arg));
+        let extra = more.chain(targets).collect::<Result<Vec<_>>>()?;
+        Ok(same_file.into_iter().chain(extra).collect())
     }
 }
 
 #[derive(Parser)]
-#[command(version, about)]
+#[command(version, about, arg_required_else_help = true)]
 struct Args {
     #[command(subcommand)]
-    command: Command,
+    command: Option<Command>,
+
+    #[command(flatten)]
+    input: InputOptions,
+
+    /// What to do when a file fails to parse during a multi-file operation:
+    /// `list`, `foreach`, and a `write` or `preview` of a glob or several
+    /// locations. The file holding the version bumped from is always needed.
+    #[arg(long, value_enum, default_value_t = ParseErrorPolicy::Fail, global = true)]
+    on_parse_error: ParseErrorPolicy,
+
+    /// Regex for `--type regex` files, capturing the version as `version`
+    /// (e.g. '__version__ = "(?P<version>.+)"'); the selector can then be left out
+    #[arg(long, global = true)]
+    pattern: Option<String>,
 
-    /// Force specific file type
-    #[arg(short = 't', long = "type", value_enum)]
-    file_type: Option<FileType>,
+    /// Config file to use instead of discovering svbump.toml or .svbump.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Directory to keep the journal `undo` reads in
+    #[arg(long, global = true, value_name = "DIR", default_value = ".")]
+    journal_dir: PathBuf,
+
+    /// Print the effective configuration, with command-line overrides, and exit
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "toml"
+    )]
+    print_config: Option<ConfigFormat>,
+
+    /// Output format; json is the same as passing --json to every command
+    #[arg(long, global = true, value_enum, value_name = "FORMAT")]
+    output: Option<OutputFormat>,
+
+    /// Exit with status 0 when a bump is skipped as a no-op (the default)
+    #[arg(long, global = true, overrides_with = "no_op_exit_nonzero")]
+    no_op_exit_zero: bool,
+
+    /// Exit with status 3 when a bump is skipped as a no-op
+    #[arg(long, global = true, overrides_with = "no_op_exit_zero")]
+    no_op_exit_nonzero: bool,
+
+    /// Print only the bare version on stdout and no notes on stderr, for
+    /// command substitution; JSON output is unaffected
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log the detected file type, how the selector was resolved and each
+    /// change to stderr
+    #[arg(short, long, global = true)]
+    verbose: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// A single component of a version, printed by `read --part`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Part {
+    Major,
+    Minor,
+    Patch,
+    Prerelease,
+    Build,
+}
+
+impl Part {
+    /// The `--format` placeholder for this part
+    fn placeholder(&self) -> &'static str {
+        match self {
+            Part::Major => "{major}",
+            Part::Minor => "{minor}",
+            Part::Patch => "{patch}",
+            Part::Prerelease => "{prerelease}",
+            Part::Build => "{build}",
+        }
+    }
+}
+
+/// What `foreach` does with each package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ForeachAction {
+    Write,
+    Preview,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+/// Exit status for skipped bumps under `--no-op-exit-nonzero`
+const NO_OP_EXIT_CODE: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ParseErrorPolicy {
+    /// Log the file and continue with the rest
+    Skip,
+    /// Abort the whole run
+    Fail,
 }
 
 #[derive(clap::Subcommand)]
 enum Command {
     /// Read current version
     Read {
-        /// Field selector using dot notation (e.g. "package.version")
+        /// Field selector using dot notation (e.g. "package.version"), or the
+        /// file itself for a plain version file
+        #[arg(add = ArgValueCandidates::new(selector_candidates))]
         selector: String,
 
         /// Path to the file to process
-        file: PathBuf,
+        file: Option<PathBuf>,
+
+        /// Select the `[[package]]` entry with this name in a Cargo.lock
+        #[arg(long)]
+        package: Option<String>,
+
+        /// Print the version in canonical semver form (e.g. "1.2" as "1.2.0")
+        #[arg(long)]
+        canonical: bool,
+
+        /// Print the version through this template, e.g. "{major}.{minor}",
+        /// with {version}, {major}, {minor}, {patch}, {prerelease} and {build}
+        #[arg(long, value_name = "TEMPLATE")]
+        format: Option<String>,
+
+        /// Print just this component of the version
+        #[arg(long, value_enum, conflicts_with = "format")]
+        part: Option<Part>,
+
+        /// Fail unless the version is valid under this scheme (semver when
+        /// given on its own), e.g. to lint a manifest in CI
+        #[arg(
+            long,
+            value_enum,
+            value_name = "SCHEME",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "semver"
+        )]
+        validate: Option<Scheme>,
+
+        /// Format of the calendar versions checked by --validate=calver
+        /// (default: YYYY.MM.MICRO)
+        #[arg(long, value_name = "FORMAT", requires = "validate")]
+        calver_format: Option<String>,
     },
+
     /// Write new version
     Write {
-        /// Version segment to update (major, minor, patch)
-        #[arg(value_parser = clap::value_parser!(VersionBump))]
-        level: VersionBump,
+        /// Version segment to update (major, minor, patch, alpha, beta, rc, pre, build, post, dev, revision, epoch), omitted with --level-file or --interactive
+        #[arg(required_unless_present_any = ["level_file", "interactive"])]
+        level: Option<String>,
+
+        /// Field selector using dot notation (e.g. "package.version"),
+        /// omitted to use the locations in svbump.toml
+        #[arg(add = ArgValueCandidates::new(selector_candidates))]
+        selector: Option<String>,
+
+        /// Path to the file to process
+        file: Option<PathBuf>,
+
+        #[command(flatten)]
+        extra: ExtraTargets,
+
+        /// Read the level from the first line of this file instead
+        #[arg(long)]
+        level_file: Option<PathBuf>,
+
+        /// Choose the level from a list of the versions each gives, and
+        /// confirm before writing
+        #[arg(short, long, conflicts_with_all = ["level_file", "dry_run", "json"])]
+        interactive: bool,
+
+        #[command(flatten)]
+        target: TargetOptions,
+
+        #[command(flatten)]
+        bump: BumpOptions,
+
+        #[command(flatten)]
+        lock: LockOptions,
+
+        #[command(flatten)]
+        backup: BackupOptions,
+
+        #[command(flatten)]
+        tag: TagOptions,
+
+        #[command(flatten)]
+        git: GitOptions,
+
+        #[command(flatten)]
+        hooks: HookOptions,
+
+        /// Also set the version in package-lock.json and npm-shrinkwrap.json
+        /// next to package.json
+        #[arg(long)]
+        update_lockfile: bool,
+
+        /// Also move the parent version of a Maven pom.xml's modules, and
+        /// dependencies on the project or its modules, to the new version, as
+        /// `mvn versions:set` does
+        #[arg(long)]
+        propagate: bool,
+
+        /// Also move this crate's entry in the `[workspace.dependencies]` of
+        /// its Cargo workspace to the new version, keeping its operator
+        #[arg(long)]
+        workspace_deps: bool,
+
+        /// Also move the `## [Unreleased]` entries of this changelog under a
+        /// heading for the new version
+        #[arg(long, value_name = "FILE")]
+        changelog: Option<PathBuf>,
+
+        /// Write the updated document to this file instead, leaving the
+        /// source file untouched
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["update_lockfile", "propagate", "workspace_deps"])]
+        out: Option<PathBuf>,
+
+        /// Print a unified diff of the changes instead of writing them
+        #[arg(long, conflicts_with_all = ["json", "commit", "tag"])]
+        dry_run: bool,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Preview version bump without making changes
+    Preview {
+        /// Version segment to update (major, minor, patch, alpha, beta, rc, pre, build, post, dev, revision, epoch), omitted with --level-file
+        #[arg(required_unless_present = "level_file")]
+        level: Option<String>,
 
+        /// Field selector using dot notation (e.g. "package.version"),
+        /// omitted to use the locations in svbump.toml
+        #[arg(add = ArgValueCandidates::new(selector_candidates))]
+        selector: Option<String>,
+
+        /// Path to the file to process
+        file: Option<PathBuf>,
+
+        #[command(flatten)]
+        extra: ExtraTargets,
+
+        /// Read the level from the first line of this file instead
+        #[arg(long)]
+        level_file: Option<PathBuf>,
+
+        #[command(flatten)]
+        target: TargetOptions,
+
+        #[command(flatten)]
+        bump: BumpOptions,
+
+        #[command(flatten)]
+        tag: TagOptions,
+
+        /// Bump the highest version tagged `<tag-prefix>X.Y.Z` that is
+        /// reachable from HEAD, instead of a version in a file
+        #[arg(long, conflicts_with_all = ["selector", "file", "selectors"])]
+        from_git: bool,
+
+        /// Print the version through this template, e.g. "{major}.{minor}",
+        /// with {version}, {major}, {minor}, {patch}, {prerelease} and {build}
+        #[arg(long, value_name = "TEMPLATE", conflicts_with = "json")]
+        format: Option<String>,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Finalize a pre-release version (e.g. 2.0.0-rc.3 -> 2.0.0)
+    Release {
         /// Field selector using dot notation (e.g. "package.version")
+        #[arg(add = ArgValueCandidates::new(selector_candidates))]
         selector: String,
 
         /// Path to the file to process
         file: PathBuf,
+
+        #[command(flatten)]
+        lock: LockOptions,
+
+        #[command(flatten)]
+        tag: TagOptions,
+
+        #[command(flatten)]
+        git: GitOptions,
+
+        #[command(flatten)]
+        hooks: HookOptions,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
     },
-    /// Preview version bump without making changes
-    Preview {
-        /// Version segment to update (major, minor, patch)
-        #[arg(value_parser = clap::value_parser!(VersionBump))]
-        level: VersionBump,
 
+    /// Bump by the Conventional Commits made since the last git tag
+    Auto {
         /// Field selector using dot notation (e.g. "package.version")
+        #[arg(add = ArgValueCandidates::new(selector_candidates))]
         selector: String,
 
         /// Path to the file to process
         file: PathBuf,
+
+        /// Print which commits decided the level
+        #[arg(long)]
+        explain: bool,
+
+        #[command(flatten)]
+        bump: BumpOptions,
+
+        #[command(flatten)]
+        lock: LockOptions,
+
+        #[command(flatten)]
+        tag: TagOptions,
+
+        #[command(flatten)]
+        git: GitOptions,
+
+        #[command(flatten)]
+        hooks: HookOptions,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
     },
-}
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+    /// Render a template file with the bumped version
+    Render {
+        /// Version segment to update (major, minor, patch, alpha, beta, rc, pre, build, post, dev, revision, epoch)
+        #[arg(value_parser = clap::value_parser!(VersionBump))]
+        level: VersionBump,
 
-    match args.command {
-        Command::Read { selector, file } => {
-            let path = file.as_path();
-            let content = fs::read_to_string(path)?;
+        /// Field selector using dot notation (e.g. "package.version")
+        #[arg(add = ArgValueCandidates::new(selector_candidates))]
+        selector: String,
 
-            let version = match get_file_type(path, args.file_type)? {
-                "toml" => {
-                    let doc = content.parse::<DocumentMut>()?;
-                    read_version_toml(&doc, &selector)?
-                }
-                "yml" | "yaml" => {
-                    let value: YamlValue = serde_yaml::from_str(&content)?;
-                    read_version_yaml(&value, &selector)?
-                }
-                _ => {
-                    let value: JsonValue = serde_json::from_str(&content)
-                        .context("Failed to parse JSON with preserved ordering")?;
-                    read_version_json(&value, &selector)?
-                }
-            };
-            println!("{}", version);
-        }
-        Command::Preview { level, selector, file } => {
-            let path = file.as_path();
-            let content = fs::read_to_string(path)?;
-            
-            let current_version = match get_file_type(path, args.file_type)? {
-                "toml" => {
-                    let doc = content.parse::<DocumentMut>()?;
-                    read_version_toml(&doc, &selector)?
-                }
-                "yml" | "yaml" => {
-                    let value: YamlValue = serde_yaml::from_str(&content)?;
-                    read_version_yaml(&value, &selector)?
-                }
-                _ => {
-                    let value: JsonValue = serde_json::from_str(&content)?;
-                    read_version_json(&value, &selector)?
-                }
-            };
+        /// Path to the file holding the current version
+        file: PathBuf,
 
-            let new_version = bump_semver(&current_version, &level)?;
-            println!("{}", new_version);
-        }
-        Command::Write { level, selector, file } => {
-            let path = file.as_path();
-            let content = fs::read_to_string(path)?;
-            match get_file_type(path, args.file_type)? {
-                "toml" => {
-                    let mut doc = content.parse::<DocumentMut>()?;
-                    bump_version_toml(&mut doc, &selector, &level)?;
-                    fs::write(path, doc.to_string())?;
-                }
-                "yml" | "yaml" => {
-                    let mut value: YamlValue = serde_yaml::from_str(&content)?;
-                    bump_version_yaml(&mut value, &selector, &level)?;
-                    fs::write(path, serde_yaml::to_string(&value)?)?;
-                }
-                _ => {
-                    let mut value: JsonValue = serde_json::from_str(&content)?;
-                    bump_version_json(&mut value, &selector, &level)?;
-                    fs::write(path, format!("{}\n", serde_json::to_string_pretty(&value)?))?;
-                }
-            }
-        }
-    }
+        /// Template with `{version}`, `{major}`, `{minor}`, `{patch}`,
+        /// `{prerelease}` and `{build}` placeholders
+        #[arg(long)]
+        template: PathBuf,
 
-    Ok(())
-}
+        /// Where to write the rendered template
+        #[arg(long)]
+        out: PathBuf,
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum FileType {
-    Json,
-    Yaml,
-    Toml,
-}
+        #[command(flatten)]
+        bump: BumpOptions,
+    },
 
-impl FileType {
-    fn as_str(&self) -> &'static str {
-        match self {
-            FileType::Json => "json",
-            FileType::Yaml => "yaml",
-            FileType::Toml => "toml",
-        }
-    }
-}
+    /// Check that versions match an expected version, or each other, without
+    /// changing anything. Exits 1 on a mismatch.
+    Check {
+        /// An optional expected version, followed by `SELECTOR FILE` pairs,
+        /// `FILE:SELECTOR` arguments or plain version files; svbump.toml is
+        /// used when no files are given
+        #[arg(value_name = "VERSION|SELECTOR FILE|FILE:SELECTOR")]
+        args: Vec<String>,
 
-fn get_file_type<'a>(path: &Path, override_type: Option<FileType>) -> Result<&'a str> {
-    if let Some(typ) = override_type {
-        Ok(typ.as_str())
-    } else {
-        let ext = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .ok_or_else(|| anyhow::anyhow!("File has no extension"))?;
+        /// Print results as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
-        match ext {
-            "json" => Ok("json"),
-            "yml" | "yaml" => Ok("yaml"),
-            "toml" => Ok("toml"),
-            _ => anyhow::bail!("Unsupported file extension: {}", ext),
-        }
-    }
-}
+    /// Compare the versions at two locations, printing whether the first is
+    /// equal to, greater or less than the second. Exits 1 unless equal.
+    Compare {
+        /// Selector of the first version
+        #[arg(add = ArgValueCandidates::new(selector_candidates))]
+        left_selector: String,
 
-fn bump_semver(version: &str, level: &VersionBump) -> Result<String> {
-    let current = Version::parse(version)?;
+        /// File holding the first version
+        left_file: PathBuf,
 
-    let new_version = match level {
-        VersionBump::Major => {
-            let mut v = current.clone();
-            v.major += 1;
-            v.minor = 0;
-            v.patch = 0;
-            v
-        }
-        VersionBump::Minor => {
-            let mut v = current.clone();
-            v.minor += 1;
-            v.patch = 0;
-            v
-        }
-        VersionBump::Patch => {
-            let mut v = current.clone();
-            v.patch += 1;
-            v
-        }
-        VersionBump::Specific(target) => {
-            if target < &current {
-                anyhow::bail!(
-                    "New version {} must be greater than current version {}",
-                    target,
-                    current
-                );
-            }
-            if target == &current {
-                println!("Version not changed (already at {})", current);
-            }
-            target.clone()
-        }
-    };
+        /// Selector of the second version
+        #[arg(add = ArgValueCandidates::new(selector_candidates))]
+        right_selector: String,
 
-    // Preserve any existing pre-release and build metadata
-    Ok(format!(
-        "{}.{}.{}",
-        new_version.major, new_version.minor, new_version.patch
-    ))
-}
+        /// File holding the second version
+        right_file: PathBuf,
 
-fn bump_version_toml(doc: &mut DocumentMut, selector: &str, level: &VersionBump) -> Result<()> {
-    let path_parts: Vec<&str> = selector.split('.').collect();
-    let mut current = doc.as_table_mut();
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
-    for part in &path_parts[..path_parts.len() - 1] {
-        current = current[part]
-            .as_table_mut()
-            .with_context(|| format!("No table found at selector {}", part))?;
-    }
+    /// List the version each level would bump to, e.g. for a release tool to
+    /// offer as choices
+    Next {
+        /// Field selector using dot notation (e.g. "package.version"), or the
+        /// file itself for a plain version file
+        #[arg(add = ArgValueCandidates::new(selector_candidates))]
+        selector: String,
 
-    let last_part = path_parts.last().unwrap();
-    let version = current[last_part]
-        .as_str()
-        .with_context(|| format!("No string value found at {}", selector))?;
+        /// Path to the file to process
+        file: Option<PathBuf>,
 
-    let new_version = bump_semver(version, level)?;
-    current[last_part] = Item::Value(TomlValue::from(new_version));
-    Ok(())
-}
+        #[command(flatten)]
+        bump: BumpOptions,
 
-fn walk_json_mut<'a>(value: &'a mut JsonValue, parts: &[&str]) -> Result<&'a mut JsonValue> {
-    let part = parts[0];
-    let value = value
-        .get_mut(part)
-        .with_context(|| format!("Missing key: {}", part))?;
+        /// Print the candidates as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
-    if parts.len() == 1 {
-        Ok(value)
-    } else {
-        walk_json_mut(value, &parts[1..])
-    }
-}
+    /// Bump the chart version in a Helm Chart.yaml, and set its appVersion
+    /// in the same write
+    Helm {
+        /// Version segment to update (major, minor, patch, alpha, beta, rc, pre, build, or a version)
+        level: String,
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use tempfile::NamedTempFile;
+        /// Chart.yaml, or the chart directory holding it
+        #[arg(default_value = "Chart.yaml")]
+        chart: PathBuf,
 
-    #[test]
-    fn test_json_version_bump() -> Result<()> {
-        let json_content = r#"{
-            "name": "test-package",
-            "version": "1.2.3"
-        }"#;
+        /// Set appVersion to this version, when the chart has one
+        #[arg(long, value_name = "VERSION")]
+        app_version: Option<String>,
 
-        let temp_file = NamedTempFile::new()?;
-        fs::write(&temp_file, json_content)?;
+        /// Set appVersion to the version at this location, when the chart
+        /// has one
+        #[arg(long, value_name = "SELECTOR@FILE", conflicts_with = "app_version")]
+        app_version_from: Option<String>,
 
-        let args = Args {
-            command: Command::Write {
-                level: VersionBump::Patch,
-                selector: "version".to_string(),
-                file: temp_file.path().to_path_buf(),
-            },
-            file_type: None,
-        };
+        #[command(flatten)]
+        bump: BumpOptions,
 
-        let content = fs::read_to_string(temp_file.path())?;
-        let mut value: JsonValue = serde_json::from_str(&content)?;
-        if let Command::Write { level, selector, .. } = &args.command {
-            bump_version_json(&mut value, &selector, level)?;
-        }
+        #[command(flatten)]
+        lock: LockOptions,
 
-        assert_eq!(value["version"], "1.2.4");
-        Ok(())
-    }
+        #[command(flatten)]
+        backup: BackupOptions,
 
-    #[test]
-    fn test_toml_version_bump() -> Result<()> {
-        let toml_content = r#"
-[package]
-name = "test-package"
-version = "1.2.3"
-"#;
+        #[command(flatten)]
+        tag: TagOptions,
 
-        let temp_file = NamedTempFile::new()?;
-        fs::write(&temp_file, toml_content)?;
+        #[command(flatten)]
+        git: GitOptions,
 
-        let args = Args {
-            command: Command::Write {
-                level: VersionBump::Minor,
-                selector: "package.version".to_string(),
-                file: temp_file.path().to_path_buf(),
-            },
-            file_type: None,
-        };
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
-        let content = fs::read_to_string(temp_file.path())?;
-        let mut doc = content.parse::<DocumentMut>()?;
-        if let Command::Write { level, selector, .. } = &args.command {
-            bump_version_toml(&mut doc, &selector, level)?;
-        }
+    /// Compare the version in a file with the same file at a git revision,
+    /// printing both and the size of the bump
+    Diff {
+        /// Field selector using dot notation (e.g. "package.version")
+        #[arg(add = ArgValueCandidates::new(selector_candidates))]
+        selector: String,
 
-        assert_eq!(doc["package"]["version"].as_str().unwrap(), "1.3.0");
-        Ok(())
-    }
+        /// Path to the file to process
+        file: PathBuf,
 
-    #[test]
-    fn test_specific_version_bump() -> Result<()> {
-        let json_content = r#"{
-            "name": "test-package",
-            "version": "1.2.3"
-        }"#;
+        /// Git revision to read the old version from (e.g. HEAD~1, v1.2.0)
+        #[arg(long, value_name = "REF", default_value = "HEAD")]
+        against: String,
 
-        let temp_file = NamedTempFile::new()?;
-        fs::write(&temp_file, json_content)?;
+        /// Exit 1 unless the bump is of this size
+        #[arg(long, value_enum, value_name = "LEVEL")]
+        expect: Option<BumpLevel>,
 
-        let content = fs::read_to_string(temp_file.path())?;
-        let mut value: JsonValue = serde_json::from_str(&content)?;
-        bump_version_json(
-            &mut value,
-            "version",
-            &VersionBump::Specific(Version::new(2, 5, 0)),
-        )?;
-        assert_eq!(value["version"], "2.5.0");
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
-        // Test that setting a lower version fails
-        let result = bump_version_json(
-            &mut value,
-            "version",
-            &VersionBump::Specific(Version::new(1, 0, 0)),
-        );
+    /// Copy the version at one location to other files, writing only the
+    /// files that differ
+    Sync {
+        /// Selector and file holding the canonical version
+        #[arg(
+            long,
+            num_args = 2,
+            value_names = ["SELECTOR", "FILE"],
+            required = true,
+            action = ArgAction::Set
+        )]
+        from: Vec<String>,
 
-        assert!(result.is_err());
-        Ok(())
-    }
+        /// Selector and file to update, repeatable
+        #[arg(long, num_args = 2, value_names = ["SELECTOR", "FILE"], required = true)]
+        to: Vec<String>,
 
-    #[test]
-    fn test_yaml_version_bump() -> Result<()> {
-        let yaml_content = r#"
-name: test-package
-version: 1.2.3
-"#;
+        #[command(flatten)]
+        lock: LockOptions,
 
-        let temp_file = NamedTempFile::new()?;
-        fs::write(&temp_file, yaml_content)?;
+        /// Print results as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
-        let args = Args {
-            command: Command::Write {
-                level: VersionBump::Major,
-                selector: "version".to_string(),
-                file: temp_file.path().to_path_buf(),
-            },
-            file_type: None,
-        };
+    /// Put back the versions replaced by the last write, as recorded in
+    /// .svbump/history.jsonl; repeat to go further back
+    Undo {
+        /// Undo even if a version has changed since it was written
+        #[arg(long)]
+        force: bool,
 
-        let content = fs::read_to_string(temp_file.path())?;
-        let mut value: YamlValue = serde_yaml::from_str(&content)?;
-        if let Command::Write { level, selector, .. } = &args.command {
-            bump_version_yaml(&mut value, &selector, level)?;
-        }
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
-        assert_eq!(value["version"].as_str().unwrap(), "2.0.0");
-        Ok(())
-    }
-}
+    /// Commands for Cargo workspaces
+    Workspace {
+        #[command(subcommand)]
+        command: WorkspaceCommand,
+    },
 
-fn walk_yaml_mut<'a>(value: &'a mut YamlValue, parts: &[&str]) -> Result<&'a mut YamlValue> {
-    let part = parts[0];
-    let value = value
-        .get_mut(part)
-        .with_context(|| format!("Missing key: {}", part))?;
+    /// Print a shell completion script
+    Completions {
+        /// Shell to complete in
+        shell: Shell,
+    },
 
-    if parts.len() == 1 {
-        Ok(value)
-    } else {
-        walk_yaml_mut(value, &parts[1..])
-    }
-}
+    /// Write a starter svbump.toml listing the known manifests in the
+    /// current directory that hold a version
+    Init {
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
 
-fn bump_version_yaml(value: &mut YamlValue, selector: &str, bump: &VersionBump) -> Result<()> {
-    let parts: Vec<&str> = selector.split('.').collect();
-    let target = walk_yaml_mut(value, &parts)?;
+    /// Bump each package in the `[packages]` of svbump.toml from its own
+    /// version, printing a summary of old and new versions
+    Foreach {
+        /// Whether to write the new versions or only print them
+        #[arg(value_enum)]
+        action: ForeachAction,
 
-    let version = target
-        .as_str()
-        .with_context(|| format!("Version field is not a string at {}", selector))?;
+        /// Version segment to update (major, minor, patch, alpha, beta, rc, pre, build, post, dev, revision, epoch)
+        level: String,
 
-    let new_version = bump_semver(version, bump)?;
-    *target = YamlValue::String(new_version);
-    Ok(())
-}
+        /// Only bump these packages (comma-separated)
+        #[arg(long, value_delimiter = ',', value_name = "PACKAGE")]
+        only: Vec<String>,
 
-fn bump_version_json(value: &mut JsonValue, selector: &str, bump: &VersionBump) -> Result<()> {
-    let parts: Vec<&str> = selector.split('.').collect();
-    let target = walk_json_mut(value, &parts)?;
+        #[command(flatten)]
+        bump: BumpOptions,
 
-    let version = target
-        .as_str()
-        .with_context(|| format!("Version field is not a string at {}", selector))?;
+        #[command(flatten)]
+        lock: LockOptions,
 
-    let new_version = bump_semver(version, bump)?;
-    *target = JsonValue::String(new_version);
-    Ok(())
-}
-fn walk_json<'a>(value: &'a JsonValue, parts: &[&str]) -> Result<&'a JsonValue> {
-    let part = parts[0];
-    let value = value
-        .get(part)
-        .with_context(|| format!("Missing key: {}", part))?;
+        #[command(flatten)]
+        backup: BackupOptions,
 
-    if parts.len() == 1 {
-        Ok(value)
-    } else {
-        walk_json(value, &parts[1..])
-    }
-}
+        #[command(flatten)]
+        hooks: HookOptions,
 
-fn walk_yaml<'a>(value: &'a YamlValue, parts: &[&str]) -> Result<&'a YamlValue> {
-    let part = parts[0];
-    let value = value
-        .get(part)
-        .with_context(|| format!("Missing key: {}", part))?;
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
-    if parts.len() == 1 {
-        Ok(value)
-    } else {
-        walk_yaml(value, &parts[1..])
-    }
-}
+    /// List versions across files matching glob patterns
+    List {
+        /// Glob patterns of files to read (e.g. "crates/*/Cargo.toml")
+        #[arg(required = true)]
+        patterns: Vec<String>,
 
-fn read_version_json(value: &JsonValue, selector: &str) -> Result<String> {
-    let parts: Vec<&str> = selector.split('.').collect();
-    let target = walk_json(value, &parts)?;
+        /// Field selector using dot notation (e.g. "package.version"); without
+        /// one, every field holding a semver version is listed with its selector
+        #[arg(short, long, add = ArgValueCandidates::new(selector_candidates))]
+        selector: Option<String>,
 
-    target
-        .as_str()
-        .with_context(|| format!("Version field is not a string at {}", selector))
-        .map(String::from)
+        /// Skip matched files that also match this glob (e.g. "**/node_modules/**"), repeatable
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+
+        /// Print results as JSON
+        #[arg(long)]
+        json: bool,
+    },
 }
 
-fn read_version_yaml(value: &YamlValue, selector: &str) -> Result<String> {
-    let parts: Vec<&str> = selector.split('.').collect();
-    let target = walk_yaml(value, &parts)?;
+#[derive(clap::Subcommand)]
+enum WorkspaceCommand {
+    /// Bump workspace.package.version in a workspace's Cargo.toml
+    Write {
+        /// Version segment to update (major, minor, patch, alpha, beta, rc, pre, build, post, dev, revision, epoch, or a version)
+        #[arg(value_parser = clap::value_parser!(VersionBump))]
+        level: VersionBump,
+
+        /// Root manifest of the workspace
+        #[arg(long, default_value = "Cargo.toml")]
+        manifest: PathBuf,
 
-    target
-        .as_str()
-        .with_context(|| format!("Version field is not a string at {}", selector))
-        .map(String::from)
-}
+        /// Also move the version requirements of path dependencies on members
+        /// that inherit the workspace version
+        #[arg(long)]
+        path_deps: bool,
 
-fn read_version_toml(doc: &DocumentMut, selector: &str) -> Result<String> {
-    let path_parts: Vec<&str> = selector.split('.').collect();
-    let mut current = doc.as_table();
+        #[command(flatten)]
+        bump: BumpOptions,
 
-    for part in &path_parts[..path_parts.len() - 1] {
-        current = current
-            .get(*part)
-            .and_then(|v| v.as_table())
-            .with_context(|| format!("No table found at selector {}", part))?;
-    }
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
 
-    let last_part = path_parts.last().unwrap();
-    current
-        .get(*last_part)
-        .and_then(|v| v.as_str())
-        .with_context(|| format!("No string value found at {}", selector))
-        .map(String::from)
+impl Command {
+    /// The selector and file named on the command line, if any
+    fn location(&self) -> Option<(String, PathBuf)> {
+        match self {
+            Command::Read { selector, file, .. } | Command::Next { selector, file, .. } => {
+                Some(plain_file_location(selector.clone(), file.clone()))
+            }
+            Command::Release { selector, file, .. }
+            | Command::Auto { selector, file, .. }
+            | Command::Render { selector, file, .. } => Some((selector.clone(), file.clone())),
+            Command::Write {
+                level,
+                selector,
+                file,
+                level_file,
+                ..
+            }
+            | Command::Preview {
+                level,
+                selector,
+                file,
+                level_file,
+                ..
+            } => match level_file {
+                Some(_) => Some(plain_file_location(
+                    level.clone()?,
+                    selector.as_ref().map(PathBuf::from),
+                )),
+                None => Some(plain_file_location(selector.clone()?, file.clone())),
+            },
+            Command::List { .. }
+            | Command::Check { .. }
+            | Command::Compare { .. }
+            | Command::Diff { .. }
+            | Command::Helm { .. }
+            | Command::Sync { .. }
+            | Command::Undo { .. }
+            | Command::Workspace { .. }
+            | Command::Completions { .. }
+            | Command::Init { .. }
+            | Command::Foreach { .. } => None,
+        }
+    }
+
+    /// The command's `--json` flag, if it has one
+    fn json_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            Command::Write { json, .. }
+            | Command::Preview { json, .. }
+            | Command::Release { json, .. }
+            | Command::Auto { json, .. }
+            | Command::Check { json, .. }
+            | Command::Compare { json, .. }
+            | Command::Next { json, .. }
+            | Command::Diff { json, .. }
+            | Command::Helm { json, .. }
+            | Command::Sync { json, .. }
+            | Command::Undo { json, .. }
+            | Command::List { json, .. }
+            | Command::Foreach { json, .. }
+            | Command::Workspace {
+                command: WorkspaceCommand::Write { json, .. },
+            } => Some(json),
+            Command::Read { .. }
+            | Command::Render { .. }
+            | Command::Completions { .. }
+            | Command::Init { .. } => None,
+        }
+    }
+
+    /// Extra targets given with `FILE:SELECTOR` or `--target`
+    fn extra_locations(&self, location: &mut Option<(String, PathBuf)>) -> Result<Vec<Location>> {
+        match self {
+            Command::Write { extra, .. } | Command::Preview { extra, .. } => {
+                extra.locations(location)
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+fn main() -> Result<ExitCode> {
+    // Answers completion requests from shells set up with `COMPLETE=<shell> svbump`
+    CompleteEnv::with_factory(Args::command).complete();
+    run(Args::parse())
+}
+
+fn run(args: Args) -> Result<ExitCode> {
+    let mut no_op = false;
+    verbosity::set(Verbosity::from_flags(args.quiet, args.verbose));
+    if let Some(config) = Config::load(args.config.as_deref())? {
+        plugin::register(&config.plugins);
+    }
+
+    if let Some(format) = args.print_config {
+        println!(
+            "{}",
+            format_config(&effective_config(&args)?, format)?.trim_end()
+        );
+        return Ok(ExitCode::SUCCESS);
+    }
+    let Some(mut command) = args.command else {
+        anyhow::bail!("A subcommand is required, see --help");
+    };
+    let output_json = args.output == Some(OutputFormat::Json);
+    if let Some(json) = command.json_mut() {
+        *json |= output_json;
+    }
+    if args.pattern.is_some() && args.input.file_type != Some(FileType::Regex) {
+        anyhow::bail!("--pattern only applies to --type regex");
+    }
+    // `release` is `write` with the release level
+    let command = match command {
+        Command::Release {
+            selector,
+            file,
+            lock,
+            tag,
+            git,
+            hooks,
+            json,
+        } => Command::Write {
+            level: Some("release".to_string()),
+            selector: Some(selector),
+            file: Some(file),
+            extra: ExtraTargets::default(),
+            level_file: None,
+            interactive: false,
+            target: TargetOptions::default(),
+            bump: BumpOptions::default(),
+            lock,
+            backup: BackupOptions::default(),
+            tag,
+            git,
+            hooks,
+            update_lockfile: false,
+            propagate: false,
+            workspace_deps: false,
+            changelog: None,
+            out: None,
+            dry_run: false,
+            json,
+        },
+        Command::Auto {
+            selector,
+            file,
+            explain,
+            bump,
+            lock,
+            tag,
+            git,
+            hooks,
+            json,
+        } => {
+            let Some(level) = auto_level(&file, explain)? else {
+                let skipped = Skipped {
+                    current: read_version_file(&file, args.input, &selector)?,
+                    reason: "no feat, fix or breaking commits since the last tag".to_string(),
+                };
+                print_skipped(&file, &selector, skipped.into(), json, None)?;
+                return exit_code(true, args.no_op_exit_nonzero);
+            };
+            Command::Write {
+                level: Some(level.as_str().to_string()),
+                selector: Some(selector),
+                file: Some(file),
+                extra: ExtraTargets::default(),
+                level_file: None,
+                interactive: false,
+                target: TargetOptions::default(),
+                bump,
+                lock,
+                backup: BackupOptions::default(),
+                tag,
+                git,
+                hooks,
+                update_lockfile: false,
+                propagate: false,
+                workspace_deps: false,
+                changelog: None,
+                out: None,
+                dry_run: false,
+                json,
+            }
+        }
+        command => command,
+    };
+
+    match command {
+        Command::Read {
+            selector,
+            file,
+            package,
+            canonical,
+            format,
+            part,
+            validate,
+            calver_format,
+        } => {
+            if output_json && (format.is_some() || part.is_some()) {
+                anyhow::bail!("--format and --part can't be used with JSON output");
+            }
+            let (selector, file) = plain_file_location(selector, file);
+            let selector = infer_selector(
+                selector,
+                &file,
+                args.pattern.as_deref(),
+                args.config.as_deref(),
+            )?;
+            let version = match package {
+                Some(name) => {
+                    load_document(&file, args.input)?.read_package_version(&name, &selector)?
+                }
+                None => read_version_file(&file, args.input, &selector)?,
+            };
+            if let Some(scheme) = validate {
+                validate_version(&version, scheme, calver_format.as_deref()).with_context(
+                    || format!("Invalid version at {} in {}", selector, file.display()),
+                )?;
+            }
+            let version = if canonical {
+                parse_loose(&version)?.to_string()
+            } else {
+                version
+            };
+            if output_json {
+                let output = serde_json::json!({
+                    "file": file,
+                    "selector": selector,
+                    "version": version,
+                });
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else {
+                let format = part
+                    .map(|part| part.placeholder())
+                    .or(format.as_deref())
+                    .unwrap_or("{version}");
+                println!("{}", render_template(format, &version)?);
+            }
+        }
+        Command::Preview {
+            level,
+            selector,
+            file,
+            extra,
+            level_file,
+            target,
+            bump,
+            tag,
+            from_git,
+            format,
+            json,
+        } => {
+            if json && format.is_some() {
+                anyhow::bail!("--format can't be used with JSON output");
+            }
+            let has_format = format.is_some();
+            let format = format.unwrap_or_else(|| "{version}".to_string());
+            if from_git {
+                let (level, _) = resolve_positionals(level, None, None, level_file)?;
+                no_op = preview_from_git(&level, &bump, &tag, json, &format)?;
+                return exit_code(no_op, args.no_op_exit_nonzero);
+            }
+            let (level, mut location) = resolve_positionals(level, selector, file, level_file)?;
+            let extra = extra.locations(&mut location)?;
+            let (selector, file) = match location {
+                Some((selector, file)) if extra.is_empty() => {
+                    let pattern = args.pattern.as_deref();
+                    let config = args.config.as_deref();
+                    (infer_selector(selector, &file, pattern, config)?, file)
+                }
+                location => {
+                    let config = multi_config(args.config.as_deref(), location, extra, &target)?;
+                    match bump_config(
+                        &config,
+                        args.input,
+                        args.on_parse_error,
+                        &level,
+                        &bump,
+                        None,
+                    ) {
+                        Ok(changes) => print_config_changes(&changes, &tag, json, Some(&format))?,
+                        Err(err) => {
+                            let primary = config.primary.as_ref().unwrap();
+                            print_skipped(
+                                &primary.file,
+                                &primary.selector,
+                                err,
+                                json,
+                                Some(&format),
+                            )?;
+                            no_op = true;
+                        }
+                    }
+                    return exit_code(no_op, args.no_op_exit_nonzero);
+                }
+            };
+            let mut doc = load_document(&file, args.input)?;
+            if let Some(paths) = wildcard_paths(&doc, &selector, &target) {
+                if has_format {
+                    anyhow::bail!(
+                        "--format applies to a single version, not every field {} matches",
+                        selector
+                    );
+                }
+                let bumped = doc.bump_paths(&paths, &level, &bump)?;
+                print_path_bumps(&file, &bumped, json)?;
+                no_op = bumped.iter().all(|(_, result)| result.is_err());
+                return exit_code(no_op, args.no_op_exit_nonzero);
+            }
+            match doc.apply_bump(&selector, &level, &target, &bump) {
+                Ok(change) => print_change(&file, &selector, &change, &tag, json, &format)?,
+                Err(err) => {
+                    print_skipped(&file, &selector, err, json, Some(&format))?;
+                    no_op = true;
+                }
+            }
+        }
+        Command::Write {
+            level,
+            selector,
+            file,
+            extra,
+            level_file,
+            interactive,
+            target,
+            bump,
+            lock,
+            backup,
+            tag,
+            git,
+            hooks,
+            update_lockfile,
+            propagate,
+            workspace_deps,
+            changelog,
+            out,
+            dry_run,
+            json,
+        } => {
+            if dry_run && json {
+                anyhow::bail!("--dry-run prints a diff and can't be used with --output json");
+            }
+            let (level, mut location) = match interactive {
+                true => {
+                    if !extra.is_empty() {
+                        anyhow::bail!("--interactive only applies to a single file");
+                    }
+                    let Some((level, location)) = choose_interactively(
+                        level,
+                        selector,
+                        file,
+                        &bump,
+                        args.input,
+                        args.pattern.as_deref(),
+                        args.config.as_deref(),
+                    )?
+                    else {
+                        if !verbosity::is_quiet() {
+                            eprintln!("Nothing written");
+                        }
+                        return exit_code(true, args.no_op_exit_nonzero);
+                    };
+                    (level, Some(location))
+                }
+                false => resolve_positionals(level, selector, file, level_file)?,
+            };
+            let extra = extra.locations(&mut location)?;
+            if update_lockfile && (location.is_none() || !extra.is_empty()) {
+                anyhow::bail!("--update-lockfile only applies to a single package.json");
+            }
+            if propagate && (location.is_none() || !extra.is_empty()) {
+                anyhow::bail!("--propagate only applies to a single pom.xml");
+            }
+            if workspace_deps && (location.is_none() || !extra.is_empty()) {
+                anyhow::bail!("--workspace-deps only applies to a single Cargo.toml");
+            }
+            if out.is_some() && (location.is_none() || !extra.is_empty()) {
+                anyhow::bail!("--out only applies to a single file");
+            }
+            let changelog = load_changelog(changelog.as_deref())?;
+            let config_hooks = Config::load(args.config.as_deref())?
+                .unwrap_or_default()
+                .hooks;
+            let hooks = hooks.with_config(&config_hooks);
+            let (selector, file) = match location {
+                Some((selector, file)) if extra.is_empty() => {
+                    let pattern = args.pattern.as_deref();
+                    let config = args.config.as_deref();
+                    (infer_selector(selector, &file, pattern, config)?, file)
+                }
+                location if dry_run => {
+                    let config = multi_config(args.config.as_deref(), location, extra, &target)?;
+                    match bump_config(
+                        &config,
+                        args.input,
+                        args.on_parse_error,
+                        &level,
+                        &bump,
+                        None,
+                    ) {
+                        Ok(changes) => {
+                            let locations: Vec<&Location> =
+                                changes.iter().map(|(location, _)| location).collect();
+                            let version = &changes[0].1.new;
+                            for file in distinct_files(&locations) {
+                                let mut in_file =
+                                    locations.iter().filter(|location| location.file == file);
+                                let first = in_file.next().unwrap();
+                                let old = load_location(first, args.input)?;
+                                let mut new = load_location(first, args.input)?;
+                                for location in std::iter::once(first).chain(in_file) {
+                                    new.set_version(&location.selector, version)?;
+                                }
+                                print_diff(file, &old.render()?, &new.render()?);
+                            }
+                            if let Some((path, old)) = &changelog {
+                                print_diff(path, old, &release_changelog(old, &changes[0].1)?);
+                            }
+                        }
+                        Err(err) => {
+                            let primary = config.primary.as_ref().unwrap();
+                            print_skipped(&primary.file, &primary.selector, err, false, None)?;
+                            no_op = true;
+                        }
+                    }
+                    return exit_code(no_op, args.no_op_exit_nonzero);
+                }
+                location => {
+                    let config = multi_config(args.config.as_deref(), location, extra, &target)?;
+                    let files: Vec<&Path> = config
+                        .primary
+                        .iter()
+                        .chain(&config.targets)
+                        .map(|location| location.file.as_path())
+                        .chain(changelog.as_ref().map(|(path, _)| path.as_path()))
+                        .collect();
+                    git.preflight(&files)?;
+                    let writing = Writing {
+                        lock: &lock,
+                        backup: &backup,
+                        hooks: &hooks,
+                        tag: Some(&tag),
+                        journal: &args.journal_dir,
+                    };
+                    match bump_config(
+                        &config,
+                        args.input,
+                        args.on_parse_error,
+                        &level,
+                        &bump,
+                        Some(writing),
+                    ) {
+                        Ok(changes) => {
+                            if let Some((path, old)) = &changelog {
+                                backup.save(path)?;
+                                write_file(path, release_changelog(old, &changes[0].1)?)?;
+                            }
+                            print_config_changes(&changes, &tag, json, None)?;
+                            git.record(&files, &tag, &changes[0].1.new)?;
+                        }
+                        Err(err) => {
+                            let primary = config.primary.as_ref().unwrap();
+                            print_skipped(&primary.file, &primary.selector, err, json, None)?;
+                            no_op = true;
+                        }
+                    }
+                    return exit_code(no_op, args.no_op_exit_nonzero);
+                }
+            };
+            let path = file.as_path();
+            if is_glob(path) {
+                if update_lockfile
+                    || propagate
+                    || workspace_deps
+                    || changelog.is_some()
+                    || out.is_some()
+                {
+                    anyhow::bail!(
+                        "--update-lockfile, --propagate, --workspace-deps, --changelog and --out don't apply to a glob"
+                    );
+                }
+                let (files, _) = expand_globs(&[path.to_string_lossy().into_owned()], &[])?;
+                let _locks = match dry_run {
+                    true => Vec::new(),
+                    false => files
+                        .iter()
+                        .map(|file| lock.acquire(file))
+                        .collect::<Result<Vec<_>>>()?,
+                };
+                let bumped = bump_files(
+                    &files,
+                    &selector,
+                    &level,
+                    &target,
+                    &bump,
+                    args.input,
+                    args.on_parse_error,
+                )?;
+                let changed: Vec<_> = bumped
+                    .iter()
+                    .filter_map(|bumped| Some((bumped, bumped.result.as_ref().ok()?)))
+                    .collect();
+                no_op = changed.is_empty();
+                if dry_run {
+                    for (bumped, _) in &changed {
+                        print_diff(&bumped.path, &bumped.source, &bumped.doc.render()?);
+                    }
+                    return exit_code(no_op, args.no_op_exit_nonzero);
+                }
+                let versions: Vec<&str> = changed.iter().map(|(_, c)| c.new.as_str()).collect();
+                if (git.commit || git.tag) && versions.windows(2).any(|pair| pair[0] != pair[1]) {
+                    anyhow::bail!(
+                        "--commit and --tag need every matched file to reach the same version"
+                    );
+                }
+                let paths: Vec<&Path> = changed.iter().map(|(b, _)| b.path.as_path()).collect();
+                git.preflight(&paths)?;
+                if let (Some(path), Some(version)) = (paths.first(), versions.first()) {
+                    tag.check_unreleased(path, version)?;
+                }
+                let write = |bumped: &FileBump| -> Result<()> {
+                    backup.save(&bumped.path)?;
+                    write_file(&bumped.path, bumped.doc.render()?)
+                };
+                // Hooks run around each file's write, so files are only
+                // written in parallel without them
+                if hooks.is_empty() {
+                    changed
+                        .par_iter()
+                        .try_for_each(|(bumped, _)| write(bumped))?;
+                } else {
+                    for (bumped, change) in &changed {
+                        hooks.run_pre(change)?;
+                        write(bumped)?;
+                        hooks.run_post(change)?;
+                    }
+                }
+                journal_writes(
+                    &args.journal_dir,
+                    changed.iter().map(|(bumped, change)| {
+                        (bumped.path.as_path(), selector.as_str(), *change)
+                    }),
+                );
+                print_file_bumps(&bumped, &selector, json)?;
+                if let Some(version) = versions.first() {
+                    git.record(&paths, &tag, version)?;
+                }
+                return exit_code(no_op, args.no_op_exit_nonzero);
+            }
+            let wildcard = match selector.contains('*') && !is_stdio(path) {
+                true => wildcard_paths(&load_document(path, args.input)?, &selector, &target),
+                false => None,
+            };
+            if wildcard.is_some()
+                && (update_lockfile || propagate || workspace_deps || changelog.is_some())
+            {
+                anyhow::bail!(
+                    "--update-lockfile, --propagate, --workspace-deps and --changelog don't apply to a selector matching several versions"
+                );
+            }
+            let mut modules = match propagate {
+                true => maven::modules(path, args.input)?,
+                false => Vec::new(),
+            };
+            let workspace_root = match workspace_deps {
+                true => Some(workspace::find_root(path)?),
+                false => None,
+            };
+            if dry_run {
+                let mut doc = load_document(path, args.input)?;
+                let old = doc.render()?;
+                if let Some(paths) = &wildcard {
+                    let bumped = doc.bump_paths(paths, &level, &bump)?;
+                    print_diff(path, &old, &doc.render()?);
+                    no_op = bumped.iter().all(|(_, result)| result.is_err());
+                    return exit_code(no_op, args.no_op_exit_nonzero);
+                }
+                match doc.apply_bump(&selector, &level, &target, &bump) {
+                    Ok(change) => {
+                        let before = modules
+                            .iter()
+                            .map(|(_, doc)| doc.render())
+                            .collect::<Result<Vec<_>>>()?;
+                        if propagate {
+                            maven::propagate(path, &mut doc, &mut modules, &change)?;
+                        }
+                        let workspace_doc = match &workspace_root {
+                            Some(root) => {
+                                update_workspace_deps(&mut doc, path, root, &change, args.input)?.0
+                            }
+                            None => None,
+                        };
+                        print_diff(path, &old, &doc.render()?);
+                        for ((module, doc), old) in modules.iter().zip(&before) {
+                            print_diff(module, old, &doc.render()?);
+                        }
+                        if let (Some(root), Some(root_doc)) = (&workspace_root, &workspace_doc) {
+                            print_diff(root, &fs::read_to_string(root)?, &root_doc.render()?);
+                        }
+                        if let Some((path, old)) = &changelog {
+                            print_diff(path, old, &release_changelog(old, &change)?);
+                        }
+                    }
+                    Err(err) => {
+                        print_skipped(path, &selector, err, false, None)?;
+                        no_op = true;
+                    }
+                }
+                return exit_code(no_op, args.no_op_exit_nonzero);
+            }
+            if RemotePath::parse(path).is_some() {
+                anyhow::bail!(
+                    "Writing to a remote file is not supported: {}",
+                    path.display()
+                );
+            }
+            if is_stdio(path) && changelog.is_some() {
+                anyhow::bail!("--changelog can't be used when writing to stdout");
+            }
+            if is_stdio(path) && out.is_some() {
+                anyhow::bail!("--out can't be used when writing to stdout");
+            }
+            if is_stdio(path) {
+                return write_stdio(&selector, &level, &target, &bump, args.input, &git, json)
+                    .and_then(|no_op| exit_code(no_op, args.no_op_exit_nonzero));
+            }
+            let lockfiles = match update_lockfile {
+                true => npm::lockfiles(path)?,
+                false => Vec::new(),
+            };
+            // With --out, the source is read but only the output is locked,
+            // checked with git and written
+            let dest = out.as_deref().unwrap_or(path);
+            let module_poms: Vec<PathBuf> =
+                modules.iter().map(|(module, _)| module.clone()).collect();
+            let files: Vec<&Path> = std::iter::once(dest)
+                .chain(lockfiles.iter().map(PathBuf::as_path))
+                .chain(module_poms.iter().map(PathBuf::as_path))
+                .chain(workspace_root.as_deref())
+                .chain(changelog.as_ref().map(|(path, _)| path.as_path()))
+                .collect();
+            git.preflight(&files)?;
+            let _lock = lock.acquire(dest)?;
+            let mut doc = load_document(path, args.input)?;
+            if let Some(paths) = &wildcard {
+                let bumped = doc.bump_paths(paths, &level, &bump)?;
+                let changes: Vec<&Change> = bumped
+                    .iter()
+                    .filter_map(|(_, result)| result.as_ref().ok())
+                    .collect();
+                if let Some(first) = changes.first() {
+                    if (git.commit || git.tag) && changes.iter().any(|c| c.new != first.new) {
+                        anyhow::bail!(
+                            "--commit and --tag need every matched field to reach the same version"
+                        );
+                    }
+                    tag.check_unreleased(dest, &first.new)?;
+                    hooks.run_pre(first)?;
+                    save_and_write(&backup, dest, doc.render()?)?;
+                    journal_writes(
+                        &args.journal_dir,
+                        bumped.iter().filter_map(|(path, result)| {
+                            Some((dest, path.as_str(), result.as_ref().ok()?))
+                        }),
+                    );
+                    hooks.run_post(first)?;
+                }
+                print_path_bumps(dest, &bumped, json)?;
+                if let Some(first) = changes.first() {
+                    git.record(&files, &tag, &first.new)?;
+                }
+                return exit_code(changes.is_empty(), args.no_op_exit_nonzero);
+            }
+            if let Some(name) = &target.package {
+                eprintln!(
+                    "warning: editing {} directly; `cargo update -p {}` is the canonical way to refresh it",
+                    path.display(),
+                    name
+                );
+            }
+            match doc.apply_bump(&selector, &level, &target, &bump) {
+                Ok(change) => {
+                    let locked = lockfiles
+                        .iter()
+                        .map(|lockfile| npm::update_lockfile(lockfile, &change.new))
+                        .collect::<Result<Vec<_>>>()?;
+                    let propagated = match propagate {
+                        true => maven::propagate(dest, &mut doc, &mut modules, &change)?,
+                        false => Vec::new(),
+                    };
+                    let (workspace_doc, workspace_changes) = match &workspace_root {
+                        Some(root) => {
+                            update_workspace_deps(&mut doc, dest, root, &change, args.input)?
+                        }
+                        None => (None, Vec::new()),
+                    };
+                    let released = changelog
+                        .as_ref()
+                        .map(|(path, old)| release_changelog(old, &change).map(|new| (path, new)))
+                        .transpose()?;
+                    tag.check_unreleased(dest, &change.new)?;
+                    hooks.run_pre(&change)?;
+                    save_and_write(&backup, dest, doc.render()?)?;
+                    for (module, doc) in &modules {
+                        if propagated.iter().any(|edit| edit.pom == *module) {
+                            backup.save(module)?;
+                            write_file(module, doc.render()?)?;
+                        }
+                    }
+                    if let (Some(root), Some(root_doc)) = (&workspace_root, &workspace_doc) {
+                        if !workspace_changes.is_empty() {
+                            backup.save(root)?;
+                            write_file(root, root_doc.render()?)?;
+                        }
+                    }
+                    let also: Vec<(&Path, &str, &Change)> = propagated
+                        .iter()
+                        .map(|edit| (edit.pom.as_path(), edit.selector.as_str(), &edit.change))
+                        .chain(workspace_changes.iter().map(|(selector, change)| {
+                            let root = workspace_root.as_deref().unwrap();
+                            (root, selector.as_str(), change)
+                        }))
+                        .collect();
+                    journal_writes(
+                        &args.journal_dir,
+                        std::iter::once((dest, selector.as_str(), &change)).chain(also.clone()),
+                    );
+                    for (file, selector, change) in also {
+                        note_change(file, selector, change);
+                    }
+                    for (lockfile, doc) in lockfiles.iter().zip(&locked) {
+                        backup.save(lockfile)?;
+                        write_file(lockfile, doc.render()?)?;
+                    }
+                    if let Some((path, contents)) = released {
+                        backup.save(path)?;
+                        write_file(path, contents)?;
+                    }
+                    hooks.run_post(&change)?;
+                    if json {
+                        print_change(dest, &selector, &change, &tag, json, "{version}")?;
+                    } else {
+                        note_change(dest, &selector, &change);
+                        if tag.emit_tag_name {
+                            git::emit_tag_name(&tag.tag_name(&change.new)?)?;
+                        }
+                    }
+                    git.record(&files, &tag, &change.new)?;
+                }
+                Err(err) => {
+                    print_skipped(path, &selector, err, json, None)?;
+                    no_op = true;
+                }
+            }
+        }
+        Command::Release { .. } | Command::Auto { .. } => unreachable!("runs as write"),
+        Command::Render {
+            level,
+            selector,
+            file,
+            template,
+            out,
+            bump,
+        } => {
+            let mut doc = load_document(&file, args.input)?;
+            let version = match doc.bump_version(&selector, &level, &bump) {
+                Ok(change) => change.new,
+                Err(err) => {
+                    let skipped = err.downcast::<Skipped>()?;
+                    eprintln!("{}", skipped);
+                    no_op = true;
+                    skipped.current
+                }
+            };
+            let template = fs::read_to_string(&template)
+                .with_context(|| format!("Failed to read {}", template.display()))?;
+            write_file(&out, render_template(&template, &version)?)?;
+        }
+        Command::Check { args: values, json } => {
+            let (expected, mut locations) = check_locations(&values)?;
+            if locations.is_empty() {
+                let config = load_config(args.config.as_deref())?;
+                locations.extend(config.primary);
+                locations.extend(config.targets);
+            }
+            let mut found = Vec::new();
+            for mut location in locations {
+                location.selector = infer_selector(
+                    location.selector,
+                    &location.file,
+                    args.pattern.as_deref(),
+                    args.config.as_deref(),
+                )?;
+                let version =
+                    load_location(&location, args.input)?.read_version(&location.selector)?;
+                found.push((location, version));
+            }
+            let expected = match expected {
+                Some(version) => version.to_string(),
+                None => found[0].1.clone(),
+            };
+            let matches = found.iter().all(|(_, version)| *version == expected);
+            if json {
+                let versions: Vec<_> = found
+                    .iter()
+                    .map(|(location, version)| {
+                        serde_json::json!({
+                            "file": location.file,
+                            "selector": location.selector,
+                            "version": version,
+                            "matches": *version == expected,
+                        })
+                    })
+                    .collect();
+                let output = serde_json::json!({
+                    "expected": expected,
+                    "matches": matches,
+                    "versions": versions,
+                });
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else {
+                for (location, version) in &found {
+                    let mark = if *version == expected {
+                        "ok"
+                    } else {
+                        "mismatch"
+                    };
+                    println!(
+                        "{}: {} {} ({})",
+                        location.file.display(),
+                        location.selector,
+                        version,
+                        mark
+                    );
+                }
+            }
+            if !matches {
+                eprintln!("versions do not match {}", expected);
+                return Ok(ExitCode::from(1));
+            }
+        }
+        Command::Compare {
+            left_selector,
+            left_file,
+            right_selector,
+            right_file,
+            json,
+        } => {
+            let left = Location::new(left_file, left_selector);
+            let right = Location::new(right_file, right_selector);
+            let (left_version, right_version, ordering) =
+                compare_locations(&left, &right, args.input)?;
+            let result = ordering_name(ordering);
+            if json {
+                let side = |location: &Location, version: &str| {
+                    serde_json::json!({
+                        "file": location.file,
+                        "selector": location.selector,
+                        "version": version,
+                    })
+                };
+                let output = serde_json::json!({
+                    "left": side(&left, &left_version),
+                    "right": side(&right, &right_version),
+                    "result": result,
+                });
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else {
+                println!("{}", result);
+            }
+            if ordering.is_ne() {
+                eprintln!(
+                    "{} in {} is {} than {} in {}",
+                    left_version,
+                    left.file.display(),
+                    result,
+                    right_version,
+                    right.file.display()
+                );
+                return Ok(ExitCode::from(1));
+            }
+        }
+        Command::Next {
+            selector,
+            file,
+            bump,
+            json,
+        } => {
+            let (selector, file) = plain_file_location(selector, file);
+            let selector = infer_selector(
+                selector,
+                &file,
+                args.pattern.as_deref(),
+                args.config.as_deref(),
+            )?;
+            print!(
+                "{}",
+                next_versions(&file, &selector, args.input, &bump, json)?
+            );
+        }
+        Command::Helm {
+            level,
+            chart,
+            app_version,
+            app_version_from,
+            bump,
+            lock,
+            backup,
+            tag,
+            git,
+            json,
+        } => {
+            let level: VersionBump = level.parse()?;
+            let chart = match chart.is_dir() {
+                true => chart.join("Chart.yaml"),
+                false => chart,
+            };
+            let app_version = match (app_version, app_version_from) {
+                (Some(version), _) => Some(version),
+                (None, Some(arg)) => {
+                    let location = Location::parse_selector_at_file(&arg)?;
+                    let doc = load_location(&location, args.input)?;
+                    Some(doc.read_version(&location.selector)?)
+                }
+                (None, None) => None,
+            };
+            git.preflight(&[&chart])?;
+            let _lock = lock.acquire(&chart)?;
+            let mut doc = load_document(&chart, args.input)?;
+            let mut changes = match doc.bump_version("version", &level, &bump) {
+                Ok(change) => vec![("version", change)],
+                Err(err) => {
+                    print_skipped(&chart, "version", err, json, None)?;
+                    return exit_code(true, args.no_op_exit_nonzero);
+                }
+            };
+            // appVersion is optional, so a chart without one only has its
+            // version bumped
+            match &app_version {
+                Some(app_version) if doc.contains("appVersion")? => {
+                    changes.push(("appVersion", doc.set_version("appVersion", app_version)?));
+                }
+                Some(_) => eprintln!("skipping appVersion: not set in {}", chart.display()),
+                None => {}
+            }
+            tag.check_unreleased(&chart, &changes[0].1.new)?;
+            backup.save(&chart)?;
+            write_file(&chart, doc.render()?)?;
+            journal_writes(
+                &args.journal_dir,
+                changes
+                    .iter()
+                    .map(|(selector, change)| (chart.as_path(), *selector, change)),
+            );
+            if json {
+                let output: Vec<_> = changes
+                    .iter()
+                    .map(|(selector, change)| change_json(&chart, selector, change))
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else if verbosity::is_quiet() {
+                println!("{}", changes[0].1.new);
+            } else {
+                for (selector, change) in &changes {
+                    println!("{}: {} -> {}", selector, change.old, change.new);
+                }
+            }
+            if tag.emit_tag_name {
+                git::emit_tag_name(&tag.tag_name(&changes[0].1.new)?)?;
+            }
+            git.record(&[&chart], &tag, &changes[0].1.new)?;
+        }
+        Command::Diff {
+            selector,
+            file,
+            against,
+            expect,
+            json,
+        } => {
+            let location = Location::new(file, selector);
+            let new = load_location(&location, args.input)?.read_version(&location.selector)?;
+            let source = git::show_file(&location.file, &against)?;
+            let old = parse_document(&location.file, &source, args.input)
+                .and_then(|doc| doc.read_version(&location.selector))
+                .with_context(|| {
+                    format!("Failed to read {} at {}", location.file.display(), against)
+                })?;
+            let bump = bump_kind(&parse_loose(&old)?, &parse_loose(&new)?);
+            if json {
+                let output = serde_json::json!({
+                    "file": location.file,
+                    "selector": location.selector,
+                    "against": against,
+                    "old": old,
+                    "new": new,
+                    "bump": bump,
+                });
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else {
+                println!("{} -> {} ({})", old, new, bump);
+            }
+            if let Some(expect) = expect.filter(|expect| expect.as_str() != bump) {
+                eprintln!(
+                    "expected a {} bump since {}, got {}",
+                    expect.as_str(),
+                    against,
+                    bump
+                );
+                return Ok(ExitCode::from(1));
+            }
+        }
+        Command::Sync {
+            from,
+            to,
+            lock,
+            json,
+        } => {
+            let source = Location::new(from[1].clone().into(), from[0].clone());
+            let targets: Vec<_> = to
+                .chunks(2)
+                .map(|pair| Location::new(pair[1].clone().into(), pair[0].clone()))
+                .collect();
+            let changes = sync_versions(&source, &targets, args.input, &lock, &args.journal_dir)?;
+            if json {
+                let output: Vec<_> = changes
+                    .iter()
+                    .map(|(location, change)| {
+                        let mut output = change_json(&location.file, &location.selector, change);
+                        output["changed"] = (change.old != change.new).into();
+                        output
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else if verbosity::is_quiet() {
+                print_new_versions(changes.iter().map(|(_, change)| Ok(change)));
+            } else {
+                for (location, change) in &changes {
+                    if change.old == change.new {
+                        println!("{}: {} unchanged", location.file.display(), change.new);
+                    } else {
+                        println!(
+                            "{}: {} -> {}",
+                            location.file.display(),
+                            change.old,
+                            change.new
+                        );
+                    }
+                }
+            }
+        }
+        Command::Workspace {
+            command:
+                WorkspaceCommand::Write {
+                    level,
+                    manifest,
+                    path_deps,
+                    bump,
+                    json,
+                },
+        } => match workspace::bump(&manifest, &level, &bump, path_deps, true).inspect(|edits| {
+            journal_writes(
+                &args.journal_dir,
+                edits.iter().map(|edit| {
+                    (
+                        edit.manifest.as_path(),
+                        edit.selector.as_str(),
+                        &edit.change,
+                    )
+                }),
+            )
+        }) {
+            Ok(edits) if json => {
+                let output: Vec<_> = edits
+                    .iter()
+                    .map(|edit| change_json(&edit.manifest, &edit.selector, &edit.change))
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            }
+            Ok(edits) if verbosity::is_quiet() => {
+                print_new_versions(edits.iter().map(|edit| Ok(&edit.change)));
+            }
+            Ok(edits) => {
+                for edit in &edits {
+                    println!(
+                        "{}: {} {} -> {}",
+                        edit.manifest.display(),
+                        edit.selector,
+                        edit.change.old,
+                        edit.change.new
+                    );
+                }
+            }
+            Err(err) => {
+                print_skipped(&manifest, workspace::VERSION_SELECTOR, err, json, None)?;
+                no_op = true;
+            }
+        },
+        Command::Undo { force, json } => {
+            let Some(entry) = journal::undo(&args.journal_dir, args.input, force)? else {
+                anyhow::bail!(
+                    "Nothing to undo, {} has no writes",
+                    args.journal_dir.join(journal::JOURNAL_DIR).display()
+                );
+            };
+            if json {
+                println!("{}", serde_json::to_string_pretty(&entry)?);
+            } else {
+                for change in &entry.changes {
+                    println!(
+                        "{}: {} -> {}",
+                        change.file.display(),
+                        change.new,
+                        change.old
+                    );
+                }
+            }
+        }
+        Command::Completions { shell } => {
+            clap_complete::generate(shell, &mut Args::command(), "svbump", &mut io::stdout());
+        }
+        Command::Init { force } => {
+            let path = args
+                .config
+                .unwrap_or_else(|| PathBuf::from(config::CONFIG_FILE_NAMES[0]));
+            if path.exists() && !force {
+                anyhow::bail!(
+                    "{} already exists (pass --force to overwrite it)",
+                    path.display()
+                );
+            }
+            let found = scaffold_locations(Path::new("."))?;
+            let mut locations = found.iter().map(|(location, _)| location.clone());
+            let config = Config {
+                primary: locations.next(),
+                targets: locations.collect(),
+                ..Config::default()
+            };
+            if config.primary.is_none() {
+                anyhow::bail!("No known manifests with a version found in the current directory");
+            }
+            write_file(&path, format_config(&config, ConfigFormat::Toml)?)?;
+            for (location, version) in &found {
+                eprintln!(
+                    "{}: {} {}",
+                    location.file.display(),
+                    location.selector,
+                    version
+                );
+            }
+            eprintln!("wrote {}", path.display());
+        }
+        Command::Foreach {
+            action,
+            level,
+            only,
+            bump,
+            lock,
+            backup,
+            hooks,
+            json,
+        } => {
+            let level: VersionBump = level.parse()?;
+            let config = Config::load(args.config.as_deref())?
+                .with_context(|| format!("No {} found", config::CONFIG_FILE_NAMES.join(" or ")))?;
+            let packages = select_packages(&config, &only)?;
+            let hooks = hooks.with_config(&config.hooks);
+            // Every package is bumped in memory first, so nothing is written
+            // if any of them fails to parse or bump. Packages are bumped in
+            // turn, each from the documents left by those before it, so
+            // packages sharing a file keep each other's edits. Each package
+            // is then written on its own, so a failure while writing one,
+            // such as a hook, leaves the packages before it written.
+            let configs: Vec<Config> = packages
+                .iter()
+                .map(|(_, package)| package.config())
+                .collect();
+            let _locks = match action {
+                ForeachAction::Write => {
+                    let locations: Vec<&Location> = configs
+                        .iter()
+                        .flat_map(|config| config.primary.iter().chain(&config.targets))
+                        .collect();
+                    lock_locations(&locations, &lock)?
+                }
+                ForeachAction::Preview => Vec::new(),
+            };
+            let mut bumped = Vec::new();
+            let mut planned = Vec::new();
+            let mut edited: Vec<(&Path, String)> = Vec::new();
+            for ((name, package), config) in packages.into_iter().zip(&configs) {
+                match plan_config(
+                    config,
+                    args.input,
+                    args.on_parse_error,
+                    &level,
+                    &bump,
+                    &edited,
+                ) {
+                    Ok((docs, changes)) => {
+                        for (file, doc) in &docs {
+                            edited.retain(|(edited, _)| edited != file);
+                            edited.push((file, doc.render()?));
+                        }
+                        bumped.push((name, package, Ok(changes)));
+                        planned.push(docs);
+                    }
+                    Err(err) => {
+                        let skipped = err
+                            .downcast::<Skipped>()
+                            .with_context(|| format!("Failed to bump package {}", name))?;
+                        bumped.push((name, package, Err(skipped)));
+                        planned.push(Vec::new());
+                    }
+                }
+            }
+            if action == ForeachAction::Write {
+                let writing = Writing {
+                    lock: &lock,
+                    backup: &backup,
+                    hooks: &hooks,
+                    tag: None,
+                    journal: &args.journal_dir,
+                };
+                let mut written = Vec::new();
+                for ((name, _, result), docs) in bumped.iter().zip(&planned) {
+                    let Ok(changes) = result else { continue };
+                    write_config(docs, changes, &writing).with_context(|| {
+                        match written.is_empty() {
+                            true => format!("Failed to write package {}", name),
+                            false => format!(
+                                "Failed to write package {} after writing {}",
+                                name,
+                                written.join(", ")
+                            ),
+                        }
+                    })?;
+                    written.push(*name);
+                }
+            }
+            no_op = bumped.iter().all(|(_, _, result)| result.is_err());
+            print_package_bumps(&bumped, json)?;
+        }
+        Command::List {
+            patterns,
+            selector,
+            exclude,
+            json,
+        } => {
+            let (files, excluded) = expand_globs(&patterns, &exclude)?;
+            let Some(selector) = selector else {
+                let mut output = Vec::new();
+                for path in &files {
+                    let doc = load_document(path, args.input)
+                        .with_context(|| format!("Failed to parse {}", path.display()))?;
+                    for (selector, version) in doc.find_versions()? {
+                        if json {
+                            output.push(serde_json::json!({
+                                "file": path,
+                                "selector": selector,
+                                "version": version,
+                            }));
+                        } else {
+                            println!("{}: {} {}", path.display(), selector, version);
+                        }
+                    }
+                }
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&output)?);
+                }
+                return exit_code(no_op, args.no_op_exit_nonzero);
+            };
+            let mut report = list_versions(&files, args.input, &selector, args.on_parse_error)?;
+            report.excluded = excluded;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report.to_json())?);
+            } else {
+                for (path, version) in &report.found {
+                    println!("{}: {}", path.display(), version);
+                }
+            }
+            if !report.missing.is_empty() {
+                eprintln!("selector {} not found in:", selector);
+                for path in &report.missing {
+                    eprintln!("  {}", path.display());
+                }
+            }
+            if !report.unparsed.is_empty() {
+                eprintln!("skipped files that failed to parse:");
+                for (path, error) in &report.unparsed {
+                    eprintln!("  {}: {}", path.display(), error);
+                }
+            }
+            if report.excluded > 0 {
+                eprintln!("excluded {} files matching --exclude", report.excluded);
+            }
+        }
+    }
+
+    exit_code(no_op, args.no_op_exit_nonzero)
+}
+
+/// The largest bump called for by commits since the last tag in the
+/// repository holding `file`, printing the reasoning to stderr if `explain`
+fn auto_level(file: &Path, explain: bool) -> Result<Option<BumpLevel>> {
+    let (tag, commits) = git::history_since_last_tag(&git::repo_dir(file))?;
+    let mut level = None;
+    if explain {
+        match &tag {
+            Some(tag) => eprintln!("commits since {}:", tag),
+            None => eprintln!("no tags found, commits since the start of history:"),
+        }
+    }
+    for commit in &commits {
+        let commit_level = conventional::classify(&commit.message);
+        if explain {
+            let header = commit.message.lines().next().unwrap_or_default();
+            let label = commit_level.map_or("-", |level| level.as_str());
+            eprintln!("  {:<5}  {} {}", label, commit.hash, header);
+        }
+        level = level.max(commit_level);
+    }
+    if explain {
+        eprintln!("bump: {}", level.map_or("none", |level| level.as_str()));
+    }
+    Ok(level)
+}
+
+/// Print the version after the latest version tag, returning whether the bump
+/// was skipped
+fn preview_from_git(
+    level: &VersionBump,
+    bump: &BumpOptions,
+    tag: &TagOptions,
+    json: bool,
+    format: &str,
+) -> Result<bool> {
+    let (tag_name, current) = git::latest_version_tag(Path::new("."), &tag.tag_prefix)?
+        .with_context(|| format!("No {}X.Y.Z tag is reachable from HEAD", tag.tag_prefix))?;
+    let current = current.to_string();
+    let (new, skipped) = match svbump::bump_semver(&current, level, bump) {
+        Ok(new) => (new, None),
+        Err(err) => (current.clone(), Some(err.downcast::<Skipped>()?)),
+    };
+    if json {
+        let mut output = serde_json::json!({
+            "source": tag_name,
+            "old": current,
+            "new": new,
+        });
+        if let Some(skipped) = &skipped {
+            output["skipped_reason"] = skipped.reason.clone().into();
+        }
+        if tag.emit_tag_name {
+            output["tag"] = tag.tag_name(&new)?.into();
+        }
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        if let Some(skipped) = &skipped {
+            eprintln!("{}", skipped);
+        }
+        println!("{}", render_template(format, &new)?);
+        if tag.emit_tag_name {
+            git::emit_tag_name(&tag.tag_name(&new)?)?;
+        }
+    }
+    Ok(skipped.is_some())
+}
+
+/// The size of the change from `old` to `new`: the most significant release
+/// segment bumped, `prerelease` when only the pre-release differs, or `none`
+/// or `downgrade`
+fn bump_kind(old: &Version, new: &Version) -> &'static str {
+    match old.cmp_precedence(new) {
+        std::cmp::Ordering::Greater => "downgrade",
+        std::cmp::Ordering::Equal => "none",
+        std::cmp::Ordering::Less => {
+            BumpLevel::between(old, new).map_or("prerelease", |level| level.as_str())
+        }
+    }
+}
+
+fn exit_code(no_op: bool, no_op_exit_nonzero: bool) -> Result<ExitCode> {
+    if no_op && no_op_exit_nonzero {
+        Ok(ExitCode::from(NO_OP_EXIT_CODE))
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn load_config(path: Option<&Path>) -> Result<Config> {
+    let config = Config::load(path)?.with_context(|| {
+        format!(
+            "No selector and file given, and no {} found",
+            config::CONFIG_FILE_NAMES.join(" or ")
+        )
+    })?;
+    if config.primary.is_none() {
+        anyhow::bail!("The config file lists no version locations");
+    }
+    Ok(config)
+}
+
+/// The config file merged with command-line overrides: an explicit selector
+/// and file replace the config's locations, and `--type` fills in locations
+/// without a type of their own.
+fn effective_config(args: &Args) -> Result<Config> {
+    let config = Config::load(args.config.as_deref())?.unwrap_or_default();
+    let (location, extra) = match &args.command {
+        Some(command) => {
+            let mut location = command.location();
+            let extra = command.extra_locations(&mut location)?;
+            (location, extra)
+        }
+        None => (None, Vec::new()),
+    };
+    let mut config = merge_locations(config, location, extra);
+    for location in config.primary.iter_mut().chain(config.targets.iter_mut()) {
+        location.file_type = location.file_type.or(args.input.file_type);
+    }
+    Ok(config)
+}
+
+/// The well-known manifests in `dir` that hold a version, with the version
+fn scaffold_locations(dir: &Path) -> Result<Vec<(Location, String)>> {
+    let mut found = Vec::new();
+    for (file, selector) in defaults::SELECTORS {
+        let path = dir.join(file);
+        if !path.is_file() {
+            continue;
+        }
+        // e.g. a virtual workspace's Cargo.toml has no package.version
+        if let Ok(version) = read_version_file(&path, InputOptions::default(), selector) {
+            found.push((Location::new(file.into(), selector.to_string()), version));
+        }
+    }
+    Ok(found)
+}
+
+/// Replace the config's locations with an explicit selector and file, and
+/// add any extra targets
+fn merge_locations(
+    mut config: Config,
+    location: Option<(String, PathBuf)>,
+    extra: Vec<Location>,
+) -> Config {
+    if let Some((selector, file)) = location {
+        config.primary = Some(Location::new(file, selector));
+        config.targets.clear();
+    }
+    config.targets.extend(extra);
+    config
+}
+
+/// The locations for a `write` or `preview` of more than one file: the
+/// selector and file plus extra targets, or the config file's locations
+fn multi_config(
+    path: Option<&Path>,
+    location: Option<(String, PathBuf)>,
+    extra: Vec<Location>,
+    target: &TargetOptions,
+) -> Result<Config> {
+    if target.bump_key || target.package.is_some() || target.build_number.is_some() {
+        anyhow::bail!("--bump-key, --package and --build-number only apply to a single file");
+    }
+    let config = match location {
+        Some(_) => Config::default(),
+        None => load_config(path)?,
+    };
+    Ok(merge_locations(config, location, extra))
+}
+
+fn format_config(config: &Config, format: ConfigFormat) -> Result<String> {
+    Ok(match format {
+        ConfigFormat::Toml => toml_edit::ser::to_string_pretty(config)?,
+        ConfigFormat::Json => serde_json::to_string_pretty(config)?,
+    })
+}
+
+/// What happens around writing files
+struct Writing<'a> {
+    lock: &'a LockOptions,
+    backup: &'a BackupOptions,
+    hooks: &'a Hooks,
+    tag: Option<&'a TagOptions>,
+    /// Directory of the journal the writes are recorded in for `undo`
+    journal: &'a Path,
+}
+
+/// Bump the config's primary location and set every target to the new
+/// version, writing the files if `write` is given. Nothing is written unless
+/// every file parses and has its selector, though targets in files that
+/// don't parse are left out under `ParseErrorPolicy::Skip`.
+fn bump_config(
+    config: &Config,
+    input: InputOptions,
+    on_parse_error: ParseErrorPolicy,
+    level: &VersionBump,
+    bump: &BumpOptions,
+    write: Option<Writing>,
+) -> Result<Vec<(Location, Change)>> {
+    let primary = config.primary.as_ref().context("No [primary] location")?;
+    let locations: Vec<&Location> = std::iter::once(primary).chain(&config.targets).collect();
+    let _locks = match &write {
+        Some(write) => lock_locations(&locations, write.lock)?,
+        None => Vec::new(),
+    };
+    let (docs, changes) = plan_config(config, input, on_parse_error, level, bump, &[])?;
+    if let Some(write) = write {
+        write_config(&docs, &changes, &write)?;
+    }
+    Ok(changes)
+}
+
+/// Refuse to write any of `locations` that is stdout or remote, then lock
+/// each distinct file of them
+fn lock_locations(locations: &[&Location], lock: &LockOptions) -> Result<Vec<Option<FileLock>>> {
+    for location in locations {
+        if is_stdio(&location.file) {
+            anyhow::bail!("Writing to stdout is only supported for a single file");
+        }
+        if RemotePath::parse(&location.file).is_some() {
+            anyhow::bail!(
+                "Writing to a remote file is not supported: {}",
+                location.file.display()
+            );
+        }
+    }
+    distinct_files(locations)
+        .into_iter()
+        .map(|file| lock.acquire(file))
+        .collect()
+}
+
+/// A config's documents, bumped but not yet written, and the change made at
+/// each of its locations
+type ConfigPlan<'a> = (Vec<(&'a Path, Document)>, Vec<(Location, Change)>);
+
+/// The config's documents with its primary bumped and its targets set, and
+/// the change made at each location, without writing anything. Files in
+/// `edited` are parsed from the content given there instead of being read.
+fn plan_config<'a>(
+    config: &'a Config,
+    input: InputOptions,
+    on_parse_error: ParseErrorPolicy,
+    level: &VersionBump,
+    bump: &BumpOptions,
+    edited: &[(&Path, String)],
+) -> Result<ConfigPlan<'a>> {
+    let primary = config.primary.as_ref().context("No [primary] location")?;
+    let locations: Vec<&Location> = std::iter::once(primary).chain(&config.targets).collect();
+
+    // Locations in the same file share one parsed document, so each file is
+    // read and written once with all of its selectors updated
+    let mut docs = load_locations(&locations, input, on_parse_error, edited)?;
+    let locations: Vec<&Location> = locations
+        .into_iter()
+        .filter(|location| docs.iter().any(|(file, _)| *file == location.file))
+        .collect();
+    if config.lockstep {
+        check_lockstep(&locations, &docs)?;
+    }
+    let mut changes: Vec<(Location, Change)> = Vec::new();
+    for location in locations {
+        let doc = docs
+            .iter_mut()
+            .find_map(|(file, doc)| (*file == location.file).then_some(doc))
+            .unwrap();
+        let change = match changes.first() {
+            None => doc.bump_version(&location.selector, level, bump)?,
+            Some((_, primary)) => doc
+                .set_version(&location.selector, &primary.new)
+                .with_context(|| format!("Failed to update {}", location.file.display()))?,
+        };
+        changes.push((location.clone(), change));
+    }
+    Ok((docs, changes))
+}
+
+/// Write the documents planned for a config, whose files are already locked,
+/// running the hooks around the write and recording it in the journal
+fn write_config(
+    docs: &[(&Path, Document)],
+    changes: &[(Location, Change)],
+    write: &Writing,
+) -> Result<()> {
+    let (primary, change) = &changes[0];
+    if let Some(tag) = write.tag {
+        tag.check_unreleased(&primary.file, &change.new)?;
+    }
+    write.hooks.run_pre(change)?;
+    docs.par_iter().try_for_each(|(file, doc)| {
+        write.backup.save(file)?;
+        write_file(file, doc.render()?)
+    })?;
+    journal_writes(
+        write.journal,
+        changes.iter().map(|(location, change)| {
+            (location.file.as_path(), location.selector.as_str(), change)
+        }),
+    );
+    write.hooks.run_post(change)
+}
+
+/// Fail unless every target holds the version of the primary, the first of
+/// `locations`, reporting each one that has drifted
+fn check_lockstep(locations: &[&Location], docs: &[(&Path, Document)]) -> Result<()> {
+    let read = |location: &Location| {
+        let (_, doc) = docs
+            .iter()
+            .find(|(file, _)| *file == location.file)
+            .unwrap();
+        doc.read_version(&location.selector)
+            .with_context(|| format!("Failed to read {}", location.file.display()))
+    };
+    let (primary, targets) = locations.split_first().context("No [primary] location")?;
+    let canonical = read(primary)?;
+    let mut drifted = Vec::new();
+    for location in targets {
+        let version = read(location)?;
+        if version != canonical {
+            drifted.push(format!(
+                "  {}: {} {}",
+                location.file.display(),
+                location.selector,
+                version
+            ));
+        }
+    }
+    if !drifted.is_empty() {
+        anyhow::bail!(
+            "{} of {} lockstep targets drifted from the canonical {} at {} in {}:\n{}\nSet them back (e.g. with `svbump sync`) before bumping",
+            drifted.len(),
+            targets.len(),
+            canonical,
+            primary.selector,
+            primary.file.display(),
+            drifted.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// The document of each distinct file of `locations`, in order, loaded in
+/// parallel from the file or, for files in `edited`, from the content given
+/// there; fails listing every file that couldn't be loaded. Under
+/// `ParseErrorPolicy::Skip`, files after the first that don't parse are
+/// logged and left out.
+fn load_locations<'a>(
+    locations: &[&'a Location],
+    input: InputOptions,
+    on_parse_error: ParseErrorPolicy,
+    edited: &[(&Path, String)],
+) -> Result<Vec<(&'a Path, Document)>> {
+    let firsts: Vec<&Location> = distinct_files(locations)
+        .into_iter()
+        .map(|file| *locations.iter().find(|l| l.file == file).unwrap())
+        .collect();
+    let results: Vec<Result<Result<Document>>> = firsts
+        .par_iter()
+        .map(|location| {
+            let content = match edited.iter().find(|(file, _)| *file == location.file) {
+                Some((_, content)) => content.clone(),
+                None => read_source(&location.file)
+                    .with_context(|| format!("Failed to load {}", location.file.display()))?,
+            };
+            Ok(parse_location(location, &content, input))
+        })
+        .collect();
+    let mut docs = Vec::new();
+    let mut failed = Vec::new();
+    for (i, (location, result)) in firsts.iter().zip(results).enumerate() {
+        match result {
+            Ok(Ok(doc)) => docs.push((location.file.as_path(), doc)),
+            Ok(Err(err)) if i > 0 && on_parse_error == ParseErrorPolicy::Skip => {
+                eprintln!("skipping {}: {:#}", location.file.display(), err);
+            }
+            Ok(Err(err)) | Err(err) => failed.push(err),
+        }
+    }
+    if failed.len() > 1 {
+        let failed: Vec<String> = failed.iter().map(|err| format!("  {:#}", err)).collect();
+        anyhow::bail!(
+            "Could not load {} files:\n{}",
+            failed.len(),
+            failed.join("\n")
+        );
+    }
+    match failed.pop() {
+        Some(err) => Err(err),
+        None => Ok(docs),
+    }
+}
+
+/// The files of `locations`, each once, in order
+fn distinct_files<'a>(locations: &[&'a Location]) -> Vec<&'a Path> {
+    let mut files: Vec<&Path> = Vec::new();
+    for location in locations {
+        if !files.contains(&location.file.as_path()) {
+            files.push(&location.file);
+        }
+    }
+    files
+}
+
+/// Load the file of a location, parsed as the location's own type if it has one
+fn load_location(location: &Location, input: InputOptions) -> Result<Document> {
+    let content = read_source(&location.file)
+        .with_context(|| format!("Failed to load {}", location.file.display()))?;
+    parse_location(location, &content, input)
+}
+
+/// Parse the content of a location's file as the location's own type if it
+/// has one
+fn parse_location(location: &Location, content: &str, input: InputOptions) -> Result<Document> {
+    let input = InputOptions {
+        file_type: location.file_type.or(input.file_type),
+        ..input
+    };
+    parse_document(&location.file, content, input)
+        .with_context(|| format!("Failed to load {}", location.file.display()))
+}
+
+/// Set every target to the version read from `source`. Nothing is written
+/// unless every target can be updated, and targets already at the version are
+/// left untouched.
+fn sync_versions(
+    source: &Location,
+    targets: &[Location],
+    input: InputOptions,
+    lock: &LockOptions,
+    journal: &Path,
+) -> Result<Vec<(Location, Change)>> {
+    let version = load_location(source, input)?.read_version(&source.selector)?;
+    let mut locks = Vec::new();
+    let mut updated = Vec::new();
+    for target in targets {
+        if RemotePath::parse(&target.file).is_some() {
+            anyhow::bail!(
+                "Writing to a remote file is not supported: {}",
+                target.file.display()
+            );
+        }
+        locks.push(lock.acquire(&target.file)?);
+        let mut doc = load_location(target, input)?;
+        let change = doc
+            .set_version(&target.selector, &version)
+            .with_context(|| format!("Failed to update {}", target.file.display()))?;
+        updated.push((target.clone(), doc, change));
+    }
+    for (target, doc, change) in &updated {
+        if change.old != change.new {
+            write_file(&target.file, doc.render()?)?;
+        }
+    }
+    journal_writes(
+        journal,
+        updated
+            .iter()
+            .map(|(target, _, change)| (target.file.as_path(), target.selector.as_str(), change)),
+    );
+    Ok(updated
+        .into_iter()
+        .map(|(target, _, change)| (target, change))
+        .collect())
+}
+
+/// Split `check` arguments into the expected version, if the first argument
+/// is one, and the locations: `SELECTOR FILE` pairs, `FILE:SELECTOR`
+/// arguments, and lone plain version files
+fn check_locations(args: &[String]) -> Result<(Option<Version>, Vec<Location>)> {
+    let (expected, mut rest) = match args.split_first() {
+        Some((first, rest)) => match Version::parse(first) {
+            Ok(version) => (Some(version), rest),
+            Err(_) => (None, args),
+        },
+        None => (None, args),
+    };
+    let mut locations = Vec::new();
+    while let Some((arg, tail)) = rest.split_first() {
+        rest = tail;
+        if arg.contains(':') {
+            locations.push(Location::parse_file_selector(arg)?);
+        } else if let Some((file, tail)) = rest.split_first().filter(|(f, _)| !f.contains(':')) {
+            rest = tail;
+            locations.push(Location::new(file.into(), arg.clone()));
+        } else {
+            locations.push(Location::new(arg.into(), ".".to_string()));
+        }
+    }
+    Ok((expected, locations))
+}
+
+/// Print the changes of a multi-file bump; previews print just the new
+/// version, rendered with their `--format` template
+fn print_config_changes(
+    changes: &[(Location, Change)],
+    tag: &TagOptions,
+    json: bool,
+    preview: Option<&str>,
+) -> Result<()> {
+    let new_version = &changes[0].1.new;
+    if json {
+        let output: Vec<_> = changes
+            .iter()
+            .map(|(location, change)| change_json(&location.file, &location.selector, change))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+    for (location, change) in changes {
+        note_change(&location.file, &location.selector, change);
+    }
+    if let Some(format) = preview {
+        println!("{}", render_template(format, new_version)?);
+    } else if verbosity::is_quiet() {
+        println!("{}", new_version);
+    } else {
+        for (location, change) in changes {
+            println!(
+                "{}: {} -> {}",
+                location.file.display(),
+                change.old,
+                change.new
+            );
+        }
+    }
+    if tag.emit_tag_name {
+        git::emit_tag_name(&tag.tag_name(new_version)?)?;
+    }
+    Ok(())
+}
+
+/// Log a change with `--verbose`
+fn note_change(file: &Path, selector: &str, change: &Change) {
+    verbosity::note(format_args!(
+        "{} {}: {} -> {}",
+        file.display(),
+        selector,
+        change.old,
+        change.new
+    ));
+}
+
+fn change_json(file: &Path, selector: &str, change: &Change) -> JsonValue {
+    serde_json::json!({
+        "file": file,
+        "selector": selector,
+        "old": change.old,
+        "new": change.new,
+    })
+}
+
+/// Print the new version of a change, rendered with a `--format` template
+fn print_change(
+    file: &Path,
+    selector: &str,
+    change: &Change,
+    tag: &TagOptions,
+    json: bool,
+    format: &str,
+) -> Result<()> {
+    note_change(file, selector, change);
+    let tag_name = match tag.emit_tag_name {
+        true => Some(tag.tag_name(&change.new)?),
+        false => None,
+    };
+    let output = change_output(file, selector, change, tag_name.as_deref(), json, format)?;
+    println!("{}", output);
+    if let (Some(tag_name), false) = (&tag_name, json) {
+        git::emit_tag_name(tag_name)?;
+    }
+    Ok(())
+}
+
+/// What `print_change` prints for a change: its JSON, with the tag name if
+/// one is given, or its new version rendered with `format`
+fn change_output(
+    file: &Path,
+    selector: &str,
+    change: &Change,
+    tag_name: Option<&str>,
+    json: bool,
+    format: &str,
+) -> Result<String> {
+    if !json {
+        return render_template(format, &change.new);
+    }
+    let mut output = change_json(file, selector, change);
+    if let Some(tag_name) = tag_name {
+        output["tag"] = tag_name.into();
+    }
+    Ok(serde_json::to_string_pretty(&output)?)
+}
+
+fn skipped_json(file: &Path, selector: &str, skipped: &Skipped) -> JsonValue {
+    let unchanged = Change {
+        old: skipped.current.clone(),
+        new: skipped.current.clone(),
+    };
+    let mut output = change_json(file, selector, &unchanged);
+    output["skipped_reason"] = skipped.reason.clone().into();
+    output
+}
+
+/// Report a skipped bump, passing any other error through. Previews still
+/// print the unchanged version, rendered with their `--format` template, so
+/// command substitution keeps working.
+fn print_skipped(
+    file: &Path,
+    selector: &str,
+    err: anyhow::Error,
+    json: bool,
+    preview: Option<&str>,
+) -> Result<()> {
+    let skipped = err.downcast::<Skipped>()?;
+    if json {
+        let output = skipped_json(file, selector, &skipped);
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        if !verbosity::is_quiet() {
+            eprintln!("{}", skipped);
+        }
+        if let Some(format) = preview {
+            println!("{}", render_template(format, &skipped.current)?);
+        }
+    }
+    Ok(())
+}
+
+/// The workspace root to write, if it isn't the manifest itself, and the
+/// `[workspace.dependencies]` entries moved in it
+type WorkspaceDeps = (Option<Document>, Vec<(String, Change)>);
+
+/// With --workspace-deps, move the `[workspace.dependencies]` entries for the
+/// crate in `doc` to its new version. They're edited in `doc` itself when it
+/// is the workspace root, otherwise in the root, which is returned for writing.
+fn update_workspace_deps(
+    doc: &mut Document,
+    manifest: &Path,
+    root: &Path,
+    change: &Change,
+    input: InputOptions,
+) -> Result<WorkspaceDeps> {
+    let name = doc.read_version("package.name")?;
+    let (root_doc, changes) = if std::path::absolute(manifest)? == std::path::absolute(root)? {
+        (None, workspace::update_dependency(doc, &name, &change.new))
+    } else {
+        let mut root_doc = load_document(root, input)?;
+        let changes = workspace::update_dependency(&mut root_doc, &name, &change.new);
+        (Some(root_doc), changes)
+    };
+    if changes.is_empty() && !verbosity::is_quiet() {
+        eprintln!(
+            "warning: no [workspace.dependencies] entry for {} in {}",
+            name,
+            root.display()
+        );
+    }
+    Ok((root_doc, changes))
+}
+
+/// Record written changes in the journal for `undo`. The files are already
+/// written by then, so a failure is only a warning.
+fn journal_writes<'a>(
+    dir: &Path,
+    changes: impl IntoIterator<Item = (&'a Path, &'a str, &'a Change)>,
+) {
+    let changes = changes
+        .into_iter()
+        // A key created by --create has no old version to put back
+        .filter(|(_, _, change)| change.old != change.new && !change.old.is_empty())
+        .map(|(file, selector, change)| Replaced {
+            file: file.to_path_buf(),
+            selector: selector.to_string(),
+            old: change.old.clone(),
+            new: change.new.clone(),
+        })
+        .collect();
+    if let Err(err) = journal::record(dir, changes) {
+        eprintln!("warning: failed to record the write for undo: {:#}", err);
+    }
+}
+
+/// Back up `path` if it exists and backups were asked for, then write it;
+/// an `--out` file may not exist yet
+fn save_and_write(backup: &BackupOptions, path: &Path, contents: String) -> Result<()> {
+    if path.exists() {
+        backup.save(path)?;
+    }
+    write_file(path, contents)
+}
+
+/// Print a unified diff between two renderings of the file at `path`
+fn print_diff(path: &Path, old: &str, new: &str) {
+    print!("{}", unified_diff(path, old, new));
+}
+
+fn unified_diff(path: &Path, old: &str, new: &str) -> String {
+    similar::TextDiff::from_lines(old, new)
+        .unified_diff()
+        .header(
+            &format!("a/{}", path.display()),
+            &format!("b/{}", path.display()),
+        )
+        .to_string()
+}
+
+/// Read the changelog given with `--changelog`, which must have an unreleased
+/// section to move under the new version
+fn load_changelog(path: Option<&Path>) -> Result<Option<(PathBuf, String)>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if !svbump::changelog::has_unreleased(&contents) {
+        anyhow::bail!("No `## [Unreleased]` section found in {}", path.display());
+    }
+    Ok(Some((path.to_path_buf(), contents)))
+}
+
+/// The changelog with its unreleased entries under a heading for `change.new`,
+/// dated today
+fn release_changelog(contents: &str, change: &Change) -> Result<String> {
+    svbump::changelog::release(contents, &change.new, Local::now().date_naive())
+}
+
+/// Bump a document read from stdin and write the result to stdout. A skipped
+/// bump passes the input through unchanged so pipelines keep their content.
+fn write_stdio(
+    selector: &str,
+    level: &VersionBump,
+    target: &TargetOptions,
+    bump: &BumpOptions,
+    input: InputOptions,
+    git: &GitOptions,
+    json: bool,
+) -> Result<bool> {
+    if json {
+        anyhow::bail!("--json can't be used when writing to stdout");
+    }
+    if git.commit || git.tag {
+        anyhow::bail!("--commit and --tag need a file, not stdin");
+    }
+    let path = Path::new(STDIO_PATH);
+    let mut doc = load_document(path, input)?;
+    let no_op = match doc.apply_bump(selector, level, target, bump) {
+        Ok(_) => false,
+        Err(err) => {
+            eprintln!("{}", err.downcast::<Skipped>()?);
+            true
+        }
+    };
+    print!("{}", doc.render()?);
+    Ok(no_op)
+}
+
+/// Expand glob patterns into a sorted list of files, dropping any that match
+/// an `exclude` pattern. Returns the files and how many were excluded.
+fn expand_globs(patterns: &[String], exclude: &[String]) -> Result<(Vec<PathBuf>, usize)> {
+    let exclude = exclude
+        .iter()
+        .map(|pattern| {
+            glob::Pattern::new(pattern).with_context(|| format!("Invalid glob: {}", pattern))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    let is_excluded = |path: &Path| {
+        let path = path.strip_prefix(".").unwrap_or(path);
+        exclude
+            .iter()
+            .any(|pattern| pattern.matches_path_with(path, options))
+    };
+
+    let mut files = Vec::new();
+    for pattern in patterns {
+        let before = files.len();
+        for entry in glob::glob(pattern).with_context(|| format!("Invalid glob: {}", pattern))? {
+            let path = entry?;
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+        if files.len() == before {
+            anyhow::bail!("No files matched {}", pattern);
+        }
+    }
+    files.sort();
+    files.dedup();
+    let matched = files.len();
+    files.retain(|path| !is_excluded(path));
+    let excluded = matched - files.len();
+    Ok((files, excluded))
+}
+
+/// Whether a file argument is a glob pattern rather than an existing path
+fn is_glob(path: &Path) -> bool {
+    !path.exists()
+        && path
+            .to_str()
+            .is_some_and(|path| path.contains(['*', '?', '[']))
+}
+
+/// A file bumped from its own version, not yet written
+struct FileBump {
+    path: PathBuf,
+    /// The file's content before the bump
+    source: String,
+    doc: Document,
+    result: std::result::Result<Change, Skipped>,
+}
+
+/// Bump `selector` in each file from its own version, reading and parsing
+/// the files in parallel. Fails without writing anything if a file lacks the
+/// selector or doesn't parse, listing every such file, though files that
+/// don't parse are logged and left out under `ParseErrorPolicy::Skip`.
+fn bump_files(
+    files: &[PathBuf],
+    selector: &str,
+    level: &VersionBump,
+    target: &TargetOptions,
+    bump: &BumpOptions,
+    input: InputOptions,
+    on_parse_error: ParseErrorPolicy,
+) -> Result<Vec<FileBump>> {
+    let results: Vec<Result<Result<FileBump>>> = files
+        .par_iter()
+        .map(|path| {
+            let source = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let mut doc = match parse_document(path, &source, input) {
+                Ok(doc) => doc,
+                Err(err) if on_parse_error == ParseErrorPolicy::Skip => return Ok(Err(err)),
+                Err(err) => return Err(err),
+            };
+            let result = match doc.apply_bump(selector, level, target, bump) {
+                Ok(change) => Ok(change),
+                Err(err) => Err(err.downcast::<Skipped>()?),
+            };
+            Ok(Ok(FileBump {
+                path: path.clone(),
+                source,
+                doc,
+                result,
+            }))
+        })
+        .collect();
+    let mut bumped = Vec::new();
+    let mut failed = Vec::new();
+    for (path, result) in files.iter().zip(results) {
+        match result {
+            Ok(Ok(file)) => bumped.push(file),
+            Ok(Err(err)) => eprintln!("skipping {}: {:#}", path.display(), err),
+            Err(err) => failed.push(format!("  {}: {:#}", path.display(), err)),
+        }
+    }
+    if !failed.is_empty() {
+        anyhow::bail!("Could not bump {} in:\n{}", selector, failed.join("\n"));
+    }
+    Ok(bumped)
+}
+
+/// The packages of `config` named in `only`, or all of them
+fn select_packages<'a>(config: &'a Config, only: &[String]) -> Result<Vec<(&'a str, &'a Package)>> {
+    if config.packages.is_empty() {
+        anyhow::bail!("The config file lists no [packages]");
+    }
+    if let Some(unknown) = only
+        .iter()
+        .find(|name| !config.packages.contains_key(*name))
+    {
+        let known: Vec<&str> = config.packages.keys().map(String::as_str).collect();
+        anyhow::bail!("Unknown package {} (known: {})", unknown, known.join(", "));
+    }
+    Ok(config
+        .packages
+        .iter()
+        .filter(|(name, _)| only.is_empty() || only.contains(name))
+        .map(|(name, package)| (name.as_str(), package))
+        .collect())
+}
+
+/// A package bumped by `foreach`, with its changes or why it was skipped
+type PackageBump<'a> = (
+    &'a str,
+    &'a Package,
+    Result<Vec<(Location, Change)>, Skipped>,
+);
+
+/// The versions at `left` and `right`, and how the first compares to the
+/// second by semver precedence
+fn compare_locations(
+    left: &Location,
+    right: &Location,
+    input: InputOptions,
+) -> Result<(String, String, std::cmp::Ordering)> {
+    let read =
+        |location: &Location| load_location(location, input)?.read_version(&location.selector);
+    let (left_version, right_version) = (read(left)?, read(right)?);
+    let ordering = parse_loose(&left_version)?.cmp_precedence(&parse_loose(&right_version)?);
+    Ok((left_version, right_version, ordering))
+}
+
+/// How `compare` prints an ordering
+fn ordering_name(ordering: std::cmp::Ordering) -> &'static str {
+    match ordering {
+        std::cmp::Ordering::Equal => "equal",
+        std::cmp::Ordering::Greater => "greater",
+        std::cmp::Ordering::Less => "less",
+    }
+}
+
+/// The version each level would bump `selector` in `file` to, as a table or
+/// JSON, for `next`
+fn next_versions(
+    file: &Path,
+    selector: &str,
+    input: InputOptions,
+    bump: &BumpOptions,
+    json: bool,
+) -> Result<String> {
+    let current = read_version_file(file, input, selector)?;
+    let candidates = prompt::candidates(&current, bump);
+    if candidates.is_empty() {
+        anyhow::bail!("No level applies to version {}", current);
+    }
+    if json {
+        let candidates: Vec<_> = candidates
+            .iter()
+            .map(|(level, version)| serde_json::json!({ "level": level, "version": version }))
+            .collect();
+        let output = serde_json::json!({
+            "file": file,
+            "selector": selector,
+            "current": current,
+            "candidates": candidates,
+        });
+        return Ok(format!("{}\n", serde_json::to_string_pretty(&output)?));
+    }
+    Ok(candidates
+        .iter()
+        .map(|(level, version)| format!("{:<8} {}\n", level, version))
+        .collect())
+}
+
+/// Print a table of packages with their old and new versions
+fn print_package_bumps(bumped: &[PackageBump], json: bool) -> Result<()> {
+    if json {
+        let output: Vec<_> = bumped
+            .iter()
+            .map(|(name, package, result)| {
+                let primary = package.primary.as_ref().unwrap();
+                let mut output = match result {
+                    Ok(changes) => change_json(&primary.file, &primary.selector, &changes[0].1),
+                    Err(skipped) => skipped_json(&primary.file, &primary.selector, skipped),
+                };
+                output["package"] = (*name).into();
+                output
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+    if verbosity::is_quiet() {
+        let results = bumped
+            .iter()
+            .map(|(_, _, result)| result.as_ref().map(|changes| &changes[0].1));
+        print_new_versions(results);
+        return Ok(());
+    }
+    let width = bumped
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, _, result) in bumped {
+        match result {
+            Ok(changes) => println!(
+                "{:width$}  {} -> {}",
+                name, changes[0].1.old, changes[0].1.new
+            ),
+            Err(skipped) => println!("{:width$}  {} ({})", name, skipped.current, skipped),
+        }
+    }
+    Ok(())
+}
+
+/// The paths a selector with `*` segments matches when there are several,
+/// each then bumped from its own version; a single match is bumped as usual
+fn wildcard_paths(doc: &Document, selector: &str, target: &TargetOptions) -> Option<Vec<String>> {
+    if !selector.contains('*') || matches!(doc, Document::Text(_)) {
+        return None;
+    }
+    if target.bump_key || target.package.is_some() || target.build_number.is_some() {
+        return None;
+    }
+    let paths = doc.expand_selector(selector).ok()?;
+    (paths.len() > 1).then_some(paths)
+}
+
+/// Print a table of the paths in `file` bumped from their own versions
+fn print_path_bumps(
+    file: &Path,
+    bumped: &[(String, std::result::Result<Change, Skipped>)],
+    json: bool,
+) -> Result<()> {
+    if json {
+        let output: Vec<_> = bumped
+            .iter()
+            .map(|(path, result)| match result {
+                Ok(change) => change_json(file, path, change),
+                Err(skipped) => skipped_json(file, path, skipped),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+    for (path, result) in bumped {
+        if let Ok(change) = result {
+            note_change(file, path, change);
+        }
+    }
+    if verbosity::is_quiet() {
+        print_new_versions(bumped.iter().map(|(_, result)| result.as_ref()));
+        return Ok(());
+    }
+    let width = bumped.iter().map(|(path, _)| path.len()).max().unwrap_or(0);
+    for (path, result) in bumped {
+        match result {
+            Ok(change) => println!("{:width$}  {} -> {}", path, change.old, change.new),
+            Err(skipped) => println!("{:width$}  {} ({})", path, skipped.current, skipped),
+        }
+    }
+    Ok(())
+}
+
+/// Print each distinct new version once, for `--quiet`
+fn print_new_versions<'a>(
+    results: impl Iterator<Item = std::result::Result<&'a Change, &'a Skipped>>,
+) {
+    let mut printed: Vec<&str> = Vec::new();
+    for change in results.flatten() {
+        if !printed.contains(&change.new.as_str()) {
+            println!("{}", change.new);
+            printed.push(&change.new);
+        }
+    }
+}
+
+/// Print a table of the files bumped from their own versions
+fn print_file_bumps(bumped: &[FileBump], selector: &str, json: bool) -> Result<()> {
+    if json {
+        let output: Vec<_> = bumped
+            .iter()
+            .map(|bumped| match &bumped.result {
+                Ok(change) => change_json(&bumped.path, selector, change),
+                Err(skipped) => skipped_json(&bumped.path, selector, skipped),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+    for bumped in bumped {
+        if let Ok(change) = &bumped.result {
+            note_change(&bumped.path, selector, change);
+        }
+    }
+    if verbosity::is_quiet() {
+        print_new_versions(bumped.iter().map(|bumped| bumped.result.as_ref()));
+        return Ok(());
+    }
+    let width = bumped
+        .iter()
+        .map(|bumped| bumped.path.display().to_string().len())
+        .max()
+        .unwrap_or(0);
+    for bumped in bumped {
+        let path = bumped.path.display().to_string();
+        match &bumped.result {
+            Ok(change) => println!("{:width$}  {} -> {}", path, change.old, change.new),
+            Err(skipped) => println!("{:width$}  {} ({})", path, skipped.current, skipped),
+        }
+    }
+    Ok(())
+}
+
+/// Versions read from a set of files, split by whether the selector was present
+#[derive(Debug, Default)]
+struct ListReport {
+    found: Vec<(PathBuf, String)>,
+    missing: Vec<PathBuf>,
+    /// Files skipped under `--on-parse-error skip`, with the parse error
+    unparsed: Vec<(PathBuf, String)>,
+    /// Number of matched files dropped by `--exclude`
+    excluded: usize,
+}
+
+impl ListReport {
+    fn to_json(&self) -> JsonValue {
+        serde_json::json!({
+            "versions": self
+                .found
+                .iter()
+                .map(|(path, version)| serde_json::json!({ "file": path, "version": version }))
+                .collect::<Vec<_>>(),
+            "missing": self.missing,
+            "unparsed": self
+                .unparsed
+                .iter()
+                .map(|(path, error)| serde_json::json!({ "file": path, "error": error }))
+                .collect::<Vec<_>>(),
+            "excluded": self.excluded,
+        })
+    }
+}
+
+/// Read the version at `selector` from each of `files`. Files that are gone
+/// or don't have the selector are reported as missing; any other failure to
+/// read a version is an error.
+fn list_versions(
+    files: &[PathBuf],
+    input: InputOptions,
+    selector: &str,
+    on_parse_error: ParseErrorPolicy,
+) -> Result<ListReport> {
+    let mut report = ListReport::default();
+    for path in files {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                report.missing.push(path.clone());
+                continue;
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()))
+            }
+        };
+        let doc = match parse_document(path, &content, input) {
+            Ok(doc) => doc,
+            Err(err) if on_parse_error == ParseErrorPolicy::Skip => {
+                eprintln!("skipping {}: {:#}", path.display(), err);
+                report.unparsed.push((path.clone(), format!("{:#}", err)));
+                continue;
+            }
+            Err(err) => return Err(err.context(format!("Failed to parse {}", path.display()))),
+        };
+        match doc.read_version(selector) {
+            Ok(version) => report.found.push((path.clone(), version)),
+            Err(_) if !doc.contains(selector)? => report.missing.push(path.clone()),
+            Err(err) => return Err(err.context(format!("Failed to read {}", path.display()))),
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use semver::VersionReq;
+    use std::fs;
+    use svbump::{bump_semver, get_file_type};
+    use tempfile::NamedTempFile;
+
+    /// Run svbump with its journal in `dir` rather than the working directory
+    fn run_in<T>(dir: &Path, args: impl IntoIterator<Item = T>) -> Result<ExitCode>
+    where
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let mut args = Args::parse_from(args);
+        args.journal_dir = dir.to_path_buf();
+        run(args)
+    }
+
+    #[test]
+    fn test_list_versions_across_glob() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for (name, content) in [
+            ("a", "[package]\nname = \"a\"\nversion = \"1.2.3\"\n"),
+            ("b", "[package]\nname = \"b\"\nversion = \"0.4.0\"\n"),
+            ("c", "[workspace]\nmembers = []\n"),
+        ] {
+            let crate_dir = dir.path().join("crates").join(name);
+            fs::create_dir_all(&crate_dir)?;
+            fs::write(crate_dir.join("Cargo.toml"), content)?;
+        }
+
+        let pattern = format!("{}/crates/*/Cargo.toml", dir.path().display());
+        let (files, _) = expand_globs(&[pattern], &[])?;
+        assert_eq!(files.len(), 3);
+
+        let report = list_versions(
+            &files,
+            InputOptions::default(),
+            "package.version",
+            ParseErrorPolicy::Fail,
+        )?;
+        let found: Vec<_> = report
+            .found
+            .iter()
+            .map(|(path, version)| {
+                (
+                    path.parent().unwrap().file_name().unwrap(),
+                    version.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(found, [("a".as_ref(), "1.2.3"), ("b".as_ref(), "0.4.0")]);
+        assert_eq!(report.missing, [dir.path().join("crates/c/Cargo.toml")]);
+
+        let json = report.to_json();
+        assert_eq!(json["versions"][1]["version"], "0.4.0");
+        assert_eq!(json["missing"].as_array().unwrap().len(), 1);
+
+        fs::write(
+            dir.path().join("crates/c/Cargo.toml"),
+            "[package]\nversion = { workspace = true }\n",
+        )?;
+        let err = list_versions(
+            &files,
+            InputOptions::default(),
+            "package.version",
+            ParseErrorPolicy::Fail,
+        )
+        .unwrap_err();
+        let err = format!("{:#}", err);
+        assert!(err.contains("crates/c/Cargo.toml"));
+        assert!(err.contains("No string value found at package.version"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_versions_on_parse_error() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("a.json"), r#"{"version": "1.0.0"}"#)?;
+        fs::write(dir.path().join("b.json"), r#"{"version": "#)?;
+        fs::write(dir.path().join("c.json"), r#"{"version": "3.0.0"}"#)?;
+        let (files, _) = expand_globs(&[format!("{}/*.json", dir.path().display())], &[])?;
+
+        let err = list_versions(
+            &files,
+            InputOptions::default(),
+            "version",
+            ParseErrorPolicy::Fail,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("b.json"));
+
+        let report = list_versions(
+            &files,
+            InputOptions::default(),
+            "version",
+            ParseErrorPolicy::Skip,
+        )?;
+        let versions: Vec<_> = report.found.iter().map(|(_, v)| v.as_str()).collect();
+        assert_eq!(versions, ["1.0.0", "3.0.0"]);
+        assert_eq!(report.unparsed.len(), 1);
+        assert_eq!(report.unparsed[0].0, dir.path().join("b.json"));
+        assert_eq!(
+            report.to_json()["unparsed"][0]["file"],
+            files[1].to_str().unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_pre_release_case() -> Result<()> {
+        let target: VersionBump = "2.0.0-RC.1".parse()?;
+        let preserved = bump_semver("1.2.3", &target, &BumpOptions::default())?;
+        assert_eq!(preserved, "2.0.0-RC.1");
+
+        let opts = BumpOptions {
+            normalize_pre: true,
+            ..Default::default()
+        };
+        assert_eq!(bump_semver("1.2.3", &target, &opts)?, "2.0.0-rc.1");
+
+        let conflicting = Args::try_parse_from([
+            "svbump",
+            "write",
+            "--normalize-pre",
+            "--preserve-pre-case",
+            "2.0.0-RC.1",
+            "version",
+            "package.json",
+        ]);
+        assert!(conflicting.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_type_fallback_sniffs_content() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("version.txt");
+        fs::write(&path, "[package]\nname = \"a\"\nversion = \"1.2.3\"\n")?;
+
+        let strict = Args::parse_from(["svbump", "--type", "json", "read", "version", "x"]);
+        assert!(load_document(&path, strict.input).is_err());
+
+        let args = Args::parse_from([
+            "svbump",
+            "--type",
+            "json",
+            "--type-fallback",
+            "write",
+            "patch",
+            "package.version",
+            path.to_str().unwrap(),
+        ]);
+        let mut doc = load_document(&path, args.input)?;
+        assert_eq!(doc.type_name(), "toml");
+        doc.bump_version(
+            "package.version",
+            &VersionBump::Patch,
+            &BumpOptions::default(),
+        )?;
+        fs::write(&path, doc.render()?)?;
+
+        assert_eq!(
+            read_version_file(&path, args.input, "package.version")?,
+            "1.2.4"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_reasons() -> Result<()> {
+        let skip_reason = |version, level: &str, opts| -> Result<String> {
+            let err = bump_semver(version, &level.parse()?, &opts).unwrap_err();
+            Ok(err.downcast::<Skipped>()?.to_string())
+        };
+
+        let only_if = BumpOptions {
+            only_if: Some(VersionReq::parse("<1.0.0")?),
+            ..Default::default()
+        };
+        assert_eq!(
+            skip_reason("1.3.0", "patch", only_if)?,
+            "skipped: current 1.3.0 does not match --only-if <1.0.0"
+        );
+
+        let set_if_greater = BumpOptions {
+            set_if_greater: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            skip_reason("1.3.0", "1.2.9", set_if_greater.clone())?,
+            "skipped: current 1.3.0 is already >= 1.2.9"
+        );
+        assert_eq!(
+            bump_semver("1.3.0", &"1.4.0".parse()?, &set_if_greater)?,
+            "1.4.0"
+        );
+
+        let mut doc = Document::parse(r#"{"version": "1.3.0"}"#, "json")?;
+        let err = doc
+            .bump_version("version", &"1.2.9".parse()?, &set_if_greater)
+            .unwrap_err();
+        let json = skipped_json(Path::new("package.json"), "version", &err.downcast()?);
+        assert_eq!(json["old"], "1.3.0");
+        assert_eq!(json["skipped_reason"], "current 1.3.0 is already >= 1.2.9");
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_op_exit_policy() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let temp_file = NamedTempFile::new()?;
+        fs::write(&temp_file, r#"{"version": "1.2.3"}"#)?;
+        let path = temp_file.path().to_str().unwrap();
+        let write = |policy: Option<&str>| {
+            let mut argv = vec!["svbump", "-t", "json"];
+            argv.extend(policy);
+            argv.extend(["write", "1.2.3", "version", path]);
+            run_in(dir.path(), argv)
+        };
+
+        assert_eq!(write(None)?, ExitCode::SUCCESS);
+        assert_eq!(write(Some("--no-op-exit-zero"))?, ExitCode::SUCCESS);
+        assert_eq!(
+            write(Some("--no-op-exit-nonzero"))?,
+            ExitCode::from(NO_OP_EXIT_CODE)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_templates() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("Cargo.toml");
+        fs::write(&source, "[package]\nversion = \"1.2.3\"\n")?;
+
+        let header = dir.path().join("version.h.in");
+        fs::write(
+            &header,
+            "#define VERSION \"{version}\"\n#define VERSION_MAJOR {major}\n#define VERSION_MINOR {minor}\n",
+        )?;
+        let module = dir.path().join("version.ts.in");
+        fs::write(&module, "export const VERSION = \"{version}\";\n")?;
+
+        for (template, out) in [(&header, "version.h"), (&module, "version.ts")] {
+            run_in(
+                dir.path(),
+                [
+                    "svbump",
+                    "render",
+                    "minor",
+                    "package.version",
+                    source.to_str().unwrap(),
+                    "--template",
+                    template.to_str().unwrap(),
+                    "--out",
+                    dir.path().join(out).to_str().unwrap(),
+                ],
+            )?;
+        }
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("version.h"))?,
+            "#define VERSION \"1.3.0\"\n#define VERSION_MAJOR 1\n#define VERSION_MINOR 3\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("version.ts"))?,
+            "export const VERSION = \"1.3.0\";\n"
+        );
+        assert_eq!(
+            read_version_file(&source, InputOptions::default(), "package.version")?,
+            "1.2.3"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_level_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let manifest = dir.path().join("package.json");
+        let level_file = dir.path().join("LEVEL");
+        fs::write(&manifest, r#"{"version": "1.2.3"}"#)?;
+
+        for (level, expected) in [
+            ("minor\n", "1.3.0"),
+            ("  patch  \nignored\n", "1.3.1"),
+            ("major", "2.0.0"),
+            ("2.1.0\n", "2.1.0"),
+        ] {
+            fs::write(&level_file, level)?;
+            run_in(
+                dir.path(),
+                [
+                    "svbump",
+                    "write",
+                    "--level-file",
+                    level_file.to_str().unwrap(),
+                    "version",
+                    manifest.to_str().unwrap(),
+                ],
+            )?;
+            assert_eq!(
+                read_version_file(&manifest, InputOptions::default(), "version")?,
+                expected
+            );
+        }
+
+        let both = run_in(
+            dir.path(),
+            [
+                "svbump",
+                "preview",
+                "--level-file",
+                level_file.to_str().unwrap(),
+                "patch",
+                "version",
+                manifest.to_str().unwrap(),
+            ],
+        );
+        assert!(both.unwrap_err().to_string().contains("not both"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_many_files_parse_error() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let contents = [
+            ("a.json", r#"{"version": "1.2.3"}"#),
+            ("b.json", r#"{"version": "1.2.3"}"#),
+            ("c.json", r#"{"version": "#),
+            ("d.yaml", "version: 1.2.3\n"),
+            ("e.json", "{"),
+        ];
+        for (name, content) in contents {
+            fs::write(dir.path().join(name), content)?;
+        }
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        let targets: Vec<String> = ["b.json", "c.json", "d.yaml", "e.json"]
+            .iter()
+            .map(|name| format!("{}:version", path(name)))
+            .collect();
+        let primary = path("a.json");
+        let mut argv = vec!["svbump", "write", "minor", "version", primary.as_str()];
+        argv.extend(targets.iter().map(String::as_str));
+
+        // Both broken files are reported, and none of the files is written
+        let err = format!("{:#}", run_in(dir.path(), argv).unwrap_err());
+        assert!(err.contains("Could not load 2 files"));
+        assert!(err.contains("c.json") && err.contains("e.json"));
+        for (name, content) in contents {
+            assert_eq!(fs::read_to_string(dir.path().join(name))?, content);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_targets() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cargo = dir.path().join("Cargo.toml");
+        let package = dir.path().join("package.json");
+        let chart = dir.path().join("chart/Chart.yaml");
+        let config = dir.path().join("svbump.toml");
+        fs::create_dir(dir.path().join("chart"))?;
+        fs::write(&cargo, "[package]\nname = \"demo\"\nversion = \"1.2.3\"\n")?;
+        fs::write(&package, r#"{"name": "demo", "version": "1.2.3"}"#)?;
+        fs::write(&chart, "name: demo\nversion: 0.4.0\nappVersion: 1.2.3\n")?;
+        fs::write(
+            &config,
+            r#"
+[primary]
+file = "Cargo.toml"
+selector = "package.version"
+
+[[targets]]
+file = "package.json"
+selector = "version"
+
+[[targets]]
+file = "chart/Chart.yaml"
+selector = "appVersion"
+"#,
+        )?;
+        let write = |level: &str| {
+            run_in(
+                dir.path(),
+                [
+                    "svbump",
+                    "--config",
+                    config.to_str().unwrap(),
+                    "write",
+                    level,
+                ],
+            )
+        };
+
+        write("minor")?;
+        let input = InputOptions::default();
+        assert_eq!(
+            read_version_file(&cargo, input, "package.version")?,
+            "1.3.0"
+        );
+        assert_eq!(read_version_file(&package, input, "version")?, "1.3.0");
+        assert_eq!(read_version_file(&chart, input, "appVersion")?, "1.3.0");
+        assert_eq!(read_version_file(&chart, input, "version")?, "0.4.0");
+
+        // A target missing its field leaves every file untouched
+        fs::write(&chart, "name: demo\n")?;
+        assert!(write("patch").is_err());
+        assert_eq!(
+            read_version_file(&cargo, input, "package.version")?,
+            "1.3.0"
+        );
+        assert_eq!(read_version_file(&package, input, "version")?, "1.3.0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_lockstep() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cargo = dir.path().join("Cargo.toml");
+        let package = dir.path().join("package.json");
+        let config = dir.path().join("svbump.toml");
+        fs::write(&cargo, "[package]\nname = \"demo\"\nversion = \"1.2.3\"\n")?;
+        fs::write(&package, r#"{"name": "demo", "version": "1.2.2"}"#)?;
+        fs::write(
+            &config,
+            r#"
+lockstep = true
+
+[primary]
+file = "Cargo.toml"
+selector = "package.version"
+
+[[targets]]
+file = "package.json"
+selector = "version"
+"#,
+        )?;
+        let write = || {
+            let config = config.to_str().unwrap();
+            run_in(dir.path(), ["svbump", "--config", config, "write", "minor"])
+        };
+
+        let err = write().unwrap_err().to_string();
+        assert!(err.contains("1 of 1 lockstep targets drifted from the canonical 1.2.3"));
+        assert!(err.contains("package.json: version 1.2.2"));
+        let input = InputOptions::default();
+        assert_eq!(
+            read_version_file(&cargo, input, "package.version")?,
+            "1.2.3"
+        );
+
+        fs::write(&package, r#"{"name": "demo", "version": "1.2.3"}"#)?;
+        write()?;
+        assert_eq!(read_version_file(&package, input, "version")?, "1.3.0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_print_config() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = dir.path().join("svbump.toml");
+        fs::write(
+            &config,
+            r#"
+[primary]
+file = "Cargo.toml"
+selector = "package.version"
+
+[[targets]]
+file = "chart/Chart.yaml"
+selector = "appVersion"
+type = "yaml"
+"#,
+        )?;
+
+        let args = Args::parse_from([
+            "svbump",
+            "--config",
+            config.to_str().unwrap(),
+            "-t",
+            "toml",
+            "--print-config=json",
+        ]);
+        let printed: JsonValue = serde_json::from_str(&format_config(
+            &effective_config(&args)?,
+            ConfigFormat::Json,
+        )?)?;
+        assert_eq!(
+            printed["primary"]["file"],
+            dir.path().join("Cargo.toml").to_str().unwrap()
+        );
+        assert_eq!(printed["primary"]["type"], "toml");
+        assert_eq!(printed["targets"][0]["type"], "yaml");
+
+        let args = Args::parse_from([
+            "svbump",
+            "--config",
+            config.to_str().unwrap(),
+            "--print-config",
+            "write",
+            "patch",
+            "version",
+            "package.json",
+        ]);
+        let printed = format_config(&effective_config(&args)?, ConfigFormat::Toml)?;
+        assert_eq!(
+            printed,
+            "[primary]\nfile = \"package.json\"\nselector = \"version\"\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_exclude() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for path in [
+            "package.json",
+            "packages/app/package.json",
+            "node_modules/left-pad/package.json",
+            "packages/app/node_modules/react/package.json",
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, r#"{"version": "1.0.0"}"#)?;
+        }
+
+        let pattern = format!("{}/**/package.json", dir.path().display());
+        let (files, excluded) = expand_globs(&[pattern], &["**/node_modules/**".to_string()])?;
+        assert_eq!(
+            files,
+            [
+                dir.path().join("package.json"),
+                dir.path().join("packages/app/package.json")
+            ]
+        );
+        assert_eq!(excluded, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_release() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let manifest = dir.path().join("package.json");
+        let release = || {
+            run_in(
+                dir.path(),
+                ["svbump", "release", "version", manifest.to_str().unwrap()],
+            )
+        };
+
+        fs::write(&manifest, r#"{"version": "2.0.0-rc.3+build.7"}"#)?;
+        release()?;
+        assert_eq!(
+            read_version_file(&manifest, InputOptions::default(), "version")?,
+            "2.0.0"
+        );
+
+        let err = release().unwrap_err();
+        assert!(err.to_string().contains("no pre-release"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_multiple_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let package = dir.path().join("package.json");
+        let cargo = dir.path().join("Cargo.toml");
+        let app = dir.path().join("app.yaml");
+        fs::write(&package, r#"{"version": "1.2.3"}"#)?;
+        fs::write(&cargo, "[package]\nversion = \"1.2.3\"\n")?;
+        fs::write(&app, "app:\n  name: demo\n")?;
+        let write = |level: &str| {
+            run_in(
+                dir.path(),
+                [
+                    "svbump",
+                    "write",
+                    level,
+                    "version",
+                    package.to_str().unwrap(),
+                    &format!("{}:package.version", cargo.display()),
+                    "--target",
+                    &format!("app.version@{}", app.display()),
+                ],
+            )
+        };
+
+        // app.yaml has no app.version, so nothing is written
+        assert!(write("patch").is_err());
+        let input = InputOptions::default();
+        assert_eq!(read_version_file(&package, input, "version")?, "1.2.3");
+        assert_eq!(
+            read_version_file(&cargo, input, "package.version")?,
+            "1.2.3"
+        );
+
+        fs::write(&app, "app:\n  name: demo\n  version: 1.2.3\n")?;
+        write("patch")?;
+        assert_eq!(read_version_file(&package, input, "version")?, "1.2.4");
+        assert_eq!(
+            read_version_file(&cargo, input, "package.version")?,
+            "1.2.4"
+        );
+        assert_eq!(read_version_file(&app, input, "app.version")?, "1.2.4");
+        Ok(())
+    }
+
+    #[test]
+    fn test_plain_version_files() -> Result<()> {
+        assert_eq!(get_file_type(Path::new("VERSION"), None)?, "plain");
+        assert_eq!(get_file_type(Path::new("app/.version"), None)?, "plain");
+        assert_eq!(
+            plain_file_location("VERSION".into(), None),
+            (".".to_string(), PathBuf::from("VERSION"))
+        );
+        for content in ["1.2.3\n", "1.2.3"] {
+            let mut doc = Document::parse(content, "plain")?;
+            doc.bump_version(".", &VersionBump::Minor, &BumpOptions::default())?;
+            assert_eq!(doc.render()?, content.replace("1.2.3", "1.3.0"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_regex_files() -> Result<()> {
+        let pattern = r#"const VERSION: &str = "(?P<version>[^"]+)";"#;
+        let file = Path::new("version.rs");
+        assert_eq!(
+            infer_selector(".".into(), file, Some(pattern), None)?,
+            pattern
+        );
+        assert_eq!(infer_selector("x".into(), file, Some(pattern), None)?, "x");
+
+        let content = "// generated\npub const VERSION: &str = \"0.9.0\";\n";
+        let mut doc = Document::parse(content, "regex")?;
+        doc.bump_version(pattern, &VersionBump::Major, &BumpOptions::default())?;
+        assert_eq!(doc.render()?, content.replace("0.9.0", "1.0.0"));
+        // Patterns are used as is, not expanded as wildcard selectors
+        assert_eq!(doc.read_version(r"VERSION.*= .(\d.*)\x22")?, "1.0.0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_locations() -> Result<()> {
+        let args = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        let (expected, locations) = check_locations(&args(&[
+            "1.2.3",
+            "version",
+            "package.json",
+            "Cargo.toml:package.version",
+            "VERSION",
+        ]))?;
+        assert_eq!(expected, Some(Version::new(1, 2, 3)));
+        let found: Vec<_> = locations
+            .iter()
+            .map(|l| (l.file.to_str().unwrap(), l.selector.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("package.json", "version"),
+                ("Cargo.toml", "package.version"),
+                ("VERSION", "."),
+            ]
+        );
+
+        let (expected, locations) = check_locations(&args(&["version", "package.json"]))?;
+        assert_eq!(expected, None);
+        assert_eq!(locations.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_versions() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("Cargo.toml");
+        fs::write(&source, "[package]\nversion = \"2.0.0\"\n")?;
+        let package = dir.path().join("package.json");
+        fs::write(&package, "{\n  \"version\": \"1.0.0\"\n}\n")?;
+        let chart = dir.path().join("Chart.yaml");
+        fs::write(&chart, "appVersion: 2.0.0 # synced\n")?;
+
+        let changes = sync_versions(
+            &Location::new(source, "package.version".into()),
+            &[
+                Location::new(package.clone(), "version".into()),
+                Location::new(chart.clone(), "appVersion".into()),
+            ],
+            InputOptions::default(),
+            &LockOptions::default(),
+            dir.path(),
+        )?;
+        let changed: Vec<_> = changes.iter().map(|(_, c)| c.old != c.new).collect();
+        assert_eq!(changed, [true, false]);
+        let entries = journal::entries(dir.path())?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].changes.len(), 1);
+        assert_eq!(
+            fs::read_to_string(&package)?,
+            "{\n  \"version\": \"2.0.0\"\n}\n"
+        );
+        assert_eq!(fs::read_to_string(&chart)?, "appVersion: 2.0.0 # synced\n");
+
+        // There is one canonical version, so a second --from is refused
+        let argv = ["svbump", "sync", "--from", "version", "a.json"];
+        let twice = argv
+            .iter()
+            .chain(&["--from", "version", "b.json", "--to", "v", "c.json"]);
+        assert!(Args::try_parse_from(twice).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "name: demo\nversion: 1.2.3\n";
+        let diff = unified_diff(Path::new("app.yaml"), old, &old.replace("1.2.3", "1.3.0"));
+        assert_eq!(
+            diff,
+            "--- a/app.yaml\n+++ b/app.yaml\n@@ -1,2 +1,2 @@\n name: demo\n-version: 1.2.3\n+version: 1.3.0\n"
+        );
+    }
+
+    #[test]
+    fn test_output_json() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("package.json");
+        fs::write(&file, r#"{"version": "1.2.3"}"#)?;
+        let path = file.to_str().unwrap();
+
+        run_in(
+            dir.path(),
+            [
+                "svbump", "--output", "json", "write", "minor", "version", path,
+            ],
+        )?;
+        let input = InputOptions::default();
+        assert_eq!(read_version_file(&file, input, "version")?, "1.3.0");
+
+        let change = Change {
+            old: "1.2.3".into(),
+            new: "1.3.0".into(),
+        };
+        let output = change_output(&file, "version", &change, None, true, "{version}")?;
+        assert_eq!(
+            serde_json::from_str::<JsonValue>(&output)?,
+            serde_json::json!({"file": path, "selector": "version", "old": "1.2.3", "new": "1.3.0"})
+        );
+        let output = change_output(&file, "version", &change, Some("v1.3.0"), true, "")?;
+        assert_eq!(serde_json::from_str::<JsonValue>(&output)?["tag"], "v1.3.0");
+        assert_eq!(
+            change_output(&file, "version", &change, None, false, "v{version}")?,
+            "v1.3.0"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_verbosity_flags() -> Result<()> {
+        let args = Args::try_parse_from(["svbump", "preview", "minor", "package.json", "-q"])?;
+        assert!(args.quiet && !args.verbose);
+        let args = Args::try_parse_from(["svbump", "--verbose", "read", "package.json"])?;
+        assert!(args.verbose);
+        assert!(Args::try_parse_from(["svbump", "-q", "-v", "read", "package.json"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_part() -> Result<()> {
+        let args = Args::try_parse_from(["svbump", "read", "package.json", "--part", "minor"])?;
+        let Some(Command::Read { part, .. }) = args.command else {
+            panic!("expected read");
+        };
+        assert_eq!(part.map(|part| part.placeholder()), Some("{minor}"));
+        assert!(Args::try_parse_from([
+            "svbump",
+            "read",
+            "package.json",
+            "--part",
+            "major",
+            "--format",
+            "{major}"
+        ])
+        .is_err());
+        assert!(
+            Args::try_parse_from(["svbump", "read", "package.json", "--part", "epoch"]).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let package = dir.path().join("package.json");
+        fs::write(&package, r#"{"version": "1.2.3"}"#)?;
+        let cargo = dir.path().join("Cargo.toml");
+        let compare = |version: &str| -> Result<(&str, ExitCode)> {
+            fs::write(&cargo, format!("[package]\nversion = \"{}\"\n", version))?;
+            let left = Location::new(package.clone(), "version".into());
+            let right = Location::new(cargo.clone(), "package.version".into());
+            let (_, _, ordering) = compare_locations(&left, &right, InputOptions::default())?;
+            let code = run_in(
+                dir.path(),
+                [
+                    "svbump",
+                    "compare",
+                    "version",
+                    package.to_str().unwrap(),
+                    "package.version",
+                    cargo.to_str().unwrap(),
+                ],
+            )?;
+            Ok((ordering_name(ordering), code))
+        };
+        assert_eq!(compare("1.2.3")?, ("equal", ExitCode::SUCCESS));
+        assert_eq!(compare("1.2.3+build.5")?, ("equal", ExitCode::SUCCESS));
+        assert_eq!(compare("1.2.3-rc.1")?, ("greater", ExitCode::from(1)));
+        assert_eq!(compare("1.10.0")?, ("less", ExitCode::from(1)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_next() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("package.json");
+        fs::write(&path, r#"{"version": "1.2.3", "name": "app"}"#)?;
+        let path = path.to_str().unwrap();
+        let next = |selector: &str| run_in(dir.path(), ["svbump", "next", selector, path]);
+        assert!(next("version").is_ok());
+        assert!(run_in(dir.path(), ["svbump", "next", "version", path, "--json"]).is_ok());
+        assert!(next("name").is_err());
+
+        let bump = BumpOptions::default();
+        let table = next_versions(
+            Path::new(path),
+            "version",
+            InputOptions::default(),
+            &bump,
+            false,
+        )?;
+        let lines: Vec<&str> = table.lines().take(3).collect();
+        assert_eq!(
+            lines,
+            ["major    2.0.0", "minor    1.3.0", "patch    1.2.4"]
+        );
+        let json = next_versions(
+            Path::new(path),
+            "version",
+            InputOptions::default(),
+            &bump,
+            true,
+        )?;
+        let json: serde_json::Value = serde_json::from_str(&json)?;
+        assert_eq!(json["current"], "1.2.3");
+        assert_eq!(json["candidates"][1]["level"], "minor");
+        assert_eq!(json["candidates"][1]["version"], "1.3.0");
+        assert_eq!(
+            fs::read_to_string(path)?,
+            r#"{"version": "1.2.3", "name": "app"}"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_validate() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("package.json");
+        fs::write(
+            &path,
+            r#"{"version": "1.2", "python": "1.2.3rc1", "date": "2024.5.0"}"#,
+        )?;
+        let path = path.to_str().unwrap();
+        let read = |selector: &str, validate: &[&str]| {
+            let argv = ["svbump", "-q", "read", selector, path];
+            run(Args::try_parse_from(argv.iter().chain(validate))?)
+        };
+        assert!(read("version", &[]).is_ok());
+        assert!(read("version", &["--validate"]).is_err());
+        assert!(read("python", &["--validate=pep440"]).is_ok());
+        assert!(read("python", &["--validate=semver"]).is_err());
+        assert!(read("date", &["--validate=calver"]).is_ok());
+        assert!(read(
+            "date",
+            &["--validate=calver", "--calver-format", "YY.0M.MICRO"]
+        )
+        .is_err());
+        assert!(read("date", &["--calver-format", "YYYY.MM.MICRO"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_completions() {
+        let mut script = Vec::new();
+        clap_complete::generate(Shell::Fish, &mut Args::command(), "svbump", &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("-a \"completions\""));
+
+        let candidates: Vec<_> = selector_candidates()
+            .iter()
+            .map(|candidate| candidate.get_value().to_string_lossy().into_owned())
+            .collect();
+        assert!(candidates.contains(&"package.version".to_string()));
+    }
+
+    #[test]
+    fn test_infer_selector() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = dir.path().join("svbump.toml");
+        fs::write(
+            &config,
+            "[selectors]\n\"*.csproj\" = \"Project.PropertyGroup.Version\"\n",
+        )?;
+        let infer = |file: &str| infer_selector(".".into(), Path::new(file), None, Some(&config));
+        assert_eq!(infer("Cargo.toml")?, "package.version");
+        assert_eq!(infer("web/package.json")?, "version");
+        assert_eq!(infer("chart/Chart.yaml")?, "version");
+        assert_eq!(infer("App.csproj")?, "Project.PropertyGroup.Version");
+        assert_eq!(infer("VERSION")?, ".");
+        Ok(())
+    }
+
+    #[test]
+    fn test_selectors_in_one_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let chart = dir.path().join("Chart.yaml");
+        fs::write(&chart, "name: demo\nversion: 1.2.3\nappVersion: 1.2.0\n")?;
+        run_in(
+            dir.path(),
+            [
+                "svbump",
+                "write",
+                "minor",
+                chart.to_str().unwrap(),
+                "--selector",
+                "version",
+                "--selector",
+                "appVersion",
+                "--lock",
+            ],
+        )?;
+        assert_eq!(
+            fs::read_to_string(&chart)?,
+            "name: demo\nversion: 1.3.0\nappVersion: 1.3.0\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_scaffold_locations() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("Cargo.toml"), "[workspace]\nmembers = []\n")?;
+        fs::write(dir.path().join("package.json"), r#"{"version": "1.2.3"}"#)?;
+        fs::write(dir.path().join("VERSION"), "1.2.3\n")?;
+        let found = scaffold_locations(dir.path())?;
+        let found: Vec<_> = found
+            .iter()
+            .map(|(location, version)| (location.file.to_str().unwrap(), version.as_str()))
+            .collect();
+        assert_eq!(found, [("package.json", "1.2.3"), ("VERSION", "1.2.3")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let manifest = dir.path().join("package.json");
+        fs::write(&manifest, r#"{"version": "1.2.3"}"#)?;
+        let path = manifest.to_str().unwrap();
+        run_in(dir.path(), ["svbump", "write", "minor", "version", path])?;
+        let read = || read_version_file(&manifest, InputOptions::default(), "version");
+        assert_eq!(read()?, "1.3.0");
+
+        run_in(dir.path(), ["svbump", "undo"])?;
+        assert_eq!(read()?, "1.2.3");
+        assert!(run_in(dir.path(), ["svbump", "undo"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_glob() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for (name, version) in [("a", "1.2.3"), ("b", "0.4.0")] {
+            fs::create_dir(dir.path().join(name))?;
+            let manifest = format!(r#"{{"version": "{}"}}"#, version);
+            fs::write(dir.path().join(name).join("package.json"), manifest)?;
+        }
+        let pattern = dir.path().join("*/package.json");
+        let write = || {
+            run_in(
+                dir.path(),
+                ["svbump", "write", "patch", pattern.to_str().unwrap()],
+            )
+        };
+        write()?;
+        let input = InputOptions::default();
+        let read = |name: &str| read_version_file(&dir.path().join(name), input, "version");
+        assert_eq!(read("a/package.json")?, "1.2.4");
+        assert_eq!(read("b/package.json")?, "0.4.1");
+
+        // One file without the selector stops every write
+        fs::create_dir(dir.path().join("c"))?;
+        fs::write(dir.path().join("c/package.json"), r#"{"name": "c"}"#)?;
+        assert!(write().is_err());
+        assert_eq!(read("a/package.json")?, "1.2.4");
+
+        // Every file that fails is reported, not just the first
+        fs::create_dir(dir.path().join("d"))?;
+        fs::write(dir.path().join("d/package.json"), "{")?;
+        let err = format!("{:#}", write().unwrap_err());
+        assert!(err.contains("c/package.json") && err.contains("d/package.json"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_on_parse_error() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for (name, manifest) in [("a", r#"{"version": "1.2.3"}"#), ("b", "{")] {
+            fs::create_dir(dir.path().join(name))?;
+            fs::write(dir.path().join(name).join("package.json"), manifest)?;
+        }
+        let pattern = dir.path().join("*/package.json");
+        let write = |policy: &str| {
+            run_in(
+                dir.path(),
+                [
+                    "svbump",
+                    "write",
+                    "patch",
+                    pattern.to_str().unwrap(),
+                    "--on-parse-error",
+                    policy,
+                ],
+            )
+        };
+        let input = InputOptions::default();
+        let read = |name: &str| read_version_file(&dir.path().join(name), input, "version");
+
+        assert!(write("fail").is_err());
+        assert_eq!(read("a/package.json")?, "1.2.3");
+        write("skip")?;
+        assert_eq!(read("a/package.json")?, "1.2.4");
+        assert_eq!(fs::read_to_string(dir.path().join("b/package.json"))?, "{");
+
+        // Targets of several locations are skipped too, but not the primary
+        let a = dir.path().join("a/package.json");
+        let b = dir.path().join("b/package.json");
+        let write = |primary: &Path, target: &Path| {
+            run_in(
+                dir.path(),
+                [
+                    "svbump",
+                    "write",
+                    "minor",
+                    "version",
+                    primary.to_str().unwrap(),
+                    &format!("{}:version", target.display()),
+                    "--on-parse-error",
+                    "skip",
+                ],
+            )
+        };
+        write(&a, &b)?;
+        assert_eq!(read("a/package.json")?, "1.3.0");
+        assert!(write(&b, &a).is_err());
+        assert_eq!(read("a/package.json")?, "1.3.0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_wildcard_selector() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("versions.json");
+        let content =
+            r#"{"packages": {"a": {"version": "1.2.3"}, "b": {"version": "0.4.0"}, "c": {}}}"#;
+        fs::write(&path, content)?;
+        run_in(
+            dir.path(),
+            [
+                "svbump",
+                "write",
+                "minor",
+                "packages.*.version",
+                path.to_str().unwrap(),
+            ],
+        )?;
+        let input = InputOptions::default();
+        assert_eq!(
+            read_version_file(&path, input, "packages.a.version")?,
+            "1.3.0"
+        );
+        assert_eq!(
+            read_version_file(&path, input, "packages.b.version")?,
+            "0.5.0"
+        );
+
+        // A wildcard matching one field is bumped as before
+        run_in(
+            dir.path(),
+            [
+                "svbump",
+                "write",
+                "patch",
+                "packages.b*.version",
+                path.to_str().unwrap(),
+            ],
+        )?;
+        assert_eq!(
+            read_version_file(&path, input, "packages.a.version")?,
+            "1.3.0"
+        );
+        assert_eq!(
+            read_version_file(&path, input, "packages.b.version")?,
+            "0.5.1"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_create() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("values.yaml");
+        fs::write(&path, "name: web\n")?;
+        let write = |level: &str| {
+            run_in(
+                dir.path(),
+                [
+                    "svbump",
+                    "write",
+                    level,
+                    "image.tag",
+                    path.to_str().unwrap(),
+                    "--create",
+                ],
+            )
+        };
+        assert!(write("minor").is_err());
+        write("1.0.0")?;
+        assert_eq!(
+            read_version_file(&path, InputOptions::default(), "image.tag")?,
+            "1.0.0"
+        );
+        write("minor")?;
+        assert_eq!(
+            read_version_file(&path, InputOptions::default(), "image.tag")?,
+            "1.1.0"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_out() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("deploy.yaml");
+        let out = dir.path().join("deploy.prod.yaml");
+        fs::write(&source, "image:\n  tag: 1.2.3\n")?;
+        let write = |level: &str| {
+            run_in(
+                dir.path(),
+                [
+                    "svbump",
+                    "write",
+                    level,
+                    "image.tag",
+                    source.to_str().unwrap(),
+                    "--out",
+                    out.to_str().unwrap(),
+                ],
+            )
+        };
+        write("minor")?;
+        assert_eq!(fs::read_to_string(&source)?, "image:\n  tag: 1.2.3\n");
+        assert_eq!(fs::read_to_string(&out)?, "image:\n  tag: 1.3.0\n");
+
+        // An existing output is replaced, still from the source's version
+        write("major")?;
+        assert_eq!(fs::read_to_string(&out)?, "image:\n  tag: 2.0.0\n");
+        assert!(run_in(
+            dir.path(),
+            [
+                "svbump",
+                "write",
+                "patch",
+                "version",
+                "a.json",
+                "b.json:version",
+                "--out",
+                "c.json",
+            ]
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_max_level() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let git = |args: &[&str]| -> Result<()> {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .status()?;
+            anyhow::ensure!(status.success(), "git {} failed", args.join(" "));
+            Ok(())
+        };
+        git(&["init", "--quiet"])?;
+        git(&["config", "user.name", "svbump"])?;
+        git(&["config", "user.email", "svbump@example.com"])?;
+        git(&["config", "commit.gpgSign", "false"])?;
+        git(&["config", "tag.gpgSign", "false"])?;
+        let manifest = dir.path().join("package.json");
+        fs::write(&manifest, r#"{"version": "0.4.2"}"#)?;
+        git(&["add", "package.json"])?;
+        git(&["commit", "--quiet", "--message", "chore: init"])?;
+        git(&["tag", "v0.4.2"])?;
+        git(&[
+            "commit",
+            "--quiet",
+            "--allow-empty",
+            "--message",
+            "feat!: drop v1 api",
+        ])?;
+
+        // The breaking change is refused rather than released as a minor
+        let err = run_in(
+            dir.path(),
+            [
+                "svbump",
+                "auto",
+                "version",
+                manifest.to_str().unwrap(),
+                "--max-level",
+                "minor",
+            ],
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", err).contains("exceeds --max-level minor"));
+        assert_eq!(
+            read_version_file(&manifest, InputOptions::default(), "version")?,
+            "0.4.2"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_foreach() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for (name, version) in [("core", "1.2.3"), ("cli", "0.4.0"), ("docs", "2.0.0")] {
+            fs::create_dir(dir.path().join(name))?;
+            let manifest = format!(r#"{{"version": "{}"}}"#, version);
+            fs::write(dir.path().join(name).join("package.json"), manifest)?;
+        }
+        let config = dir.path().join("svbump.toml");
+        fs::write(
+            &config,
+            r#"
+[packages.core]
+primary = { file = "core/package.json", selector = "version" }
+
+[[packages.cli.targets]]
+file = "cli/package.json"
+selector = "version"
+
+[[packages.cli.targets]]
+file = "docs/package.json"
+selector = "version"
+"#,
+        )?;
+        let foreach = |args: &[&str]| {
+            let config = ["svbump", "--config", config.to_str().unwrap(), "foreach"];
+            run_in(dir.path(), config.iter().chain(args))
+        };
+        let input = InputOptions::default();
+        let read = |name: &str| read_version_file(&dir.path().join(name), input, "version");
+
+        foreach(&["preview", "minor"])?;
+        assert_eq!(read("core/package.json")?, "1.2.3");
+
+        foreach(&["write", "patch", "--only", "cli"])?;
+        assert_eq!(read("core/package.json")?, "1.2.3");
+        assert_eq!(read("cli/package.json")?, "0.4.1");
+        assert_eq!(read("docs/package.json")?, "0.4.1");
+
+        foreach(&["write", "minor"])?;
+        assert_eq!(read("core/package.json")?, "1.3.0");
+        assert_eq!(read("cli/package.json")?, "0.5.0");
+
+        assert!(foreach(&["write", "minor", "--only", "web"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_foreach_shared_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("versions.json");
+        fs::write(&file, r#"{"core": "1.2.3", "cli": "0.4.0"}"#)?;
+        let config = dir.path().join("svbump.toml");
+        fs::write(
+            &config,
+            r#"
+[packages.core]
+primary = { file = "versions.json", selector = "core" }
+
+[packages.cli]
+primary = { file = "versions.json", selector = "cli" }
+"#,
+        )?;
+        let config = ["--config", config.to_str().unwrap()];
+        run_in(
+            dir.path(),
+            ["svbump", config[0], config[1], "foreach", "write", "minor"],
+        )?;
+        assert_eq!(
+            fs::read_to_string(&file)?,
+            r#"{"core": "1.3.0", "cli": "0.5.0"}"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bump_kind() -> Result<()> {
+        let kind =
+            |old, new| Ok::<_, anyhow::Error>(bump_kind(&parse_loose(old)?, &parse_loose(new)?));
+        assert_eq!(kind("1.2.3", "2.0.0")?, "major");
+        assert_eq!(kind("1.2.3", "1.3.0-rc.1")?, "minor");
+        assert_eq!(kind("1.2.3", "1.2.4")?, "patch");
+        assert_eq!(kind("1.3.0-rc.1", "1.3.0")?, "prerelease");
+        assert_eq!(kind("1.2.3", "1.2.3+build")?, "none");
+        assert_eq!(kind("1.2.3", "1.2")?, "downgrade");
+        Ok(())
+    }
+
+    #[test]
+    fn test_helm() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let chart = dir.path().join("Chart.yaml");
+        fs::write(
+            &chart,
+            "apiVersion: v2\nname: web # the app\nversion: 0.3.1\nappVersion: \"1.16.0\"\n",
+        )?;
+        let package = dir.path().join("package.json");
+        fs::write(&package, r#"{"version": "1.17.2"}"#)?;
+
+        let from = format!("version@{}", package.display());
+        run_in(
+            dir.path(),
+            [
+                "svbump",
+                "helm",
+                "minor",
+                dir.path().to_str().unwrap(),
+                "--app-version-from",
+                &from,
+            ],
+        )?;
+        assert_eq!(
+            fs::read_to_string(&chart)?,
+            "apiVersion: v2\nname: web # the app\nversion: 0.4.0\nappVersion: \"1.17.2\"\n"
+        );
+
+        run_in(
+            dir.path(),
+            ["svbump", "helm", "patch", chart.to_str().unwrap()],
+        )?;
+        let input = InputOptions::default();
+        assert_eq!(read_version_file(&chart, input, "version")?, "0.4.1");
+        assert_eq!(read_version_file(&chart, input, "appVersion")?, "1.17.2");
+
+        fs::write(
+            &chart,
+            "apiVersion: v2\nname: lib\ntype: library\nversion: 0.2.0\n",
+        )?;
+        run_in(
+            dir.path(),
+            [
+                "svbump",
+                "helm",
+                "minor",
+                chart.to_str().unwrap(),
+                "--app-version",
+                "2.0.0",
+            ],
+        )?;
+        assert_eq!(
+            fs::read_to_string(&chart)?,
+            "apiVersion: v2\nname: lib\ntype: library\nversion: 0.3.0\n"
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,200 @@
+//! Propagating a Maven project's new version to its modules, as
+//! `mvn versions:set` does
+
+use crate::{load_document, Change, Document, InputOptions};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// The selector of a project's own version in pom.xml
+pub const VERSION_SELECTOR: &str = "project.version";
+
+/// Lists of dependencies whose versions follow the project's
+const DEPENDENCY_LISTS: [&str; 2] = [
+    "project.dependencies.dependency",
+    "project.dependencyManagement.dependencies.dependency",
+];
+
+/// A version changed in one of the project's poms
+#[derive(Debug)]
+pub struct Edit {
+    pub pom: PathBuf,
+    pub selector: String,
+    pub change: Change,
+}
+
+/// The groupId and artifactId of a project, the groupId inherited from the
+/// parent when it has none of its own
+fn coordinates(doc: &Document) -> Option<(String, String)> {
+    let group = doc
+        .read_version("project.groupId")
+        .or_else(|_| doc.read_version("project.parent.groupId"))
+        .ok()?;
+    let artifact = doc.read_version("project.artifactId").ok()?;
+    Some((group, artifact))
+}
+
+/// Indexed selectors of each occurrence of a repeated element such as
+/// `project.modules.module`, up to the first without the text at `probe`
+/// below it (only elements holding just text are found by themselves)
+fn each<'a>(
+    doc: &'a Document,
+    element: &'a str,
+    probe: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    (0..)
+        .map(move |index| format!("{}[{}]", element, index))
+        .take_while(move |selector| {
+            doc.contains(&format!("{}{}", selector, probe))
+                .unwrap_or(false)
+        })
+}
+
+/// The poms of the modules `doc` lists, relative to `pom`
+fn module_poms(pom: &Path, doc: &Document) -> Result<Vec<PathBuf>> {
+    let dir = pom.parent().unwrap_or(Path::new(""));
+    each(doc, "project.modules.module", "")
+        .map(|selector| {
+            let module = dir.join(doc.read_version(&selector)?);
+            Ok(match module.extension() {
+                Some(ext) if ext == "xml" => module,
+                _ => module.join("pom.xml"),
+            })
+        })
+        .collect()
+}
+
+/// Load the poms of the modules listed in `pom`, and of their modules in
+/// turn. A module is a directory holding a pom.xml or a path to the pom.
+pub fn modules(pom: &Path, input: InputOptions) -> Result<Vec<(PathBuf, Document)>> {
+    if pom.file_name() != Some("pom.xml".as_ref()) {
+        anyhow::bail!(
+            "--propagate only applies to a Maven pom.xml, not {}",
+            pom.display()
+        );
+    }
+    let mut modules: Vec<(PathBuf, Document)> = Vec::new();
+    let mut found = module_poms(pom, &load_document(pom, input)?)?;
+    let mut next = 0;
+    while let Some(module) = found.get(next).cloned() {
+        next += 1;
+        if modules.iter().any(|(known, _)| *known == module) {
+            continue;
+        }
+        let doc = load_document(&module, input)
+            .with_context(|| format!("Failed to load the module {}", module.display()))?;
+        found.extend(module_poms(&module, &doc)?);
+        modules.push((module, doc));
+    }
+    Ok(modules)
+}
+
+/// After the root project's version moved by `change`, move the parent
+/// version of its modules and the version of every dependency on the root
+/// or a module inheriting its version, where they are at the old version.
+/// Returns the versions changed in `root` and `modules`.
+pub fn propagate(
+    root_pom: &Path,
+    root: &mut Document,
+    modules: &mut [(PathBuf, Document)],
+    change: &Change,
+) -> Result<Vec<Edit>> {
+    let mut moved: Vec<(String, String)> = coordinates(root).into_iter().collect();
+    for (_, doc) in modules.iter() {
+        if !doc.contains(VERSION_SELECTOR)? {
+            moved.extend(coordinates(doc));
+        }
+    }
+
+    let mut edits = Vec::new();
+    let poms = std::iter::once((root_pom, root))
+        .chain(modules.iter_mut().map(|(pom, doc)| (pom.as_path(), doc)));
+    for (pom, doc) in poms {
+        let mut elements = vec!["project.parent".to_string()];
+        for list in DEPENDENCY_LISTS {
+            elements.extend(each(doc, list, ".artifactId"));
+        }
+        for element in elements {
+            let read = |child: &str| doc.read_version(&format!("{}.{}", element, child));
+            let (Ok(group), Ok(artifact), Ok(version)) =
+                (read("groupId"), read("artifactId"), read("version"))
+            else {
+                continue;
+            };
+            if version == change.old && moved.contains(&(group, artifact)) {
+                let selector = format!("{}.version", element);
+                edits.push(Edit {
+                    pom: pom.to_path_buf(),
+                    change: doc.set_version(&selector, &change.new)?,
+                    selector,
+                });
+            }
+        }
+    }
+    Ok(edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_propagate() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("pom.xml");
+        fs::create_dir_all(dir.path().join("core"))?;
+        fs::create_dir_all(dir.path().join("app"))?;
+        fs::write(
+            &root,
+            "<project>\n<groupId>g</groupId><artifactId>parent</artifactId><version>1.0.0</version>\n<modules><module>core</module><module>app/pom.xml</module></modules>\n</project>\n",
+        )?;
+        let parent = "<parent><groupId>g</groupId><artifactId>parent</artifactId><version>1.0.0</version></parent>";
+        fs::write(
+            dir.path().join("core/pom.xml"),
+            format!("<project>{}<artifactId>core</artifactId></project>", parent),
+        )?;
+        fs::write(
+            dir.path().join("app/pom.xml"),
+            format!(
+                "<project>{}<artifactId>app</artifactId><version>1.0.0</version><dependencies>\
+                 <dependency><groupId>g</groupId><artifactId>other</artifactId><version>1.0.0</version></dependency>\
+                 <dependency><groupId>g</groupId><artifactId>core</artifactId><version>1.0.0</version></dependency>\
+                 </dependencies></project>",
+                parent
+            ),
+        )?;
+
+        let input = InputOptions::default();
+        let mut loaded = modules(&root, input)?;
+        assert_eq!(
+            loaded
+                .iter()
+                .map(|(pom, _)| pom.clone())
+                .collect::<Vec<_>>(),
+            [
+                dir.path().join("core/pom.xml"),
+                dir.path().join("app/pom.xml")
+            ]
+        );
+        let mut doc = load_document(&root, input)?;
+        let change = doc.set_version(VERSION_SELECTOR, "1.1.0")?;
+        let edits = propagate(&root, &mut doc, &mut loaded, &change)?;
+        let selectors: Vec<_> = edits.iter().map(|edit| edit.selector.as_str()).collect();
+        assert_eq!(
+            selectors,
+            [
+                "project.parent.version",
+                "project.parent.version",
+                "project.dependencies.dependency[1].version"
+            ]
+        );
+        let (_, app) = &loaded[1];
+        assert_eq!(app.read_version(VERSION_SELECTOR)?, "1.0.0");
+        assert_eq!(
+            app.read_version("project.dependencies.dependency[0].version")?,
+            "1.0.0"
+        );
+        assert!(modules(&dir.path().join("package.json"), input).is_err());
+        Ok(())
+    }
+}
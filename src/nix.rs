@@ -0,0 +1,440 @@
+//! Locating string attributes in Nix expressions such as default.nix and
+//! flake.nix by their attribute path
+
+use crate::binding::{self, Binding};
+use anyhow::Result;
+use std::ops::Range;
+
+enum Frame {
+    /// An attribute set or the bindings of a `let`, with the path its
+    /// attributes are under and the attribute whose value is being read
+    Scope {
+        prefix: Vec<String>,
+        binding: Option<Vec<String>>,
+        is_let: bool,
+        /// `with x;` and `assert x;` in the current value, whose `;` doesn't
+        /// end the binding
+        statements: usize,
+    },
+    /// Parentheses or a list
+    Group,
+}
+
+struct Scanner<'a> {
+    content: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    frames: Vec<Frame>,
+    bindings: Vec<Binding>,
+}
+
+/// Every attribute binding in `content`, with its full path: the bindings
+/// of an attribute set are under the attribute whose value holds the set,
+/// so in `src = fetchurl { url = "..."; }` the url is `src.url`, while
+/// `let` bindings are under no path at all
+fn bindings(content: &str) -> Result<Vec<Binding>> {
+    let mut scanner = Scanner {
+        content,
+        bytes: content.as_bytes(),
+        pos: 0,
+        frames: vec![Frame::Scope {
+            prefix: Vec::new(),
+            binding: Some(Vec::new()),
+            is_let: false,
+            statements: 0,
+        }],
+        bindings: Vec::new(),
+    };
+    scanner.scan()?;
+    if scanner.frames.len() != 1 {
+        anyhow::bail!("Unbalanced brackets in Nix expression");
+    }
+    Ok(scanner.bindings)
+}
+
+impl Scanner<'_> {
+    fn scan(&mut self) -> Result<()> {
+        loop {
+            self.skip_trivia();
+            let Some(&c) = self.bytes.get(self.pos) else {
+                return Ok(());
+            };
+            match self.frames.last() {
+                Some(Frame::Scope { binding: None, .. }) => self.scan_key(c)?,
+                _ => self.scan_value(c)?,
+            }
+        }
+    }
+
+    /// Read what starts a binding: an attribute path and `=`, `inherit`, the
+    /// `in` ending a `let`, or the `}` ending a set. Anything else, such as
+    /// the arguments of a function's `{ lib, stdenv }:` pattern, is passed over.
+    fn scan_key(&mut self, c: u8) -> Result<()> {
+        if c == b'}' {
+            self.pos += 1;
+            return self.pop();
+        }
+        if !(is_ident_start(c) || c == b'"') {
+            return self.scan_value(c);
+        }
+        let path = self.attr_path()?;
+        let is_let = matches!(self.frames.last(), Some(Frame::Scope { is_let: true, .. }));
+        match path.as_slice() {
+            [keyword] if keyword == "in" && is_let => return self.pop(),
+            [keyword] if keyword == "inherit" => {
+                self.skip_past(b';')?;
+                return Ok(());
+            }
+            _ => {}
+        }
+        self.skip_trivia();
+        if self.bytes.get(self.pos) != Some(&b'=') || self.bytes.get(self.pos + 1) == Some(&b'=') {
+            return Ok(());
+        }
+        self.pos += 1;
+        self.skip_trivia();
+        let value = match self.bytes.get(self.pos) {
+            Some(b'"') => {
+                let start = self.pos;
+                self.skip_string()?;
+                let end = self.pos;
+                self.skip_trivia();
+                (self.bytes.get(self.pos) == Some(&b';')).then_some(start + 1..end - 1)
+            }
+            _ => None,
+        };
+        let Some(Frame::Scope {
+            prefix, binding, ..
+        }) = self.frames.last_mut()
+        else {
+            unreachable!("keys are only read in a scope");
+        };
+        self.bindings.push(Binding {
+            path: prefix.iter().chain(&path).cloned().collect(),
+            value,
+        });
+        *binding = Some(path);
+        Ok(())
+    }
+
+    /// Skip over one token of a value, entering and leaving nested sets,
+    /// `let`s, parentheses and lists
+    fn scan_value(&mut self, c: u8) -> Result<()> {
+        match c {
+            b'"' => self.skip_string()?,
+            b'\'' if self.bytes.get(self.pos + 1) == Some(&b'\'') => self.skip_indented_string()?,
+            b'{' => {
+                self.pos += 1;
+                let prefix = self.prefix();
+                self.push_scope(prefix, false);
+            }
+            b'(' | b'[' => {
+                self.pos += 1;
+                self.frames.push(Frame::Group);
+            }
+            b')' | b']' | b'}' => {
+                self.pos += 1;
+                self.pop()?;
+            }
+            b';' => {
+                self.pos += 1;
+                let is_root = self.frames.len() == 1;
+                if let Some(Frame::Scope {
+                    binding,
+                    statements,
+                    ..
+                }) = self.frames.last_mut()
+                {
+                    match statements {
+                        // The whole file is the root's value
+                        0 if is_root => {}
+                        0 => *binding = None,
+                        _ => *statements -= 1,
+                    }
+                }
+            }
+            c if is_ident_start(c) => match self.ident() {
+                "let" => self.push_scope(Vec::new(), true),
+                "with" | "assert" => {
+                    if let Some(Frame::Scope { statements, .. }) = self.frames.last_mut() {
+                        *statements += 1;
+                    }
+                }
+                _ => {}
+            },
+            _ => self.pos += 1,
+        }
+        Ok(())
+    }
+
+    fn push_scope(&mut self, prefix: Vec<String>, is_let: bool) {
+        self.frames.push(Frame::Scope {
+            prefix,
+            binding: None,
+            is_let,
+            statements: 0,
+        });
+    }
+
+    fn pop(&mut self) -> Result<()> {
+        if self.frames.len() == 1 {
+            anyhow::bail!("Unbalanced brackets in Nix expression");
+        }
+        self.frames.pop();
+        Ok(())
+    }
+
+    /// The path of the attribute whose value is being read, which a set
+    /// opened now is under
+    fn prefix(&self) -> Vec<String> {
+        self.frames
+            .iter()
+            .rev()
+            .find_map(|frame| match frame {
+                Frame::Scope {
+                    prefix, binding, ..
+                } => Some(
+                    prefix
+                        .iter()
+                        .chain(binding.iter().flatten())
+                        .cloned()
+                        .collect(),
+                ),
+                Frame::Group => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Read `a.b."c.d"`; an interpolated `${...}` segment is kept as written
+    fn attr_path(&mut self) -> Result<Vec<String>> {
+        let mut path = Vec::new();
+        loop {
+            let start = self.pos;
+            match self.bytes.get(self.pos) {
+                Some(b'"') => {
+                    self.skip_string()?;
+                    path.push(self.content[start + 1..self.pos - 1].replace("\\\"", "\""));
+                }
+                Some(b'$') if self.bytes.get(self.pos + 1) == Some(&b'{') => {
+                    self.skip_interpolation()?;
+                    path.push(self.content[start..self.pos].to_string());
+                }
+                Some(&c) if is_ident_start(c) => path.push(self.ident().to_string()),
+                _ => anyhow::bail!("Expected an attribute name at byte {}", self.pos),
+            }
+            self.skip_trivia();
+            if self.bytes.get(self.pos) != Some(&b'.') {
+                return Ok(path);
+            }
+            self.pos += 1;
+            self.skip_trivia();
+        }
+    }
+
+    fn ident(&mut self) -> &str {
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|&c| c.is_ascii_alphanumeric() || matches!(c, b'_' | b'\'' | b'-'))
+        {
+            self.pos += 1;
+        }
+        &self.content[start..self.pos]
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.bytes.get(self.pos) {
+                Some(c) if c.is_ascii_whitespace() => self.pos += 1,
+                Some(b'#') => {
+                    while self.bytes.get(self.pos).is_some_and(|&c| c != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(b'/') if self.bytes.get(self.pos + 1) == Some(&b'*') => {
+                    self.pos = self.content[self.pos + 2..]
+                        .find("*/")
+                        .map_or(self.bytes.len(), |end| self.pos + 2 + end + 2);
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Skip to just past the next `;`, passing over strings and comments
+    fn skip_past(&mut self, end: u8) -> Result<()> {
+        loop {
+            self.skip_trivia();
+            match self.bytes.get(self.pos) {
+                None => anyhow::bail!("Missing {:?} in Nix expression", end as char),
+                Some(b'"') => self.skip_string()?,
+                Some(&c) => {
+                    self.pos += 1;
+                    if c == end {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    fn skip_string(&mut self) -> Result<()> {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.bytes.get(self.pos) {
+                None => anyhow::bail!("Unterminated string at byte {}", start),
+                Some(b'\\') => self.pos += 2,
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(b'$') if self.bytes.get(self.pos + 1) == Some(&b'{') => {
+                    self.skip_interpolation()?
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// Skip a `''` string, where `'''`, `''$` and `''\` are escapes
+    fn skip_indented_string(&mut self) -> Result<()> {
+        let start = self.pos;
+        self.pos += 2;
+        loop {
+            let rest = &self.bytes[self.pos.min(self.bytes.len())..];
+            if rest.is_empty() {
+                anyhow::bail!("Unterminated string at byte {}", start);
+            } else if rest.starts_with(b"'''") || rest.starts_with(b"''$") {
+                self.pos += 3;
+            } else if rest.starts_with(b"''\\") {
+                self.pos += 4;
+            } else if rest.starts_with(b"''") {
+                self.pos += 2;
+                return Ok(());
+            } else if rest.starts_with(b"${") {
+                self.skip_interpolation()?;
+            } else {
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn skip_interpolation(&mut self) -> Result<()> {
+        let start = self.pos;
+        self.pos += 2;
+        let mut depth = 1;
+        loop {
+            self.skip_trivia();
+            match self.bytes.get(self.pos) {
+                None => anyhow::bail!("Unterminated interpolation at byte {}", start),
+                Some(b'"') => self.skip_string()?,
+                Some(b'\'') if self.bytes.get(self.pos + 1) == Some(&b'\'') => {
+                    self.skip_indented_string()?
+                }
+                Some(b'{') => {
+                    depth += 1;
+                    self.pos += 1;
+                }
+                Some(b'}') => {
+                    depth -= 1;
+                    self.pos += 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+}
+
+fn is_ident_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_'
+}
+
+/// Byte range of the string bound to the attribute path `path`, which must
+/// be bound exactly once, to a double-quoted string without interpolation
+pub fn locate(content: &str, path: &[String]) -> Result<Option<Range<usize>>> {
+    binding::locate(content, path, bindings, is_interpolated)
+}
+
+/// Check that replacing the string at `path` turned `old` into `new` without
+/// changing anything else about its structure, by scanning `new` again
+pub fn check_edit(old: &str, new: &str, path: &[String], version: &str) -> Result<()> {
+    binding::check_edit(old, new, path, version, bindings, is_interpolated)
+}
+
+fn is_interpolated(value: &str) -> bool {
+    value.contains("${")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() -> Result<()> {
+        let content = r#"{ lib, stdenv, fetchurl, enableFoo ? true }:
+
+# version = "0.0.1";
+stdenv.mkDerivation rec {
+  pname = "hello";
+  version = "2.12.1"; /* released { */
+  src = fetchurl {
+    url = "mirror://gnu/hello/${pname}-${version}.tar.gz";
+    hash = "sha256-jZkUKv2SV28wsM18tCqNxoCZmLxdYH2Idh9RLibH2yA=";
+  };
+  passthru.updateScript = ./update.sh;
+  passthru.tests.version = "1.0.0";
+  buildInputs = lib.optionals enableFoo [ (import ./foo.nix { version = "9"; }) ];
+  postInstall = ''
+    echo "}" ''${out}
+  '';
+  meta = with lib; {
+    description = "A program that produces a familiar, friendly greeting";
+    "dotted.name" = "3.0.0";
+    license = licenses.gpl3Plus;
+  };
+}
+"#;
+        let value = |selector: &[&str]| {
+            let path: Vec<String> = selector.iter().map(|s| s.to_string()).collect();
+            locate(content, &path).map(|range| range.map(|range| &content[range]))
+        };
+        assert_eq!(value(&["version"])?, Some("2.12.1"));
+        assert_eq!(value(&["pname"])?, Some("hello"));
+        assert_eq!(value(&["passthru", "tests", "version"])?, Some("1.0.0"));
+        assert_eq!(value(&["buildInputs", "version"])?, Some("9"));
+        assert_eq!(value(&["meta", "dotted.name"])?, Some("3.0.0"));
+        assert_eq!(value(&["missing"])?, None);
+        assert!(value(&["src", "url"]).is_err());
+        assert!(value(&["meta", "license"]).is_err());
+
+        let flake = r#"{
+  outputs = { self, nixpkgs }:
+    let
+      version = "0.3.0";
+      inner = let version = "0.0.0"; in version;
+    in { packages.default = { inherit version; }; };
+}
+"#;
+        let path = vec!["version".to_string()];
+        assert!(locate(flake, &path).is_err());
+        let flake = flake.replace("let version = \"0.0.0\"; in version", "1");
+        assert_eq!(locate(&flake, &path)?.map(|r| &flake[r]), Some("0.3.0"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_edit() -> Result<()> {
+        let old = "{ version = \"1.2.3\"; pname = \"app\"; }";
+        let path = vec!["version".to_string()];
+        let edit = |version: &str| old.replace("1.2.3", version);
+        check_edit(old, &edit("1.3.0"), &path, "1.3.0")?;
+        assert!(check_edit(old, &edit("1.3.0\"; x = \"y"), &path, "1.3.0\"; x = \"y").is_err());
+        assert!(check_edit(old, &edit("1.3\""), &path, "1.3\"").is_err());
+        Ok(())
+    }
+}
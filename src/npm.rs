@@ -0,0 +1,75 @@
+//! Keeping npm lockfiles in step with package.json
+
+use crate::{Document, InputOptions};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Lockfiles npm writes next to package.json
+pub const LOCKFILE_NAMES: [&str; 2] = ["package-lock.json", "npm-shrinkwrap.json"];
+
+/// Where a lockfile records the root package's version; the second is only
+/// present from lockfile version 2
+const LOCKFILE_SELECTORS: [&str; 2] = ["version", r#"packages."".version"#];
+
+/// The lockfiles that exist next to `package_json`
+pub fn lockfiles(package_json: &Path) -> Result<Vec<PathBuf>> {
+    if package_json.file_name() != Some("package.json".as_ref()) {
+        anyhow::bail!(
+            "--update-lockfile only applies to package.json, not {}",
+            package_json.display()
+        );
+    }
+    let dir = package_json.parent().unwrap_or(Path::new(""));
+    Ok(LOCKFILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| path.is_file())
+        .collect())
+}
+
+/// Load a lockfile and set the root package's version in it
+pub fn update_lockfile(path: &Path, version: &str) -> Result<Document> {
+    let mut doc = crate::load_document(path, InputOptions::default())?;
+    doc.set_version(LOCKFILE_SELECTORS[0], version)?;
+    if doc.read_version(LOCKFILE_SELECTORS[1]).is_ok() {
+        doc.set_version(LOCKFILE_SELECTORS[1], version)?;
+    }
+    Ok(doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_update_lockfile() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let package = dir.path().join("package.json");
+        fs::write(&package, "{\"version\": \"1.2.3\"}\n")?;
+        let lock = dir.path().join("package-lock.json");
+        let content = r#"{
+  "name": "demo",
+  "version": "1.2.3",
+  "lockfileVersion": 3,
+  "packages": {
+    "": {
+      "name": "demo",
+      "version": "1.2.3"
+    },
+    "node_modules/dep": {
+      "version": "1.2.3"
+    }
+  }
+}
+"#;
+        fs::write(&lock, content)?;
+
+        assert_eq!(lockfiles(&package)?, [lock.as_path()]);
+        assert!(lockfiles(&dir.path().join("Cargo.toml")).is_err());
+        // The dependency's version is left alone
+        let doc = update_lockfile(&lock, "1.3.0")?;
+        assert_eq!(doc.render()?, content.replacen("1.2.3", "1.3.0", 2));
+        Ok(())
+    }
+}
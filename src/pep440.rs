@@ -0,0 +1,228 @@
+//! Python package versions as described by PEP 440, such as `1.2.3a1`,
+//! `1.2.3.post1` or `2024.1.0.dev0`
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::{cmp::Ordering, fmt};
+
+/// The pattern from PEP 440, which also accepts the spellings it normalizes
+/// (`1.0-alpha.1`, `1.0_post2`, `1.0-1`, ...)
+const PATTERN: &str = r"(?ix)^\s*v?
+    (?:(?P<epoch>[0-9]+)!)?
+    (?P<release>[0-9]+(?:\.[0-9]+)*)
+    (?P<pre>[-_.]?(?P<pre_l>alpha|a|beta|b|preview|pre|c|rc)[-_.]?(?P<pre_n>[0-9]+)?)?
+    (?P<post>-(?P<post_n1>[0-9]+)|[-_.]?(?:post|rev|r)[-_.]?(?P<post_n2>[0-9]+)?)?
+    (?P<dev>[-_.]?dev[-_.]?(?P<dev_n>[0-9]+)?)?
+    (?:\+(?P<local>[a-z0-9]+(?:[-_.][a-z0-9]+)*))?
+    \s*$";
+
+/// A pre-release phase, in release order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    Alpha,
+    Beta,
+    Rc,
+}
+
+impl Phase {
+    /// The phase for a pre-release label such as `a`, `alpha` or `rc`
+    pub fn parse(label: &str) -> Result<Self> {
+        match label.to_lowercase().as_str() {
+            "a" | "alpha" => Ok(Phase::Alpha),
+            "b" | "beta" => Ok(Phase::Beta),
+            "rc" | "c" | "pre" | "preview" => Ok(Phase::Rc),
+            _ => anyhow::bail!("PEP 440 pre-releases are a, b or rc, not {}", label),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Phase::Alpha => "a",
+            Phase::Beta => "b",
+            Phase::Rc => "rc",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pep440 {
+    pub epoch: u64,
+    pub release: Vec<u64>,
+    pub pre: Option<(Phase, u64)>,
+    pub post: Option<u64>,
+    pub dev: Option<u64>,
+    pub local: Option<String>,
+}
+
+impl Pep440 {
+    pub fn parse(version: &str) -> Result<Self> {
+        let invalid = || format!("Invalid PEP 440 version: {}", version);
+        let captures = Regex::new(PATTERN)?
+            .captures(version)
+            .with_context(invalid)?;
+        let number = |name: &str| -> Result<u64> {
+            captures
+                .name(name)
+                .map_or(Ok(0), |n| n.as_str().parse())
+                .with_context(invalid)
+        };
+        let pre = match captures.name("pre_l") {
+            Some(label) => Some((Phase::parse(label.as_str())?, number("pre_n")?)),
+            None => None,
+        };
+        let post = match captures.name("post") {
+            Some(_) => Some(number("post_n1")?.max(number("post_n2")?)),
+            None => None,
+        };
+        let dev = match captures.name("dev") {
+            Some(_) => Some(number("dev_n")?),
+            None => None,
+        };
+        Ok(Pep440 {
+            epoch: number("epoch")?,
+            release: captures["release"]
+                .split('.')
+                .map(|n| n.parse().with_context(invalid))
+                .collect::<Result<_>>()?,
+            pre,
+            post,
+            dev,
+            local: captures
+                .name("local")
+                .map(|local| local.as_str().to_lowercase().replace(['-', '_'], ".")),
+        })
+    }
+
+    /// This version with the release segment at `index` incremented, the
+    /// segments after it zeroed and every suffix dropped; the release is
+    /// padded to at least three segments
+    pub fn bump_release(&self, index: usize) -> Self {
+        let mut release = self.release.clone();
+        release.resize(release.len().max(3).max(index + 1), 0);
+        release[index] += 1;
+        release[index + 1..].iter_mut().for_each(|n| *n = 0);
+        Pep440 {
+            release,
+            ..self.final_release()
+        }
+    }
+
+    /// This version without pre-release, post-release, dev or local parts
+    pub fn final_release(&self) -> Self {
+        Pep440 {
+            epoch: self.epoch,
+            release: self.release.clone(),
+            pre: None,
+            post: None,
+            dev: None,
+            local: None,
+        }
+    }
+
+    /// Everything after the release, ordered as PEP 440 requires: a dev
+    /// release of a final version comes before its pre-releases, a missing
+    /// post-release before any post-release and a missing dev release after
+    /// any dev release
+    fn suffix_key(&self) -> (u8, Option<(Phase, u64)>, Option<u64>, u64) {
+        let stage = match (self.pre, self.post, self.dev) {
+            (None, None, Some(_)) => 0,
+            (Some(_), _, _) => 1,
+            _ => 2,
+        };
+        (stage, self.pre, self.post, self.dev.unwrap_or(u64::MAX))
+    }
+}
+
+impl Ord for Pep440 {
+    /// Local labels are ignored, and trailing zeros don't count (`1.0 == 1.0.0`)
+    fn cmp(&self, other: &Self) -> Ordering {
+        let trimmed = |release: &[u64]| {
+            let end = release.iter().rposition(|n| *n != 0).map_or(0, |i| i + 1);
+            release[..end].to_vec()
+        };
+        self.epoch
+            .cmp(&other.epoch)
+            .then_with(|| trimmed(&self.release).cmp(&trimmed(&other.release)))
+            .then_with(|| self.suffix_key().cmp(&other.suffix_key()))
+    }
+}
+
+impl PartialOrd for Pep440 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Pep440 {
+    /// The normalized form, e.g. `1!2.0.0rc1.post2.dev3+local`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+        let release: Vec<String> = self.release.iter().map(u64::to_string).collect();
+        write!(f, "{}", release.join("."))?;
+        if let Some((phase, n)) = self.pre {
+            write!(f, "{}{}", phase.as_str(), n)?;
+        }
+        if let Some(n) = self.post {
+            write!(f, ".post{}", n)?;
+        }
+        if let Some(n) = self.dev {
+            write!(f, ".dev{}", n)?;
+        }
+        if let Some(local) = &self.local {
+            write!(f, "+{}", local)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_normalizes() -> Result<()> {
+        let normalized = |version| Pep440::parse(version).map(|v| v.to_string());
+        assert_eq!(normalized("1.2.3")?, "1.2.3");
+        assert_eq!(normalized("v1.2.3-Alpha.1")?, "1.2.3a1");
+        assert_eq!(normalized("1.0c2")?, "1.0rc2");
+        assert_eq!(normalized("1.0-1")?, "1.0.post1");
+        assert_eq!(normalized("1.0.post")?, "1.0.post0");
+        assert_eq!(normalized("2024.1.0.dev0")?, "2024.1.0.dev0");
+        assert_eq!(
+            normalized("1!2.0rc1.post2.dev3+Ubuntu-1")?,
+            "1!2.0rc1.post2.dev3+ubuntu.1"
+        );
+        assert!(Pep440::parse("1.2.3-foo").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ordering() -> Result<()> {
+        let versions = [
+            "1.0.dev1",
+            "1.0a1.dev1",
+            "1.0a1",
+            "1.0a2",
+            "1.0b1",
+            "1.0rc1",
+            "1.0",
+            "1.0.post1.dev1",
+            "1.0.post1",
+            "1.1",
+            "1!0.1",
+        ];
+        let parsed = versions
+            .iter()
+            .map(|v| Pep440::parse(v))
+            .collect::<Result<Vec<_>>>()?;
+        assert!(parsed.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(Pep440::parse("1.0")?, Pep440::parse("1.0")?);
+        assert_eq!(
+            Pep440::parse("1.0")?.cmp(&Pep440::parse("1.0.0+local")?),
+            Ordering::Equal
+        );
+        Ok(())
+    }
+}
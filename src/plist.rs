@@ -0,0 +1,57 @@
+//! Locating values in Apple XML property lists such as Info.plist
+
+use crate::xml;
+use std::ops::Range;
+
+/// Byte range of the trimmed text of the value following `<key>name</key>`
+/// in the top-level dictionary, e.g. the `<string>` of
+/// `CFBundleShortVersionString`
+pub fn locate(content: &str, name: &str) -> Option<Range<usize>> {
+    let mut after_key = false;
+    let pos = xml::find_element(content, |stack, pos| match stack {
+        ["plist", "dict", "key"] => {
+            after_key = xml::text(content, pos).is_some_and(|key| &content[key] == name);
+            false
+        }
+        ["plist", "dict", _] => std::mem::take(&mut after_key),
+        _ => false,
+    })?;
+    xml::text(content, pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>CFBundleIdentifier</key>
+	<string>com.example.app</string>
+	<key>NSAppTransportSecurity</key>
+	<dict>
+		<key>CFBundleVersion</key>
+		<string>99</string>
+	</dict>
+	<!-- <key>CFBundleShortVersionString</key><string>0.0.1</string> -->
+	<key>CFBundleShortVersionString</key>
+	<string>1.2.3</string>
+	<key>UIRequiresFullScreen</key>
+	<true/>
+	<key>CFBundleVersion</key>
+	<integer>42</integer>
+</dict>
+</plist>
+"#;
+        let value = |name| locate(content, name).map(|range| &content[range]);
+        assert_eq!(value("CFBundleShortVersionString"), Some("1.2.3"));
+        assert_eq!(value("CFBundleVersion"), Some("42"));
+        assert_eq!(value("CFBundleIdentifier"), Some("com.example.app"));
+        assert_eq!(value("NSAppTransportSecurity"), None);
+        assert_eq!(value("UIRequiresFullScreen"), None);
+        assert_eq!(value("missing"), None);
+    }
+}
@@ -0,0 +1,136 @@
+//! Commands handling file formats svbump doesn't know, registered by
+//! extension in the `[plugins]` table of svbump.toml:
+//!
+//! ```toml
+//! [plugins]
+//! spec = "./scripts/spec-version"
+//! wasm-format = "wasmtime run plugins/format.wasm"
+//! ```
+//!
+//! The file's content is passed on stdin. `COMMAND read SELECTOR` prints the
+//! version, and `COMMAND write SELECTOR VERSION` prints the whole content with
+//! the version replaced. A failing command fails the read or write, with its
+//! stderr as the reason.
+
+use anyhow::{Context, Result};
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    sync::RwLock,
+};
+
+static PLUGINS: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+/// Register the commands of a `[plugins]` table for the rest of the process,
+/// keyed by extension with or without the leading dot
+pub fn register(plugins: &BTreeMap<String, String>) {
+    let mut registered = PLUGINS.write().unwrap_or_else(|err| err.into_inner());
+    for (ext, command) in plugins {
+        registered.insert(ext.trim_start_matches('.').to_string(), command.clone());
+    }
+}
+
+/// The command registered for the extension of `path`
+pub fn command_for(path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_str()?;
+    let registered = PLUGINS.read().unwrap_or_else(|err| err.into_inner());
+    registered.get(ext).cloned()
+}
+
+/// Run `command` with `args` appended, `content` on stdin, returning stdout
+fn run(command: &str, args: &[&str], content: &str) -> Result<String> {
+    let mut child = if cfg!(windows) {
+        Command::new("cmd")
+            .arg("/C")
+            .arg(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+    } else {
+        // Passed through "$@" so the arguments aren't split or expanded
+        Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$@\"", command))
+            .arg("svbump")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+    }
+    .with_context(|| format!("Failed to run plugin `{}`", command))?;
+
+    let mut stdin = child.stdin.take().unwrap();
+    let content = content.to_string();
+    // Written from another thread so a plugin printing before it has read all
+    // of stdin can't deadlock against us
+    let writer = std::thread::spawn(move || stdin.write_all(content.as_bytes()));
+    let output = child.wait_with_output()?;
+    // A plugin may exit without reading stdin, closing the pipe early
+    let _ = writer.join();
+    if !output.status.success() {
+        anyhow::bail!(
+            "Plugin `{} {}` failed with {}: {}",
+            command,
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout)
+        .with_context(|| format!("Plugin `{}` printed invalid UTF-8", command))
+}
+
+/// Read the version at `selector` through the plugin
+pub fn read(command: &str, content: &str, selector: &str) -> Result<String> {
+    let version = run(command, &["read", selector], content)?;
+    let version = version.trim();
+    if version.is_empty() {
+        anyhow::bail!("Plugin `{}` found no version at {}", command, selector);
+    }
+    Ok(version.to_string())
+}
+
+/// Replace the version at `selector` through the plugin, checking the new
+/// content reads back as `version`
+pub fn write(command: &str, content: &str, selector: &str, version: &str) -> Result<String> {
+    let written = run(command, &["write", selector, version], content)?;
+    let read_back = read(command, &written, selector)?;
+    if read_back != version {
+        anyhow::bail!(
+            "Plugin `{}` wrote {} at {}, but it reads back as {}",
+            command,
+            version,
+            selector,
+            read_back
+        );
+    }
+    Ok(written)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// A plugin for `name: version` lines, in sed
+    const PLUGIN: &str = r#"f() { if [ "$1" = read ]; then sed -n "s/^$2: //p"; else sed "s/^$2: .*/$2: $3/"; fi; }; f"#;
+
+    #[test]
+    fn test_plugin() -> Result<()> {
+        let content = "name: app\nversion: 1.2.3\n";
+        assert_eq!(read(PLUGIN, content, "version")?, "1.2.3");
+        assert_eq!(
+            write(PLUGIN, content, "version", "1.3.0")?,
+            "name: app\nversion: 1.3.0\n"
+        );
+        assert!(read(PLUGIN, content, "missing").is_err());
+        // A write the plugin doesn't make is caught by reading it back
+        assert!(write("f() { cat; }; f", content, "version", "1.3.0").is_err());
+        assert!(read("exit 3; :", content, "version").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Choosing a level interactively, for releases run by hand
+
+use crate::{bump_semver, BumpOptions, VersionBump};
+use anyhow::Result;
+use std::io::{BufRead, Write};
+
+/// Levels offered, in order; those that don't apply to the current version
+/// or scheme are left out
+const LEVELS: [&str; 11] = [
+    "major", "minor", "patch", "revision", "epoch", "alpha", "beta", "rc", "post", "dev", "release",
+];
+
+/// The levels that apply to `current`, each with the version it gives
+pub fn candidates(current: &str, opts: &BumpOptions) -> Vec<(&'static str, String)> {
+    LEVELS
+        .iter()
+        .filter_map(|name| {
+            let level = name.parse().ok()?;
+            Some((*name, bump_semver(current, &level, opts).ok()?))
+        })
+        .collect()
+}
+
+/// List the levels that apply to `current` with the version each gives, ask
+/// for one by number or name, then confirm writing it to `target`. Returns
+/// None when the input ends or the write isn't confirmed.
+pub fn choose_level(
+    current: &str,
+    target: &str,
+    opts: &BumpOptions,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<Option<VersionBump>> {
+    let choices = candidates(current, opts);
+    if choices.is_empty() {
+        anyhow::bail!("No level applies to version {}", current);
+    }
+
+    writeln!(output, "Current version: {}", current)?;
+    for (i, (name, version)) in choices.iter().enumerate() {
+        writeln!(output, "  {}) {:<8} {}", i + 1, name, version)?;
+    }
+    let (name, version) = loop {
+        write!(output, "Choose a level [1-{}]: ", choices.len())?;
+        output.flush()?;
+        let Some(answer) = read_line(input)? else {
+            return Ok(None);
+        };
+        let chosen = match answer.parse::<usize>() {
+            Ok(n) => n.checked_sub(1).and_then(|i| choices.get(i)),
+            Err(_) => choices.iter().find(|(name, _)| answer == *name),
+        };
+        match chosen {
+            Some(choice) => break choice,
+            None => writeln!(output, "Not a choice: {}", answer)?,
+        }
+    };
+
+    write!(output, "Write {} to {}? [y/N] ", version, target)?;
+    output.flush()?;
+    let confirmed = read_line(input)?.is_some_and(|answer| matches!(answer.as_str(), "y" | "yes"));
+    confirmed.then(|| name.parse()).transpose()
+}
+
+/// The next line, trimmed and lowercased, or None at the end of the input
+fn read_line(input: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn choose(current: &str, answers: &str) -> Result<(Option<VersionBump>, String)> {
+        let mut output = Vec::new();
+        let level = choose_level(
+            current,
+            "package.json",
+            &BumpOptions::default(),
+            &mut answers.as_bytes(),
+            &mut output,
+        )?;
+        Ok((level, String::from_utf8(output)?))
+    }
+
+    #[test]
+    fn test_choose_level() -> Result<()> {
+        let (level, output) = choose("1.2.3", "9\n2\ny\n")?;
+        assert!(matches!(level, Some(VersionBump::Minor)));
+        assert!(output.starts_with("Current version: 1.2.3\n  1) major    2.0.0\n"));
+        assert!(output.contains("  4) alpha    1.2.3-alpha.1\n"));
+        assert!(!output.contains("release"));
+        assert!(output.contains("Not a choice: 9\n"));
+        assert!(output.ends_with("Write 1.3.0 to package.json? [y/N] "));
+
+        let (level, output) = choose("2.0.0-rc.1", "release\nyes\n")?;
+        assert!(matches!(level, Some(VersionBump::Release)));
+        assert!(output.contains("Write 2.0.0 to package.json?"));
+
+        assert!(choose("1.2.3", "1\nn\n")?.0.is_none());
+        assert!(choose("1.2.3", "")?.0.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_candidates() {
+        let opts = BumpOptions::default();
+        let levels: Vec<_> = candidates("1.2.3", &opts)
+            .into_iter()
+            .map(|(level, _)| level)
+            .collect();
+        assert_eq!(levels, ["major", "minor", "patch", "alpha", "beta", "rc"]);
+        assert_eq!(
+            candidates("2.0.0-rc.1", &opts).last(),
+            Some(&("release", "2.0.0".to_string()))
+        );
+        assert!(candidates("not a version", &opts).is_empty());
+    }
+}
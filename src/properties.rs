@@ -0,0 +1,93 @@
+//! Locating values in Java .properties files such as gradle.properties
+
+use std::ops::Range;
+
+/// Byte range of the value of `key`, which may be separated from its value by
+/// `=`, `:` or whitespace. Keys are compared with their escapes resolved
+/// (`my\ key` is `my key`), and as in Java the last definition wins. Values
+/// continued onto the next line with a trailing `\` aren't matched.
+pub fn locate(content: &str, key: &str) -> Option<Range<usize>> {
+    let mut found = None;
+    let mut continued = false;
+    let mut pos = 0;
+    for line in content.split_inclusive('\n') {
+        let start = pos;
+        pos += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+        let was_continued = continued;
+        continued = line.bytes().rev().take_while(|&b| b == b'\\').count() % 2 == 1;
+        let body = line.trim_start_matches([' ', '\t', '\x0c']);
+        if was_continued || body.is_empty() || body.starts_with(['#', '!']) {
+            continue;
+        }
+        let offset = start + line.len() - body.len();
+        let (name, rest) = split_key(body);
+        if name != key || continued {
+            continue;
+        }
+        let value = separator_end(&body[rest..]);
+        let value_start = offset + rest + value;
+        let value_end = offset + body.trim_end().len();
+        found = Some(value_start..value_end.max(value_start));
+    }
+    found
+}
+
+/// The unescaped key at the start of `body`, and where the key ends
+fn split_key(body: &str) -> (String, usize) {
+    let mut name = String::new();
+    let mut chars = body.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, escaped)) => name.push(escaped),
+                None => return (name, body.len()),
+            },
+            '=' | ':' | ' ' | '\t' | '\x0c' => return (name, i),
+            _ => name.push(c),
+        }
+    }
+    (name, body.len())
+}
+
+/// Length of the separator at the start of `rest`: whitespace around at
+/// most one `=` or `:`
+fn separator_end(rest: &str) -> usize {
+    let is_space = |c: char| matches!(c, ' ' | '\t' | '\x0c');
+    let after_space = rest.trim_start_matches(is_space);
+    let after_separator = after_space
+        .strip_prefix(['=', ':'])
+        .unwrap_or(after_space)
+        .trim_start_matches(is_space);
+    rest.len() - after_separator.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() {
+        let content = "\
+# version=0.0.1
+! also a comment
+description = multi \\
+    version=9.9.9
+group:com.example
+my\\ key = 1.0.0
+version 1.2.3  \r
+org.gradle.jvmargs=-Xmx2g
+version=1.2.4
+";
+        let value = |key| locate(content, key).map(|range| &content[range]);
+        assert_eq!(value("version"), Some("1.2.4"));
+        assert_eq!(value("group"), Some("com.example"));
+        assert_eq!(value("my key"), Some("1.0.0"));
+        assert_eq!(value("org.gradle.jvmargs"), Some("-Xmx2g"));
+        assert_eq!(value("description"), None);
+        assert_eq!(value("missing"), None);
+
+        let first = "version 1.2.3  \r\n";
+        assert_eq!(locate(first, "version").map(|r| &first[r]), Some("1.2.3"));
+    }
+}
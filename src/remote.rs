@@ -0,0 +1,109 @@
+//! Reading files from another host over SSH, for `read` and `preview`
+
+use anyhow::{Context, Result};
+use std::{env, path::Path, process::Command};
+
+/// A `[user@]host:/path` location, as accepted by scp
+#[derive(Debug, PartialEq, Eq)]
+pub struct RemotePath {
+    pub host: String,
+    pub path: String,
+}
+
+impl RemotePath {
+    /// Recognize scp-style paths. Local files that exist always win,
+    /// single-letter hosts are treated as Windows drive letters, and hosts
+    /// starting with `-` are refused so they can't be passed as ssh options.
+    pub fn parse(path: &Path) -> Option<Self> {
+        if path.exists() {
+            return None;
+        }
+        let (host, remote_path) = path.to_str()?.split_once(':')?;
+        if host.len() < 2
+            || host.starts_with('-')
+            || host.contains(['/', '\\'])
+            || remote_path.is_empty()
+        {
+            return None;
+        }
+        Some(RemotePath {
+            host: host.to_string(),
+            path: remote_path.to_string(),
+        })
+    }
+
+    /// Fetch the file using `$SVBUMP_SSH`, or `ssh` if unset
+    pub fn fetch(&self) -> Result<String> {
+        let program = env::var("SVBUMP_SSH").unwrap_or_else(|_| "ssh".to_string());
+        self.fetch_with(&program)
+    }
+
+    fn fetch_with(&self, program: &str) -> Result<String> {
+        let output = Command::new(program)
+            .arg(&self.host)
+            .arg(format!("cat {}", shell_quote(&self.path)))
+            .output()
+            .with_context(|| format!("Failed to run {}", program))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Failed to read {}:{}: {}",
+                self.host,
+                self.path,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        String::from_utf8(output.stdout).context("Remote file is not valid UTF-8")
+    }
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_remote_path() {
+        assert_eq!(
+            RemotePath::parse(Path::new("deploy@web1:/srv/app/package.json")),
+            Some(RemotePath {
+                host: "deploy@web1".to_string(),
+                path: "/srv/app/package.json".to_string(),
+            })
+        );
+        assert_eq!(RemotePath::parse(Path::new("C:/app/package.json")), None);
+        assert_eq!(RemotePath::parse(Path::new("./a:b/package.json")), None);
+        assert_eq!(RemotePath::parse(Path::new("package.json")), None);
+        assert_eq!(RemotePath::parse(Path::new("-oProxyCommand=id:x")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fetch_with_stub_command() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let stub = dir.path().join("ssh");
+        let args_file = dir.path().join("args");
+        std::fs::write(
+            &stub,
+            format!(
+                "#!/bin/sh\necho \"$1 $2\" > {}\nprintf '{{\"version\": \"1.2.3\"}}'\n",
+                args_file.display()
+            ),
+        )?;
+        std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755))?;
+
+        let remote = RemotePath::parse(Path::new("deploy@web1:/srv/my app/package.json")).unwrap();
+        let content = remote.fetch_with(stub.to_str().unwrap())?;
+        let value: serde_json::Value = serde_json::from_str(&content)?;
+        assert_eq!(value["version"], "1.2.3");
+        assert_eq!(
+            std::fs::read_to_string(args_file)?.trim(),
+            "deploy@web1 cat '/srv/my app/package.json'"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+//! Parsing selectors such as `package.version`, `packages[0].version` or
+//! `"io.github.app".version` into path segments
+
+use anyhow::{Context, Result};
+use std::{iter::Peekable, str::Chars};
+
+/// Split a selector into its segments. `[n]` indexes into an array and is the
+/// same as a `.n` segment; numeric segments are indexes wherever the value
+/// they apply to is an array. Keys containing dots are written in double or
+/// single quotes, with `\` escaping the next character.
+pub fn parse(selector: &str) -> Result<Vec<String>> {
+    let mut parts = Vec::new();
+    let mut chars = selector.chars().peekable();
+    loop {
+        match chars.peek() {
+            Some('"' | '\'') => parts.push(quoted(&mut chars, selector)?),
+            Some('[') if parts.is_empty() => {}
+            _ => {
+                let mut key = String::new();
+                while let Some(c) = chars.next_if(|c| !matches!(c, '.' | '[')) {
+                    key.push(c);
+                }
+                if key.is_empty() {
+                    anyhow::bail!("Empty segment in selector {}", selector);
+                }
+                parts.push(key);
+            }
+        }
+        while chars.next_if_eq(&'[').is_some() {
+            let index: String = chars.by_ref().take_while(|c| *c != ']').collect();
+            index
+                .parse::<usize>()
+                .with_context(|| format!("Invalid index [{}] in selector {}", index, selector))?;
+            parts.push(index);
+        }
+        match chars.next() {
+            None => return Ok(parts),
+            Some('.') => continue,
+            Some(c) => anyhow::bail!("Unexpected {:?} in selector {}", c, selector),
+        }
+    }
+}
+
+fn quoted(chars: &mut Peekable<Chars>, selector: &str) -> Result<String> {
+    let quote = chars.next().unwrap();
+    let mut key = String::new();
+    loop {
+        match chars.next() {
+            Some(c) if c == quote => return Ok(key),
+            Some('\\') => key.extend(chars.next()),
+            Some(c) => key.push(c),
+            None => anyhow::bail!("Unclosed {} in selector {}", quote, selector),
+        }
+    }
+}
+
+/// Join segments back into a selector, quoting keys that need it
+pub fn join(parts: &[impl AsRef<str>]) -> String {
+    parts
+        .iter()
+        .map(|part| {
+            let part = part.as_ref();
+            if part.is_empty() || part.contains(['.', '[', ']', '"', '\'', '\\']) {
+                format!("\"{}\"", part.replace('\\', "\\\\").replace('"', "\\\""))
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The index a segment names, if it is numeric
+pub fn index(part: &str) -> Option<usize> {
+    part.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        assert_eq!(parse("package.version")?, ["package", "version"]);
+        assert_eq!(parse("packages[0].version")?, ["packages", "0", "version"]);
+        assert_eq!(parse("packages.0.version")?, ["packages", "0", "version"]);
+        assert_eq!(parse("matrix[1][2]")?, ["matrix", "1", "2"]);
+        assert_eq!(parse("[0].version")?, ["0", "version"]);
+        assert_eq!(
+            parse(r#""io.github.app".version"#)?,
+            ["io.github.app", "version"]
+        );
+        assert_eq!(
+            parse(r#"package.'my.key'.version"#)?,
+            ["package", "my.key", "version"]
+        );
+        assert_eq!(parse(r#"a."say \"hi\"""#)?, ["a", r#"say "hi""#]);
+        assert!(parse(r#"a."unclosed"#).is_err());
+        assert!(parse(r#""a"b"#).is_err());
+        assert!(parse("packages[x].version").is_err());
+        assert!(parse("packages[0.version").is_err());
+        assert!(parse("package..version").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_join_round_trips() -> Result<()> {
+        for selector in [
+            "package.version",
+            r#"package."my.key".version"#,
+            r#"a."say \"hi\"""#,
+        ] {
+            assert_eq!(join(&parse(selector)?), selector);
+        }
+        Ok(())
+    }
+}
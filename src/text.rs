@@ -0,0 +1,405 @@
+//! Formats edited as text, where the version is located in the content and
+//! replaced in place so the rest of the file is left byte-for-byte intact
+
+use crate::encoding::{strip_bom, Encoding};
+use crate::{
+    cue, dhall, dockerfile, gradle, hcl, ini, nix, plist, plugin, properties, selector, xml,
+};
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextFormat {
+    /// `NAME := 1.2.3` style variable assignments, with the selector naming the variable
+    Makefile,
+    /// `.env` style `KEY=value` lines, with the selector naming the key
+    Env,
+    /// INI such as setup.cfg, with the selector naming `section.key`
+    Ini,
+    /// Java `.properties` such as gradle.properties, with the selector naming
+    /// the key
+    Properties,
+    /// Groovy or Kotlin Gradle scripts, with the selector naming the variable
+    /// assigned a quoted version
+    Gradle,
+    /// XML such as pom.xml or .csproj, with the selector naming the element
+    /// path from the root (e.g. "project.version")
+    Xml,
+    /// Apple XML property lists such as Info.plist, with the selector naming
+    /// a key of the top-level dictionary
+    Plist,
+    /// Nix expressions such as default.nix, with the selector naming the
+    /// attribute path of a string (e.g. "version" or "meta.version")
+    Nix,
+    /// HCL such as Terraform or Packer files, with the selector naming the
+    /// block path of a string (e.g. "module.vpc.version")
+    Hcl,
+    /// CUE, with the selector naming the field path of a string (e.g.
+    /// "deploy.image.tag")
+    Cue,
+    /// Dhall, with the selector naming the path of a string through record
+    /// fields and `let` bindings (e.g. "package.version")
+    Dhall,
+    /// Dockerfiles, with the selector naming an `ARG`, a `LABEL` key or a
+    /// `FROM` image (e.g. "ARG.VERSION" or "FROM.node")
+    Dockerfile,
+    /// A file holding only the version, with `.` as the selector
+    Plain,
+    /// Any text, with the selector a regex whose `version` group (or first
+    /// group) captures the version
+    Regex,
+    /// A format registered in svbump.toml, read and written by its command
+    Plugin,
+}
+
+impl TextFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TextFormat::Makefile => "makefile",
+            TextFormat::Env => "env",
+            TextFormat::Ini => "ini",
+            TextFormat::Properties => "properties",
+            TextFormat::Gradle => "gradle",
+            TextFormat::Xml => "xml",
+            TextFormat::Plist => "plist",
+            TextFormat::Nix => "nix",
+            TextFormat::Hcl => "hcl",
+            TextFormat::Cue => "cue",
+            TextFormat::Dhall => "dhall",
+            TextFormat::Dockerfile => "dockerfile",
+            TextFormat::Plain => "plain",
+            TextFormat::Regex => "regex",
+            TextFormat::Plugin => "plugin",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TextDocument {
+    /// The content without any BOM, which `render` puts back
+    content: String,
+    bom: bool,
+    format: TextFormat,
+    /// The command of a `Plugin` document
+    plugin: Option<String>,
+}
+
+impl TextDocument {
+    pub fn new(content: &str, format: TextFormat) -> Self {
+        TextDocument {
+            content: strip_bom(content).to_string(),
+            bom: Encoding::detect(content).bom,
+            format,
+            plugin: None,
+        }
+    }
+
+    /// A document of a format handled by the plugin `command`
+    pub fn plugin(content: &str, command: String) -> Self {
+        TextDocument {
+            plugin: Some(command),
+            ..TextDocument::new(content, TextFormat::Plugin)
+        }
+    }
+
+    /// Pattern matching the assignment chosen by `selector`, capturing the
+    /// value as `version`
+    fn pattern(selector: &str) -> Result<Regex> {
+        let pattern = format!(
+            r"(?m)^[ \t]*(?:(?:override|export)[ \t]+)*{}[ \t]*(?::::=|::=|:=|\?=|=)[ \t]*(?P<version>[^\s#]+)",
+            regex::escape(selector)
+        );
+        Ok(Regex::new(&pattern)?)
+    }
+
+    /// Pattern matching the `KEY=value` line chosen by `selector`, capturing
+    /// the value inside any quotes as `double`, `single` or `bare`
+    fn env_pattern(selector: &str) -> Result<Regex> {
+        let pattern = format!(
+            r#"(?m)^[ \t]*(?:export[ \t]+)?{}[ \t]*=[ \t]*(?:"(?P<double>[^"\r\n]*)"|'(?P<single>[^'\r\n]*)'|(?P<bare>[^\s#"']+))"#,
+            regex::escape(selector)
+        );
+        Ok(Regex::new(&pattern)?)
+    }
+
+    fn locate(&self, selector: &str) -> Result<Range<usize>> {
+        match self.format {
+            TextFormat::Makefile => {
+                let captures = Self::pattern(selector)?
+                    .captures(&self.content)
+                    .with_context(|| format!("No assignment found for {}", selector))?;
+                Ok(captures.name("version").unwrap().range())
+            }
+            TextFormat::Env => {
+                let captures = Self::env_pattern(selector)?
+                    .captures(&self.content)
+                    .with_context(|| format!("No value found for {}", selector))?;
+                let value = ["double", "single", "bare"]
+                    .into_iter()
+                    .find_map(|group| captures.name(group))
+                    .unwrap();
+                Ok(value.range())
+            }
+            TextFormat::Ini => {
+                // section names may hold dots, so the key is the last segment
+                let (section, key) = selector.rsplit_once('.').unwrap_or(("", selector));
+                ini::locate(&self.content, section, key)
+                    .with_context(|| format!("No value found for {}", selector))
+            }
+            TextFormat::Properties => properties::locate(&self.content, selector)
+                .with_context(|| format!("No value found for {}", selector)),
+            TextFormat::Gradle => gradle::locate(&self.content, selector)?
+                .with_context(|| format!("No assignment found for {}", selector)),
+            TextFormat::Xml => xml::locate(&self.content, &selector::parse(selector)?)
+                .with_context(|| format!("No text element found at {}", selector)),
+            TextFormat::Plist => plist::locate(&self.content, selector)
+                .with_context(|| format!("No string value found for key {}", selector)),
+            TextFormat::Nix => nix::locate(&self.content, &selector::parse(selector)?)?
+                .with_context(|| format!("No attribute found at {}", selector)),
+            TextFormat::Hcl => hcl::locate(&self.content, &selector::parse(selector)?)?
+                .with_context(|| format!("No attribute found at {}", selector)),
+            TextFormat::Cue => cue::locate(&self.content, &selector::parse(selector)?)?
+                .with_context(|| format!("No field found at {}", selector)),
+            TextFormat::Dhall => dhall::locate(&self.content, &selector::parse(selector)?)?
+                .with_context(|| format!("No field found at {}", selector)),
+            TextFormat::Dockerfile => dockerfile::locate(&self.content, selector)?
+                .with_context(|| format!("No value found for {}", selector)),
+            TextFormat::Plain => {
+                if selector != "." {
+                    anyhow::bail!(
+                        "Plain version files only have the . selector, got {}",
+                        selector
+                    );
+                }
+                let version = self.content.trim();
+                if version.is_empty() {
+                    anyhow::bail!("The file is empty");
+                }
+                let start = self.content.len() - self.content.trim_start().len();
+                Ok(start..start + version.len())
+            }
+            TextFormat::Regex => {
+                let pattern = Regex::new(selector)
+                    .with_context(|| format!("Invalid pattern {}", selector))?;
+                let captures = pattern
+                    .captures(&self.content)
+                    .with_context(|| format!("No match for pattern {}", selector))?;
+                let version = captures
+                    .name("version")
+                    .or_else(|| captures.get(1))
+                    .with_context(|| format!("Pattern {} captures no version group", selector))?;
+                Ok(version.range())
+            }
+            TextFormat::Plugin => anyhow::bail!("Plugin documents are read by their plugin"),
+        }
+    }
+
+    pub fn read_version(&self, selector: &str) -> Result<String> {
+        if let Some(command) = &self.plugin {
+            return plugin::read(command, &self.content, selector);
+        }
+        Ok(self.content[self.locate(selector)?].to_string())
+    }
+
+    pub fn set_version(&mut self, selector: &str, version: &str) -> Result<()> {
+        if let Some(command) = &self.plugin {
+            self.content = plugin::write(command, &self.content, selector, version)?;
+            return Ok(());
+        }
+        let range = self.locate(selector)?;
+        let mut content = self.content.clone();
+        content.replace_range(range, version);
+        match self.format {
+            TextFormat::Nix => nix::check_edit(
+                &self.content,
+                &content,
+                &selector::parse(selector)?,
+                version,
+            )?,
+            TextFormat::Hcl => hcl::check_edit(
+                &self.content,
+                &content,
+                &selector::parse(selector)?,
+                version,
+            )?,
+            TextFormat::Cue => cue::check_edit(
+                &self.content,
+                &content,
+                &selector::parse(selector)?,
+                version,
+            )?,
+            TextFormat::Dhall => dhall::check_edit(
+                &self.content,
+                &content,
+                &selector::parse(selector)?,
+                version,
+            )?,
+            TextFormat::Dockerfile => {
+                dockerfile::check_edit(&self.content, &content, selector, version)?
+            }
+            _ => {}
+        }
+        self.content = content;
+        Ok(())
+    }
+
+    pub fn format(&self) -> TextFormat {
+        self.format
+    }
+
+    pub fn render(&self) -> String {
+        let encoding = Encoding {
+            bom: self.bom,
+            crlf: false,
+        };
+        encoding.apply(self.content.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_makefile_assignments() -> Result<()> {
+        let content = "\
+# release settings
+NAME = app
+VERSION := 1.2.3  # bumped by svbump
+LIB_VERSION = 0.4.0
+
+build:
+\techo $(VERSION)
+";
+        let mut doc = TextDocument::new(content, TextFormat::Makefile);
+        assert_eq!(doc.read_version("VERSION")?, "1.2.3");
+        assert_eq!(doc.read_version("LIB_VERSION")?, "0.4.0");
+
+        doc.set_version("VERSION", "1.3.0")?;
+        doc.set_version("LIB_VERSION", "0.4.1")?;
+        assert_eq!(
+            doc.render(),
+            content
+                .replace("VERSION := 1.2.3  #", "VERSION := 1.3.0  #")
+                .replace("LIB_VERSION = 0.4.0", "LIB_VERSION = 0.4.1")
+        );
+
+        assert!(doc.read_version("MISSING").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plain_version_file() -> Result<()> {
+        for content in ["1.2.3", "1.2.3\n", "  1.2.3\r\n\n"] {
+            let mut doc = TextDocument::new(content, TextFormat::Plain);
+            assert_eq!(doc.read_version(".")?, "1.2.3");
+            doc.set_version(".", "1.3.0")?;
+            assert_eq!(doc.render(), content.replace("1.2.3", "1.3.0"));
+        }
+        assert!(TextDocument::new("1.2.3", TextFormat::Plain)
+            .read_version("version")
+            .is_err());
+        assert!(TextDocument::new("\n", TextFormat::Plain)
+            .read_version(".")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_regex_pattern() -> Result<()> {
+        let content = "\"\"\"Demo.\"\"\"\n\n__version__ = \"1.2.3\"  # bumped\n";
+        let mut doc = TextDocument::new(content, TextFormat::Regex);
+        let pattern = r#"__version__ = "(?P<version>[^"]+)""#;
+        assert_eq!(doc.read_version(pattern)?, "1.2.3");
+        doc.set_version(pattern, "1.3.0")?;
+        assert_eq!(doc.render(), content.replace("1.2.3", "1.3.0"));
+
+        let header = "#define APP_VERSION \"2.0.0\"\n";
+        let doc = TextDocument::new(header, TextFormat::Regex);
+        assert_eq!(doc.read_version(r#"APP_VERSION "([^"]+)""#)?, "2.0.0");
+        assert!(doc.read_version("APP_VERSION").is_err());
+        assert!(doc.read_version("(unclosed").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xml_elements() -> Result<()> {
+        let content = "<project>\n  <!-- release -->\n  <version>1.2.3</version>\n</project>\n";
+        let mut doc = TextDocument::new(content, TextFormat::Xml);
+        assert_eq!(doc.read_version("project.version")?, "1.2.3");
+        doc.set_version("project.version", "1.3.0")?;
+        assert_eq!(doc.render(), content.replace("1.2.3", "1.3.0"));
+        assert!(doc.read_version("project.name").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_file() -> Result<()> {
+        let content = "\
+# deploy settings
+APP_NAME='demo'
+
+export APP_VERSION=\"1.2.3\"
+WORKER_VERSION = 0.4.0 # pinned
+SINGLE='2.0.0'
+";
+        let mut doc = TextDocument::new(content, TextFormat::Env);
+        assert_eq!(doc.read_version("APP_VERSION")?, "1.2.3");
+        assert_eq!(doc.read_version("WORKER_VERSION")?, "0.4.0");
+        assert_eq!(doc.read_version("SINGLE")?, "2.0.0");
+
+        doc.set_version("APP_VERSION", "1.3.0")?;
+        doc.set_version("WORKER_VERSION", "0.4.1")?;
+        assert_eq!(
+            doc.render(),
+            content
+                .replace("\"1.2.3\"", "\"1.3.0\"")
+                .replace("0.4.0 #", "0.4.1 #")
+        );
+
+        assert!(doc.read_version("VERSION").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ini_sections() -> Result<()> {
+        let content = "[metadata]\nname = demo\nversion = 1.2.3\n\n[tool:pytest]\naddopts = -q\n";
+        let mut doc = TextDocument::new(content, TextFormat::Ini);
+        assert_eq!(doc.read_version("metadata.version")?, "1.2.3");
+        doc.set_version("metadata.version", "1.3.0")?;
+        assert_eq!(doc.render(), content.replace("1.2.3", "1.3.0"));
+        assert!(doc.read_version("version").is_err());
+
+        let doc = TextDocument::new(
+            "[bumpversion.v1]\ncurrent_version = 2.0.0\n",
+            TextFormat::Ini,
+        );
+        assert_eq!(doc.read_version("bumpversion.v1.current_version")?, "2.0.0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_properties_file() -> Result<()> {
+        let content = "# build settings\nversion=1.2.3\nsnapshot\\ name : demo\\u00e9\n";
+        let mut doc = TextDocument::new(content, TextFormat::Properties);
+        assert_eq!(doc.read_version("version")?, "1.2.3");
+        doc.set_version("version", "1.3.0")?;
+        assert_eq!(doc.render(), content.replace("1.2.3", "1.3.0"));
+        assert_eq!(doc.read_version("snapshot name")?, "demo\\u00e9");
+        assert!(doc.read_version("build").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_gradle_script() -> Result<()> {
+        let content =
+            "plugins {\n    kotlin(\"jvm\") version \"1.9.0\"\n}\n\nversion = \"1.2.3\"\n";
+        let mut doc = TextDocument::new(content, TextFormat::Gradle);
+        assert_eq!(doc.read_version("version")?, "1.2.3");
+        doc.set_version("version", "1.3.0")?;
+        assert_eq!(doc.render(), content.replace("1.2.3", "1.3.0"));
+        assert!(doc.read_version("group").is_err());
+        Ok(())
+    }
+}
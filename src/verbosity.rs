@@ -0,0 +1,65 @@
+//! How much svbump prints besides its results, set once from `--quiet` or
+//! `--verbose`
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only the bare version on stdout, for command substitution
+    Quiet,
+    #[default]
+    Normal,
+    /// Notes on stderr about file types, selectors and each change
+    Verbose,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+impl Verbosity {
+    pub fn from_flags(quiet: bool, verbose: bool) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, true) => Verbosity::Verbose,
+            (false, false) => Verbosity::Normal,
+        }
+    }
+}
+
+/// Set the verbosity for the rest of the process
+pub fn set(verbosity: Verbosity) {
+    LEVEL.store(verbosity as u8, Ordering::Relaxed);
+}
+
+pub fn get() -> Verbosity {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        2 => Verbosity::Verbose,
+        _ => Verbosity::Normal,
+    }
+}
+
+pub fn is_quiet() -> bool {
+    get() == Verbosity::Quiet
+}
+
+/// Print a note to stderr when running with `--verbose`, e.g.
+/// `note(format_args!("{}: parsing as {}", path, file_type))`
+pub fn note(message: fmt::Arguments) {
+    if get() == Verbosity::Verbose {
+        eprintln!("svbump: {}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_flags() {
+        assert_eq!(Verbosity::from_flags(false, false), Verbosity::Normal);
+        assert_eq!(Verbosity::from_flags(true, false), Verbosity::Quiet);
+        assert_eq!(Verbosity::from_flags(false, true), Verbosity::Verbose);
+        assert_eq!(get(), Verbosity::Normal);
+    }
+}
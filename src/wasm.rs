@@ -0,0 +1,113 @@
+//! JavaScript bindings for Node-based release tooling, built with
+//! `wasm-pack build --target nodejs -- --features wasm`
+//!
+//! ```js
+//! const { readVersion, bumpVersion } = require("svbump");
+//!
+//! const bump = bumpVersion(fs.readFileSync("Cargo.toml", "utf8"), "Cargo.toml", "minor");
+//! fs.writeFileSync("Cargo.toml", bump.content);
+//! console.log(`${bump.old} -> ${bump.new}`);
+//! ```
+//!
+//! Files are passed in and out as strings, so reading and writing them is left
+//! to the caller. The file name picks the format and, when the selector is left
+//! out, the selector of a well-known file.
+
+use crate::{defaults, get_file_type, Document, VersionBump};
+use anyhow::{Context, Result};
+use std::path::Path;
+use wasm_bindgen::prelude::*;
+
+/// The result of `bumpVersion`: the old and new version, and the updated file
+#[wasm_bindgen(getter_with_clone)]
+pub struct Bump {
+    pub old: String,
+    pub new: String,
+    pub content: String,
+}
+
+/// Parse `content` as the file `file_name`, with the selector to use in it
+fn parse(content: &str, file_name: &str, selector: Option<String>) -> Result<(Document, String)> {
+    let path = Path::new(file_name);
+    let selector = match selector {
+        Some(selector) => selector,
+        None => defaults::selector_for(path)
+            .with_context(|| format!("No default selector for {}, pass one", file_name))?
+            .to_string(),
+    };
+    let doc = Document::parse(content, get_file_type(path, None)?)
+        .with_context(|| format!("Failed to parse {}", file_name))?;
+    Ok((doc, selector))
+}
+
+fn read(content: &str, file_name: &str, selector: Option<String>) -> Result<String> {
+    let (doc, selector) = parse(content, file_name, selector)?;
+    doc.read_version(&selector)
+}
+
+fn bump(content: &str, file_name: &str, level: &str, selector: Option<String>) -> Result<Bump> {
+    let level: VersionBump = level.parse()?;
+    let (mut doc, selector) = parse(content, file_name, selector)?;
+    let change = doc.bump(&selector, &level)?;
+    Ok(Bump {
+        old: change.old,
+        new: change.new,
+        content: doc.render()?,
+    })
+}
+
+fn js_error(err: anyhow::Error) -> JsError {
+    JsError::new(&format!("{:#}", err))
+}
+
+/// Read the version at `selector` in `content`, the contents of `fileName`
+#[wasm_bindgen(js_name = readVersion)]
+pub fn read_version(
+    content: &str,
+    file_name: &str,
+    selector: Option<String>,
+) -> Result<String, JsError> {
+    read(content, file_name, selector).map_err(js_error)
+}
+
+/// Bump the version at `selector` in `content` by `level` (a level such as
+/// "minor", or an explicit version), keeping the rest of the file as written
+#[wasm_bindgen(js_name = bumpVersion)]
+pub fn bump_version(
+    content: &str,
+    file_name: &str,
+    level: &str,
+    selector: Option<String>,
+) -> Result<Bump, JsError> {
+    bump(content, file_name, level, selector).map_err(js_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bump() -> Result<()> {
+        let content = "[package]\nname = \"app\" # the name\nversion = \"1.2.3\"\n";
+        assert_eq!(read(content, "Cargo.toml", None)?, "1.2.3");
+        let bumped = bump(content, "Cargo.toml", "minor", None)?;
+        assert_eq!(
+            (bumped.old.as_str(), bumped.new.as_str()),
+            ("1.2.3", "1.3.0")
+        );
+        assert_eq!(
+            bumped.content,
+            "[package]\nname = \"app\" # the name\nversion = \"1.3.0\"\n"
+        );
+
+        let bumped = bump(
+            "version: 1.0.0\n",
+            "app.yaml",
+            "2.0.0",
+            Some("version".into()),
+        )?;
+        assert_eq!(bumped.content, "version: 2.0.0\n");
+        assert!(read("{}", "app.json", None).is_err());
+        Ok(())
+    }
+}
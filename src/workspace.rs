@@ -0,0 +1,417 @@
+//! Bumping the shared version of a Cargo workspace
+
+use crate::encoding::{strip_bom, Encoding};
+use crate::{
+    bump_version_toml, selector, split_range_operator, BumpOptions, Change, Document, VersionBump,
+};
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use toml_edit::{DocumentMut, Item, TableLike};
+
+/// The selector of the shared version in the root manifest
+pub const VERSION_SELECTOR: &str = "workspace.package.version";
+
+/// Tables holding dependencies in a manifest
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// A version changed in one of the workspace's manifests
+#[derive(Debug)]
+pub struct Edit {
+    pub manifest: PathBuf,
+    pub selector: String,
+    pub change: Change,
+}
+
+/// Bump `workspace.package.version` in the root manifest. With `path_deps`,
+/// the version requirements of path dependencies on members that inherit the
+/// workspace version are moved to the new version too, keeping their operator.
+/// Nothing is written unless `write` is set and every manifest can be updated.
+pub fn bump(
+    root: &Path,
+    level: &VersionBump,
+    opts: &BumpOptions,
+    path_deps: bool,
+    write: bool,
+) -> Result<Vec<Edit>> {
+    let mut root_doc = read_manifest(root)?;
+    if root_doc.get("workspace").is_none() {
+        anyhow::bail!("No [workspace] table in {}", root.display());
+    }
+    let change = bump_version_toml(&mut root_doc, VERSION_SELECTOR, level, opts)?;
+    let new_version = change.new.clone();
+    let mut edits = vec![Edit {
+        manifest: root.to_path_buf(),
+        selector: VERSION_SELECTOR.to_string(),
+        change,
+    }];
+    let mut manifests = vec![(root.to_path_buf(), root_doc)];
+
+    if path_deps {
+        for member in members(root, &manifests[0].1)? {
+            let doc = read_manifest(&member)?;
+            manifests.push((member, doc));
+        }
+        let inheriting: Vec<PathBuf> = manifests
+            .iter()
+            .filter(|(_, doc)| inherits_version(doc))
+            .map(|(manifest, _)| manifest_dir(manifest).canonicalize())
+            .collect::<Result<_, _>>()?;
+
+        for (manifest, doc) in &mut manifests {
+            let dir = manifest_dir(manifest);
+            let update = |table: &mut dyn TableLike, prefix: &[&str]| {
+                update_path_deps(table, prefix, &dir, &inheriting, &new_version)
+                    .into_iter()
+                    .map(|(selector, change)| Edit {
+                        manifest: manifest.clone(),
+                        selector,
+                        change,
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let mut found = Vec::new();
+            for name in DEPENDENCY_TABLES {
+                if let Some(table) = doc.get_mut(name).and_then(Item::as_table_like_mut) {
+                    found.extend(update(table, &[name]));
+                }
+            }
+            if let Some(targets) = doc.get_mut("target").and_then(Item::as_table_like_mut) {
+                for (cfg, target) in targets.iter_mut() {
+                    let Some(target) = target.as_table_like_mut() else {
+                        continue;
+                    };
+                    for name in DEPENDENCY_TABLES {
+                        if let Some(table) = target.get_mut(name).and_then(Item::as_table_like_mut)
+                        {
+                            found.extend(update(table, &["target", &cfg, name]));
+                        }
+                    }
+                }
+            }
+            if let Some(table) = doc
+                .get_mut("workspace")
+                .and_then(|workspace| workspace.get_mut("dependencies"))
+                .and_then(Item::as_table_like_mut)
+            {
+                found.extend(update(table, &["workspace", "dependencies"]));
+            }
+            edits.extend(found);
+        }
+    }
+
+    if write {
+        for (manifest, doc) in &manifests {
+            if manifest == root || edits.iter().any(|edit| &edit.manifest == manifest) {
+                // toml_edit writes LF without a BOM, so match the file as read
+                let encoding = Encoding::detect(&fs::read_to_string(manifest)?);
+                crate::write_file(manifest, encoding.apply(doc.to_string()))?;
+            }
+        }
+    }
+    Ok(edits)
+}
+
+fn read_manifest(path: &Path) -> Result<DocumentMut> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    strip_bom(&content)
+        .parse()
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn manifest_dir(manifest: &Path) -> PathBuf {
+    match manifest.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Manifests of the crates matched by `workspace.members`, minus
+/// `workspace.exclude`
+fn members(root: &Path, doc: &DocumentMut) -> Result<Vec<PathBuf>> {
+    let dir = manifest_dir(root);
+    let strings = |key: &str| -> Vec<String> {
+        doc.get("workspace")
+            .and_then(|workspace| workspace.get(key))
+            .and_then(Item::as_array)
+            .map(|array| {
+                array
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let exclude: Vec<PathBuf> = strings("exclude")
+        .iter()
+        .map(|path| dir.join(path))
+        .collect();
+
+    let mut manifests = Vec::new();
+    for pattern in strings("members") {
+        let pattern = dir.join(&pattern);
+        for entry in glob::glob(&pattern.to_string_lossy())? {
+            let member = entry?;
+            let manifest = member.join("Cargo.toml");
+            if manifest.is_file() && !exclude.contains(&member) {
+                manifests.push(manifest);
+            }
+        }
+    }
+    manifests.sort();
+    manifests.dedup();
+    Ok(manifests)
+}
+
+/// Whether the manifest's package takes its version from the workspace
+fn inherits_version(doc: &DocumentMut) -> bool {
+    doc.get("package")
+        .and_then(|package| package.get("version"))
+        .and_then(|version| version.get("workspace"))
+        .and_then(Item::as_bool)
+        .unwrap_or(false)
+}
+
+/// Set the version requirement of each dependency in `table` whose path
+/// leads to one of `inheriting`, returning the selector and change of each
+fn update_path_deps(
+    table: &mut dyn TableLike,
+    prefix: &[&str],
+    dir: &Path,
+    inheriting: &[PathBuf],
+    version: &str,
+) -> Vec<(String, Change)> {
+    let mut changes = Vec::new();
+    for (name, dep) in table.iter_mut() {
+        let Some(dep) = dep.as_table_like_mut() else {
+            continue;
+        };
+        let target = dep
+            .get("path")
+            .and_then(Item::as_str)
+            .and_then(|path| dir.join(path).canonicalize().ok());
+        if !target.is_some_and(|target| inheriting.contains(&target)) {
+            continue;
+        }
+        let Some(value) = dep.get_mut("version").and_then(Item::as_value_mut) else {
+            continue;
+        };
+        if let Some(change) = set_requirement(value, version) {
+            let mut parts = prefix.to_vec();
+            parts.extend([name.get(), "version"]);
+            changes.push((selector::join(&parts), change));
+        }
+    }
+    changes
+}
+
+/// Move a version requirement to `version`, keeping its operator and
+/// decoration; None if it isn't a string or is already there
+fn set_requirement(value: &mut toml_edit::Value, version: &str) -> Option<Change> {
+    let old = value.as_str()?.to_string();
+    let (operator, _) = split_range_operator(&old);
+    let new = format!("{}{}", operator, version);
+    if new == old {
+        return None;
+    }
+    let decor = value.decor().clone();
+    *value = new.as_str().into();
+    *value.decor_mut() = decor;
+    Some(Change { old, new })
+}
+
+/// The root manifest of the workspace `manifest` belongs to: the nearest
+/// Cargo.toml with a `[workspace]` table, starting from `manifest` itself
+pub fn find_root(manifest: &Path) -> Result<PathBuf> {
+    if manifest.file_name() != Some("Cargo.toml".as_ref()) {
+        anyhow::bail!(
+            "--workspace-deps only applies to a Cargo.toml, not {}",
+            manifest.display()
+        );
+    }
+    let manifest = std::path::absolute(manifest)?;
+    for dir in manifest.ancestors().skip(1) {
+        let candidate = dir.join("Cargo.toml");
+        if candidate.is_file() && read_manifest(&candidate)?.get("workspace").is_some() {
+            // Relative to the working directory where it can be, for display
+            let cwd = std::env::current_dir()?;
+            return Ok(candidate
+                .strip_prefix(&cwd)
+                .map(Path::to_path_buf)
+                .unwrap_or(candidate));
+        }
+    }
+    anyhow::bail!("No workspace found above {}", manifest.display())
+}
+
+/// Move the `[workspace.dependencies]` entries for the package `name`, by
+/// key or by `package = "name"`, to `version`, keeping their operator.
+/// Returns the selector and change of each.
+pub fn update_dependency(doc: &mut Document, name: &str, version: &str) -> Vec<(String, Change)> {
+    let Document::Toml(doc, _) = doc else {
+        return Vec::new();
+    };
+    let Some(table) = doc
+        .get_mut("workspace")
+        .and_then(|workspace| workspace.get_mut("dependencies"))
+        .and_then(Item::as_table_like_mut)
+    else {
+        return Vec::new();
+    };
+    let mut changes = Vec::new();
+    for (key, dep) in table.iter_mut() {
+        let (value, parts) = match dep.as_table_like_mut() {
+            Some(dep) => {
+                let package = dep.get("package").and_then(Item::as_str);
+                if package.unwrap_or(key.get()) != name {
+                    continue;
+                }
+                let Some(value) = dep.get_mut("version").and_then(Item::as_value_mut) else {
+                    continue;
+                };
+                (
+                    value,
+                    vec!["workspace", "dependencies", key.get(), "version"],
+                )
+            }
+            None if key.get() == name => match dep.as_value_mut() {
+                Some(value) => (value, vec!["workspace", "dependencies", key.get()]),
+                None => continue,
+            },
+            None => continue,
+        };
+        if let Some(change) = set_requirement(value, version) {
+            changes.push((selector::join(&parts), change));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_bump() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("Cargo.toml");
+        fs::write(
+            &root,
+            r#"[workspace]
+members = ["crates/*"]
+exclude = ["crates/skip"]
+
+[workspace.package]
+version = "1.2.3" # shared
+
+[workspace.dependencies]
+core = { path = "crates/core", version = "=1.2.3" }
+"#,
+        )?;
+        let manifests = [
+            ("core", "[package]\nname = \"core\"\nversion.workspace = true\n"),
+            (
+                "cli",
+                "[package]\nname = \"cli\"\nversion = { workspace = true }\n\n[dependencies]\ncore = { path = \"../core\", version = \"1.2.3\" }\nother = { path = \"../other\", version = \"0.1.0\" }\n\n[target.'cfg(unix)'.dev-dependencies]\ncore = { path = \"../core\", version = \"^1.2\" }\n",
+            ),
+            ("other", "[package]\nname = \"other\"\nversion = \"0.1.0\"\n"),
+            ("skip", "[package]\nname = \"skip\"\nversion = \"0.0.1\"\n"),
+        ];
+        for (name, content) in manifests {
+            fs::create_dir_all(dir.path().join("crates").join(name))?;
+            fs::write(
+                dir.path().join("crates").join(name).join("Cargo.toml"),
+                content,
+            )?;
+        }
+
+        let opts = BumpOptions::default();
+        let preview = bump(&root, &VersionBump::Minor, &opts, false, true)?;
+        assert_eq!(preview.len(), 1);
+        assert!(fs::read_to_string(&root)?.contains("version = \"1.3.0\" # shared"));
+
+        let edits = bump(&root, &VersionBump::Minor, &opts, true, true)?;
+        let found: Vec<_> = edits
+            .iter()
+            .map(|edit| {
+                let manifest = edit.manifest.strip_prefix(dir.path()).unwrap();
+                (
+                    manifest.to_str().unwrap(),
+                    edit.selector.as_str(),
+                    edit.change.new.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("Cargo.toml", "workspace.package.version", "1.4.0"),
+                (
+                    "Cargo.toml",
+                    "workspace.dependencies.core.version",
+                    "=1.4.0"
+                ),
+                (
+                    "crates/cli/Cargo.toml",
+                    "dependencies.core.version",
+                    "1.4.0"
+                ),
+                (
+                    "crates/cli/Cargo.toml",
+                    "target.cfg(unix).dev-dependencies.core.version",
+                    "^1.4.0"
+                ),
+            ]
+        );
+        let cli = fs::read_to_string(dir.path().join("crates/cli/Cargo.toml"))?;
+        assert!(cli.contains("other = { path = \"../other\", version = \"0.1.0\" }"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_dependency() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("Cargo.toml");
+        let content = r#"[workspace]
+members = ["crates/*"]
+
+[workspace.dependencies]
+core = "0.4.0"
+renamed = { package = "core", path = "crates/core", version = "~0.4" } # pinned
+other = { path = "crates/other", version = "0.4.0" }
+"#;
+        fs::write(&root, content)?;
+        let member = dir.path().join("crates/core/Cargo.toml");
+        fs::create_dir_all(member.parent().unwrap())?;
+        fs::write(&member, "[package]\nname = \"core\"\nversion = \"0.4.0\"\n")?;
+        assert_eq!(find_root(&member)?, std::path::absolute(&root)?);
+        assert!(find_root(&dir.path().join("crates/core/package.json")).is_err());
+
+        let mut doc = Document::parse(content, "toml")?;
+        let changes: Vec<_> = update_dependency(&mut doc, "core", "0.5.0")
+            .into_iter()
+            .map(|(selector, change)| (selector, change.new))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (
+                    "workspace.dependencies.core".to_string(),
+                    "0.5.0".to_string()
+                ),
+                (
+                    "workspace.dependencies.renamed.version".to_string(),
+                    "~0.5.0".to_string()
+                ),
+            ]
+        );
+        let rendered = doc.render()?;
+        assert!(rendered.contains(r#"version = "~0.5.0" } # pinned"#));
+        assert!(rendered.contains(r#"other = { path = "crates/other", version = "0.4.0" }"#));
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+//! Locating element text in XML documents such as pom.xml and .csproj files
+
+use std::ops::Range;
+
+/// Byte range of the trimmed text of the first element at `path`, where the
+/// segments name elements by their local name starting from the root. A
+/// numeric segment picks among same-named siblings by position, so
+/// `project.dependencies.dependency.1.version` (or `dependency[1]`) is the
+/// version of the second dependency.
+pub fn locate(content: &str, path: &[String]) -> Option<Range<usize>> {
+    let mut steps: Vec<(&str, Option<usize>)> = Vec::new();
+    for part in path {
+        match (crate::selector::index(part), steps.last_mut()) {
+            (Some(index), Some((_, nth @ None))) => *nth = Some(index),
+            _ => steps.push((part, None)),
+        }
+    }
+    // Levels of `steps` matched by the open elements, and how many elements
+    // matching each step the matched parent has held so far
+    let mut matched = 0;
+    let mut seen = vec![0; steps.len()];
+    let pos = find_element(content, |stack, _| {
+        let level = stack.len() - 1;
+        matched = matched.min(level);
+        let Some((name, nth)) = steps.get(level) else {
+            return false;
+        };
+        if level != matched || stack[level] != *name {
+            return false;
+        }
+        seen[level] += 1;
+        if nth.is_some_and(|nth| nth + 1 != seen[level]) {
+            return false;
+        }
+        matched = level + 1;
+        if let Some(next) = seen.get_mut(matched) {
+            *next = 0;
+        }
+        matched == steps.len()
+    })?;
+    text(content, pos)
+}
+
+/// Offset just past the start tag of the first element for which `found`
+/// returns true, given the local names of the open elements from the root
+/// and that same offset; empty elements are passed over
+pub(crate) fn find_element<'a>(
+    content: &'a str,
+    mut found: impl FnMut(&[&'a str], usize) -> bool,
+) -> Option<usize> {
+    let mut stack: Vec<&str> = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = content[pos..].find('<') {
+        let start = pos + offset;
+        let rest = &content[start..];
+        let skip = [
+            ("<!--", "-->"),
+            ("<![CDATA[", "]]>"),
+            ("<?", "?>"),
+            ("<!", ">"),
+        ]
+        .into_iter()
+        .find(|(open, _)| rest.starts_with(open));
+        if let Some((_, close)) = skip {
+            pos = start + rest.find(close)? + close.len();
+            continue;
+        }
+
+        let end = start + tag_end(rest)?;
+        let tag = &content[start + 1..end];
+        pos = end + 1;
+        if tag.starts_with('/') {
+            stack.pop();
+            continue;
+        }
+        if tag.ends_with('/') {
+            continue;
+        }
+        let name = tag.split(char::is_whitespace).next()?;
+        stack.push(name.rsplit(':').next()?);
+        if found(&stack, pos) {
+            return Some(pos);
+        }
+    }
+    None
+}
+
+/// Byte range of the trimmed text of the element whose start tag ends at
+/// `pos`; only elements holding just text have a version to replace
+pub(crate) fn text(content: &str, pos: usize) -> Option<Range<usize>> {
+    let text_end = pos + content[pos..].find('<')?;
+    if !content[text_end..].starts_with("</") {
+        return None;
+    }
+    let text = &content[pos..text_end];
+    let lead = text.len() - text.trim_start().len();
+    Some(pos + lead..pos + lead + text.trim().len())
+}
+
+/// Offset of the `>` closing the tag at the start of `rest`, skipping quoted
+/// attribute values
+fn tag_end(rest: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in rest.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(selector: &str) -> Vec<String> {
+        selector.split('.').map(String::from).collect()
+    }
+
+    #[test]
+    fn test_locate_elements() {
+        let pom = r#"<?xml version="1.0" encoding="UTF-8"?>
+<project xmlns="http://maven.apache.org/POM/4.0.0">
+  <!-- <version>0.0.0</version> -->
+  <parent>
+    <version>9.9.9</version>
+  </parent>
+  <artifactId attr="a>b">demo</artifactId>
+  <version>
+    1.2.3
+  </version>
+  <build/>
+</project>
+"#;
+        let range = locate(pom, &path("project.version")).unwrap();
+        assert_eq!(&pom[range], "1.2.3");
+        let range = locate(pom, &path("project.parent.version")).unwrap();
+        assert_eq!(&pom[range], "9.9.9");
+        assert!(locate(pom, &path("project.build")).is_none());
+        assert!(locate(pom, &path("version")).is_none());
+
+        let csproj = r#"<Project Sdk="Microsoft.NET.Sdk">
+  <PropertyGroup>
+    <TargetFramework>net8.0</TargetFramework>
+  </PropertyGroup>
+  <PropertyGroup>
+    <Version>2.0.0</Version>
+  </PropertyGroup>
+</Project>"#;
+        let range = locate(csproj, &path("Project.PropertyGroup.Version")).unwrap();
+        assert_eq!(&csproj[range], "2.0.0");
+    }
+
+    #[test]
+    fn test_locate_by_index() {
+        let pom = r#"<project>
+  <dependencies>
+    <dependency><artifactId>a</artifactId><version>1.0.0</version></dependency>
+    <dependency><artifactId>b</artifactId></dependency>
+    <dependency><artifactId>c</artifactId><version>3.0.0</version></dependency>
+  </dependencies>
+  <dependencyManagement>
+    <dependencies>
+      <dependency><artifactId>d</artifactId><version>4.0.0</version></dependency>
+    </dependencies>
+  </dependencyManagement>
+</project>"#;
+        let value = |selector: &str| {
+            let path = crate::selector::parse(selector).unwrap();
+            locate(pom, &path).map(|range| &pom[range])
+        };
+        assert_eq!(
+            value("project.dependencies.dependency.version"),
+            Some("1.0.0")
+        );
+        assert_eq!(
+            value("project.dependencies.dependency[0].version"),
+            Some("1.0.0")
+        );
+        assert_eq!(value("project.dependencies.dependency[1].version"), None);
+        assert_eq!(
+            value("project.dependencies.dependency[2].artifactId"),
+            Some("c")
+        );
+        assert_eq!(
+            value("project.dependencies.dependency[2].version"),
+            Some("3.0.0")
+        );
+        assert_eq!(value("project.dependencies.dependency[3].version"), None);
+        assert_eq!(
+            value("project.dependencyManagement.dependencies.dependency[0].version"),
+            Some("4.0.0")
+        );
+    }
+}
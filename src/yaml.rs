@@ -0,0 +1,354 @@
+//! Format-preserving YAML output: edited scalars and keys are replaced in the
+//! original source, so comments, anchors, ordering and quoting are kept
+
+use crate::selector;
+use serde_yaml::Value as YamlValue;
+use std::ops::Range;
+
+/// A replacement in the original source
+struct Edit {
+    range: Range<usize>,
+    text: String,
+}
+
+/// Render `value`, which was parsed from `source` and then edited. Only
+/// changed string scalars and mapping keys are patched in place; anything
+/// else falls back to re-serializing the whole document.
+pub fn render(source: &str, value: &YamlValue) -> serde_yaml::Result<String> {
+    match patch(source, value) {
+        Some(patched) => Ok(patched),
+        None => serde_yaml::to_string(value),
+    }
+}
+
+fn patch(source: &str, value: &YamlValue) -> Option<String> {
+    let original: YamlValue = serde_yaml::from_str(source).ok()?;
+    let mut edits = Vec::new();
+    diff(source, &original, value, &mut Vec::new(), &mut edits)?;
+
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+    let mut patched = source.to_string();
+    for edit in edits {
+        patched.replace_range(edit.range, &edit.text);
+    }
+
+    // Anything the line-level edits got wrong is caught here
+    let reparsed: YamlValue = serde_yaml::from_str(&patched).ok()?;
+    (&reparsed == value).then_some(patched)
+}
+
+/// Collect the edits turning `old` into `new`, or None if they differ in a
+/// way that can't be patched in place
+fn diff(
+    source: &str,
+    old: &YamlValue,
+    new: &YamlValue,
+    path: &mut Vec<String>,
+    edits: &mut Vec<Edit>,
+) -> Option<()> {
+    if old == new {
+        return Some(());
+    }
+    match (old, new) {
+        (YamlValue::String(_), YamlValue::String(text)) => {
+            edits.push(Edit {
+                range: locate(source, path, Part::Value)?,
+                text: text.clone(),
+            });
+        }
+        (YamlValue::Mapping(old_map), YamlValue::Mapping(new_map))
+            if old_map.len() == new_map.len() =>
+        {
+            for ((old_key, old_value), (new_key, new_value)) in old_map.iter().zip(new_map) {
+                path.push(old_key.as_str()?.to_string());
+                if old_key != new_key {
+                    edits.push(Edit {
+                        range: locate(source, path, Part::Key)?,
+                        text: new_key.as_str()?.to_string(),
+                    });
+                }
+                diff(source, old_value, new_value, path, edits)?;
+                path.pop();
+            }
+        }
+        (YamlValue::Sequence(old_items), YamlValue::Sequence(new_items))
+            if old_items.len() == new_items.len() =>
+        {
+            for (index, (old_item, new_item)) in old_items.iter().zip(new_items).enumerate() {
+                path.push(index.to_string());
+                diff(source, old_item, new_item, path, edits)?;
+                path.pop();
+            }
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Part {
+    Key,
+    Value,
+}
+
+#[derive(Clone, Copy)]
+struct Line<'a> {
+    start: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+impl Line<'_> {
+    /// Lines that hold data, as opposed to blanks, comments and markers
+    fn is_content(&self) -> bool {
+        let rest = &self.text[self.indent..];
+        !(rest.is_empty() || rest.starts_with('#') || rest.starts_with("---"))
+    }
+
+    /// The column an item's content starts at, for a `- item` line of a
+    /// block sequence
+    fn item_content(&self) -> Option<usize> {
+        let rest = self.text[self.indent..].strip_prefix('-')?;
+        if !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+            return None;
+        }
+        Some(self.text.len() - rest.trim_start_matches([' ', '\t']).len())
+    }
+}
+
+/// Byte range of the key or the unquoted scalar value at `path` in block
+/// mappings and sequences, or None if it's written in a style this doesn't
+/// handle
+fn locate(source: &str, path: &[String], part: Part) -> Option<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for raw in source.split_inclusive('\n') {
+        let text = raw.trim_end_matches(['\n', '\r']);
+        let indent = text.len() - text.trim_start_matches(' ').len();
+        lines.push(Line {
+            start,
+            indent,
+            text,
+        });
+        start += raw.len();
+    }
+    let mut region: Vec<Line> = lines.into_iter().filter(Line::is_content).collect();
+
+    let mut parent_indent: Option<usize> = None;
+    for (depth, segment) in path.iter().enumerate() {
+        let first = *region.first()?;
+        let indent = first.indent;
+        let is_sequence = first.item_content().is_some();
+        // A sequence may sit at the same indent as the key holding it
+        if parent_indent.is_some_and(|parent| indent < parent || indent == parent && !is_sequence) {
+            return None;
+        }
+        let end = region
+            .iter()
+            .position(|line| line.indent < indent)
+            .unwrap_or(region.len());
+        region.truncate(end);
+
+        if is_sequence {
+            let items: Vec<usize> = (0..region.len())
+                .filter(|&i| region[i].indent == indent)
+                .collect();
+            let position = selector::index(segment)?;
+            let &start = items.get(position)?;
+            let line = region[start];
+            let content = line.item_content()?;
+            if depth + 1 == path.len() {
+                if part == Part::Key {
+                    return None;
+                }
+                let range = scalar(line.text, content..line.text.len())?;
+                return Some(line.start + range.start..line.start + range.end);
+            }
+            let end = items.get(position + 1).copied().unwrap_or(region.len());
+            let mut item: Vec<Line> = region[start + 1..end].to_vec();
+            // The item's first line, after the dash, is read as though the
+            // dash were indentation
+            let rest = &line.text[content..];
+            if !(rest.is_empty() || rest.starts_with('#')) {
+                item.insert(
+                    0,
+                    Line {
+                        indent: content,
+                        ..line
+                    },
+                );
+            }
+            parent_indent = Some(indent);
+            region = item;
+            continue;
+        }
+
+        let (index, key, value) = region.iter().enumerate().find_map(|(index, line)| {
+            if line.indent != indent {
+                return None;
+            }
+            let (key, value) = split_key(line.text, line.indent)?;
+            (line.text[key.clone()] == **segment).then_some((index, key, value))
+        })?;
+        let line = region[index];
+
+        if depth + 1 == path.len() {
+            let range = match part {
+                Part::Key => key,
+                Part::Value => scalar(line.text, value)?,
+            };
+            return Some(line.start + range.start..line.start + range.end);
+        }
+        if !opens_block(&line.text[value]) {
+            return None;
+        }
+        parent_indent = Some(indent);
+        region.drain(..=index);
+        let end = region
+            .iter()
+            .position(|line| {
+                line.indent < indent || line.indent == indent && line.item_content().is_none()
+            })
+            .unwrap_or(region.len());
+        region.truncate(end);
+    }
+    None
+}
+
+/// Split a `key: value` line into the key (inside any quotes) and the rest
+/// after the colon
+fn split_key(text: &str, indent: usize) -> Option<(Range<usize>, Range<usize>)> {
+    let rest = &text[indent..];
+    let (key, after) = match rest.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let close = rest[1..].find(quote)? + 1;
+            (indent + 1..indent + close, indent + close + 1)
+        }
+        '-' | '?' | '[' | '{' | '&' | '*' | '!' | '|' | '>' => return None,
+        _ => {
+            let colon = rest
+                .match_indices(':')
+                .map(|(i, _)| i)
+                .find(|&i| rest[i + 1..].is_empty() || rest[i + 1..].starts_with([' ', '\t']))?;
+            (indent..indent + colon, indent + colon)
+        }
+    };
+    let after = text[after..].strip_prefix(':').map(|_| after + 1)?;
+    Some((key, after..text.len()))
+}
+
+/// Whether the rest of a `key:` line leaves its value to the following
+/// lines, allowing for an anchor and a comment
+fn opens_block(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    let rest = match rest.strip_prefix('&') {
+        Some(anchor) => anchor
+            .trim_start_matches(|c: char| !c.is_whitespace())
+            .trim_start(),
+        None => rest,
+    };
+    rest.is_empty() || rest.starts_with('#')
+}
+
+/// Range of the scalar in `value`, inside any quotes
+fn scalar(text: &str, value: Range<usize>) -> Option<Range<usize>> {
+    let rest = &text[value.clone()];
+    let offset = value.start + rest.len() - rest.trim_start().len();
+    let rest = rest.trim_start();
+    match rest.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let close = rest[1..].find(quote)? + 1;
+            let inner = &rest[1..close];
+            if inner.contains('\\') || rest[close + 1..].starts_with(quote) {
+                return None;
+            }
+            Some(offset + 1..offset + close)
+        }
+        '|' | '>' | '&' | '*' | '!' | '[' | '{' | '#' => None,
+        _ => {
+            let end = rest.find(" #").unwrap_or(rest.len());
+            Some(offset..offset + rest[..end].trim_end().len())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_preserves_formatting() -> serde_yaml::Result<()> {
+        let source = r#"# Release settings
+name: demo   # the app
+app:
+  # bumped by svbump
+  version: "1.2.3"  # keep quoted
+  image: &image   # shared
+    tag: '1.2.3'
+versions:
+  1.2.3:
+    date: 2024-01-01
+"#;
+        let mut value: YamlValue = serde_yaml::from_str(source)?;
+        value["app"]["version"] = YamlValue::String("1.3.0".into());
+        value["app"]["image"]["tag"] = YamlValue::String("1.3.0".into());
+        let versions = value["versions"].as_mapping_mut().unwrap();
+        let date = versions.remove("1.2.3").unwrap();
+        versions.insert("1.3.0".into(), date);
+
+        assert_eq!(
+            render(source, &value)?,
+            source
+                .replace("\"1.2.3\"", "\"1.3.0\"")
+                .replace("'1.2.3'", "'1.3.0'")
+                .replace("  1.2.3:", "  1.3.0:")
+        );
+
+        // An alias would pick up the edit too, so the document is re-serialized
+        let source = "base: &base\n  version: 1.2.3\ncopy: *base\n";
+        let mut value: YamlValue = serde_yaml::from_str(source)?;
+        value["base"]["version"] = YamlValue::String("1.3.0".into());
+        assert_eq!(
+            render(source, &value)?,
+            "base:\n  version: 1.3.0\ncopy:\n  version: 1.2.3\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_sequences() -> serde_yaml::Result<()> {
+        let source = r#"containers:
+- name: app   # compact, at the key's indent
+  image: app:1.2.3
+-
+  name: sidecar
+  image: "sidecar:1.2.3"
+versions:
+  - 1.2.3 # first
+  - - nested
+    - 1.2.3
+"#;
+        let mut value: YamlValue = serde_yaml::from_str(source)?;
+        value["containers"][0]["image"] = YamlValue::String("app:1.3.0".into());
+        value["containers"][1]["image"] = YamlValue::String("sidecar:1.3.0".into());
+        value["versions"][0] = YamlValue::String("1.3.0".into());
+        value["versions"][1][1] = YamlValue::String("1.3.0".into());
+        assert_eq!(render(source, &value)?, source.replace("1.2.3", "1.3.0"));
+
+        // Flow sequences aren't patched, so the document is re-serialized
+        let source = "tags: [1.2.3, latest] # pinned\n";
+        let mut value: YamlValue = serde_yaml::from_str(source)?;
+        value["tags"][0] = YamlValue::String("1.3.0".into());
+        assert_eq!(render(source, &value)?, "tags:\n- 1.3.0\n- latest\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_nested_keys() -> serde_yaml::Result<()> {
+        let source = "a:\n  b:\n    version: 1.0.0\n  version: 2.0.0\nversion: 3.0.0\n";
+        let mut value: YamlValue = serde_yaml::from_str(source)?;
+        value["a"]["version"] = YamlValue::String("2.1.0".into());
+        assert_eq!(render(source, &value)?, source.replace("2.0.0", "2.1.0"));
+        Ok(())
+    }
+}